@@ -7,40 +7,65 @@ pub(crate) struct ByteRange {
     pub(crate) value: [usize; 4],
 }
 
+fn find(buffer: &[u8], from: usize, needle: &[u8]) -> Option<usize> {
+    buffer
+        .get(from..)?
+        .windows(needle.len())
+        .position(|window| window == needle)
+        .map(|pos| pos + from)
+}
+
 impl ByteRange {
-    pub(crate) fn from_bytes(buffer: &[u8]) -> Result<Self> {
-        let byte_range_key = b"/ByteRange";
-        let byte_range_start = buffer
-            .windows(byte_range_key.len())
-            .position(|window| window == byte_range_key)
-            .expect("ByteRange not found");
-        let byte_range_end = buffer[byte_range_start..]
-            .windows(b"]".len())
-            .position(|window| window == b"]")
+    /// Locate the placeholders written at or after `offset`, the start of the new
+    /// incremental section, so the signatures of earlier revisions are never touched.
+    pub(crate) fn from_bytes(buffer: &[u8], offset: usize) -> Result<Self> {
+        let byte_range_start = find(buffer, offset, b"/ByteRange")
+            .ok_or_else(|| anyhow::anyhow!("ByteRange not found"))?;
+        let byte_range_end = find(buffer, byte_range_start, b"]")
             .ok_or_else(|| anyhow::anyhow!("ByteRange end not found"))?
-            + byte_range_start
             + 1;
 
         // only search prefix, position will return first match
         let contents_placeholder = hex::encode([0; 50]).into_bytes();
-        let contents_start = buffer
-            .windows(contents_placeholder.len())
-            .position(|w| w == contents_placeholder)
+        let contents_start = find(buffer, offset, &contents_placeholder)
             .ok_or_else(|| anyhow::anyhow!("Cannot find Contents placeholder"))?
             - 1;
-        let contents_end = buffer[contents_start..]
-            .windows(b">".len())
-            .position(|window| window == b">")
+        let contents_end = find(buffer, contents_start, b">")
             .ok_or_else(|| anyhow::anyhow!("Contents end not found"))?
-            + contents_start
             + 1;
 
-        let value = [
-            0,
-            contents_start,
-            contents_end,
-            buffer.len() - contents_end - 1,
-        ];
+        let value = [0, contents_start, contents_end, buffer.len() - contents_end];
+        Ok(ByteRange {
+            byte_range_start,
+            byte_range_end,
+            value,
+        })
+    }
+
+    /// Read back the ByteRange written by [`ByteRange::get_bytes`] in the last revision.
+    pub(crate) fn from_patched(buffer: &[u8]) -> Result<Self> {
+        let needle = b"/ByteRange [";
+        let byte_range_start = buffer
+            .windows(needle.len())
+            .rposition(|window| window == needle)
+            .ok_or_else(|| anyhow::anyhow!("ByteRange not found"))?;
+        let byte_range_end = find(buffer, byte_range_start, b"]")
+            .ok_or_else(|| anyhow::anyhow!("ByteRange end not found"))?
+            + 1;
+        let numbers =
+            std::str::from_utf8(&buffer[byte_range_start + needle.len()..byte_range_end - 1])?
+                .split_whitespace()
+                .map(str::parse)
+                .collect::<Result<Vec<usize>, _>>()?;
+        let value: [usize; 4] = numbers
+            .try_into()
+            .map_err(|_| anyhow::anyhow!("Invalid ByteRange"))?;
+        if value[0] != 0
+            || value[1] >= value[2]
+            || value[2].checked_add(value[3]) != Some(buffer.len())
+        {
+            return Err(anyhow::anyhow!("ByteRange does not match the document"));
+        }
         Ok(ByteRange {
             byte_range_start,
             byte_range_end,
@@ -0,0 +1,85 @@
+//! Alg
+//! Algorithm identifiers of the CMS digest and signature algorithms.
+use der::{Sequence, asn1::Any};
+use x509_cert::spki::AlgorithmIdentifierOwned;
+
+use super::oid;
+use crate::signer::{DigestAlgorithm, SignatureScheme};
+
+/// ```text
+/// RSASSA-PSS-params ::= SEQUENCE {
+///     hashAlgorithm      [0] HashAlgorithm DEFAULT sha1,
+///     maskGenAlgorithm   [1] MaskGenAlgorithm DEFAULT mgf1SHA1,
+///     saltLength         [2] INTEGER DEFAULT 20,
+///     trailerField       [3] TrailerField DEFAULT trailerFieldBC }
+/// ```
+///
+/// A `None` field takes the default value.
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+pub(crate) struct RsaPssParams {
+    #[asn1(context_specific = "0", tag_mode = "EXPLICIT", optional = "true")]
+    pub(crate) hash_algorithm: Option<AlgorithmIdentifierOwned>,
+    #[asn1(context_specific = "1", tag_mode = "EXPLICIT", optional = "true")]
+    pub(crate) mask_gen_algorithm: Option<AlgorithmIdentifierOwned>,
+    #[asn1(context_specific = "2", tag_mode = "EXPLICIT", optional = "true")]
+    pub(crate) salt_length: Option<u32>,
+    #[asn1(context_specific = "3", tag_mode = "EXPLICIT", optional = "true")]
+    pub(crate) trailer_field: Option<u32>,
+}
+
+pub(crate) fn digest_algorithm(digest: DigestAlgorithm) -> AlgorithmIdentifierOwned {
+    let oid = match digest {
+        DigestAlgorithm::Sha256 => oid::ID_SHA256,
+        DigestAlgorithm::Sha384 => oid::ID_SHA384,
+        DigestAlgorithm::Sha512 => oid::ID_SHA512,
+    };
+    AlgorithmIdentifierOwned {
+        oid,
+        parameters: None,
+    }
+}
+
+pub(crate) fn signature_algorithm(
+    scheme: SignatureScheme,
+    digest: DigestAlgorithm,
+) -> der::Result<AlgorithmIdentifierOwned> {
+    let algorithm = match scheme {
+        SignatureScheme::RsaPkcs1v15 => AlgorithmIdentifierOwned {
+            oid: match digest {
+                DigestAlgorithm::Sha256 => oid::SHA256_WITH_RSA_ENCRYPTION,
+                DigestAlgorithm::Sha384 => oid::SHA384_WITH_RSA_ENCRYPTION,
+                DigestAlgorithm::Sha512 => oid::SHA512_WITH_RSA_ENCRYPTION,
+            },
+            parameters: Some(Any::null()),
+        },
+        SignatureScheme::RsaPss => {
+            let params = RsaPssParams {
+                hash_algorithm: Some(digest_algorithm(digest)),
+                mask_gen_algorithm: Some(AlgorithmIdentifierOwned {
+                    oid: oid::ID_MGF1,
+                    parameters: Some(Any::encode_from(&digest_algorithm(digest))?),
+                }),
+                salt_length: Some(digest.len() as u32),
+                trailer_field: None,
+            };
+            AlgorithmIdentifierOwned {
+                oid: oid::ID_RSASSA_PSS,
+                parameters: Some(Any::encode_from(&params)?),
+            }
+        }
+        SignatureScheme::Ecdsa => AlgorithmIdentifierOwned {
+            oid: match digest {
+                DigestAlgorithm::Sha256 => oid::ECDSA_WITH_SHA256,
+                DigestAlgorithm::Sha384 => oid::ECDSA_WITH_SHA384,
+                DigestAlgorithm::Sha512 => oid::ECDSA_WITH_SHA512,
+            },
+            parameters: None,
+        },
+        // RFC 8419, the digest is implied by the signed attributes
+        SignatureScheme::Ed25519 => AlgorithmIdentifierOwned {
+            oid: oid::ID_ED25519,
+            parameters: None,
+        },
+    };
+    Ok(algorithm)
+}
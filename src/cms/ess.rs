@@ -0,0 +1,75 @@
+//! ESS
+//! Enhanced Security Services attributes (RFC 2634, RFC 5035) required by CAdES.
+use der::{
+    Sequence,
+    asn1::{Any, OctetString},
+};
+use x509_cert::{
+    Certificate, ext::pkix::name::GeneralName, serial_number::SerialNumber,
+    spki::AlgorithmIdentifierOwned,
+};
+
+/// ```text
+/// SigningCertificateV2 ::= SEQUENCE {
+///     certs        SEQUENCE OF ESSCertIDv2,
+///     policies     SEQUENCE OF PolicyInformation OPTIONAL }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+pub(crate) struct SigningCertificateV2 {
+    pub(crate) certs: Vec<EssCertIdV2>,
+    #[asn1(optional = "true")]
+    pub(crate) policies: Option<Vec<Any>>,
+}
+
+/// ```text
+/// ESSCertIDv2 ::= SEQUENCE {
+///     hashAlgorithm   AlgorithmIdentifier DEFAULT {algorithm id-sha256},
+///     certHash        Hash,
+///     issuerSerial    IssuerSerial OPTIONAL }
+/// ```
+///
+/// `hash_algorithm` is `None` for SHA-256, DER forbids encoding the default value.
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+pub(crate) struct EssCertIdV2 {
+    #[asn1(optional = "true")]
+    pub(crate) hash_algorithm: Option<AlgorithmIdentifierOwned>,
+    pub(crate) cert_hash: OctetString,
+    #[asn1(optional = "true")]
+    pub(crate) issuer_serial: Option<IssuerSerial>,
+}
+
+/// ```text
+/// IssuerSerial ::= SEQUENCE {
+///     issuer         GeneralNames,
+///     serialNumber   CertificateSerialNumber }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+pub(crate) struct IssuerSerial {
+    pub(crate) issuer: Vec<GeneralName>,
+    pub(crate) serial_number: SerialNumber,
+}
+
+impl SigningCertificateV2 {
+    /// Reference the signer certificate by its hash and issuer/serial, a `None`
+    /// `hash_algorithm` means SHA-256.
+    pub(crate) fn new(
+        cert: &Certificate,
+        hash_algorithm: Option<AlgorithmIdentifierOwned>,
+        cert_hash: Vec<u8>,
+    ) -> der::Result<Self> {
+        let cert_id = EssCertIdV2 {
+            hash_algorithm,
+            cert_hash: OctetString::new(cert_hash)?,
+            issuer_serial: Some(IssuerSerial {
+                issuer: vec![GeneralName::DirectoryName(
+                    cert.tbs_certificate.issuer.clone(),
+                )],
+                serial_number: cert.tbs_certificate.serial_number.clone(),
+            }),
+        };
+        Ok(SigningCertificateV2 {
+            certs: vec![cert_id],
+            policies: None,
+        })
+    }
+}
@@ -0,0 +1,262 @@
+//! Cms
+//! Assemble detached CMS SignedData structures for the CAdES based signature formats.
+use std::time::SystemTime;
+
+use anyhow::Result;
+use cms::{
+    cert::{CertificateChoices, IssuerAndSerialNumber},
+    content_info::{CmsVersion, ContentInfo},
+    revocation::RevocationInfoChoice,
+    signed_data::{
+        CertificateSet, EncapsulatedContentInfo, SignedAttributes, SignedData, SignerIdentifier,
+        SignerInfo, SignerInfos,
+    },
+};
+use der::{
+    Decode, Encode, SliceReader,
+    asn1::{Any, ObjectIdentifier, OctetString, SetOfVec, UtcTime},
+};
+use openssl::{hash::hash, x509::X509};
+use x509_cert::{Certificate, attr::Attribute};
+
+use self::ess::SigningCertificateV2;
+use crate::{
+    signer::{DigestAlgorithm, RawSigner},
+    tsa::TimestampAuthority,
+};
+
+pub(crate) mod alg;
+pub(crate) mod ess;
+pub(crate) mod ocsp;
+pub(crate) mod oid;
+pub(crate) mod tsp;
+pub(crate) mod verify;
+
+/// Builds a SignedData whose signed attributes follow PAdES B-B:
+/// content-type, message-digest and signing-certificate-v2, without signing-time
+/// unless asked for. The algorithms are those of the [`RawSigner`].
+pub(crate) struct CadesBuilder<'a> {
+    signer: &'a dyn RawSigner,
+    signing_time: bool,
+}
+
+impl<'a> CadesBuilder<'a> {
+    pub(crate) fn new(signer: &'a dyn RawSigner) -> Self {
+        CadesBuilder {
+            signer,
+            signing_time: false,
+        }
+    }
+
+    /// Add the signing-time attribute, PAdES forbids it but `adbe.pkcs7.detached`
+    /// signatures carry it.
+    pub(crate) fn with_signing_time(mut self) -> Self {
+        self.signing_time = true;
+        self
+    }
+
+    fn signed_attrs(
+        &self,
+        cert: &Certificate,
+        digest: DigestAlgorithm,
+        content_type: ObjectIdentifier,
+        data: &[u8],
+    ) -> Result<SignedAttributes> {
+        let message_digest = hash(digest.message_digest(), data)?;
+        let cert_hash = hash(digest.message_digest(), &cert.to_der()?)?;
+        let hash_algorithm =
+            (digest != DigestAlgorithm::Sha256).then(|| alg::digest_algorithm(digest));
+        let signing_certificate =
+            SigningCertificateV2::new(cert, hash_algorithm, cert_hash.to_vec())?;
+
+        let mut attrs = SetOfVec::new();
+        attrs.insert(attribute(
+            oid::ID_CONTENT_TYPE,
+            Any::encode_from(&content_type)?,
+        )?)?;
+        attrs.insert(attribute(
+            oid::ID_MESSAGE_DIGEST,
+            Any::encode_from(&OctetString::new(message_digest.to_vec())?)?,
+        )?)?;
+        attrs.insert(attribute(
+            oid::ID_AA_SIGNING_CERTIFICATE_V2,
+            Any::encode_from(&signing_certificate)?,
+        )?)?;
+        if self.signing_time {
+            let now = UtcTime::from_system_time(SystemTime::now())?;
+            attrs.insert(attribute(oid::ID_SIGNING_TIME, Any::encode_from(&now)?)?)?;
+        }
+        Ok(attrs)
+    }
+
+    /// Sign `data` and return the DER encoded ContentInfo.
+    pub(crate) fn sign_detached(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.build(oid::ID_DATA, data, false)
+    }
+
+    /// Sign `content` and embed it as the `eContent` of the returned ContentInfo.
+    pub(crate) fn sign_encapsulated(
+        &self,
+        content_type: ObjectIdentifier,
+        content: &[u8],
+    ) -> Result<Vec<u8>> {
+        self.build(content_type, content, true)
+    }
+
+    fn build(
+        &self,
+        content_type: ObjectIdentifier,
+        data: &[u8],
+        encapsulate: bool,
+    ) -> Result<Vec<u8>> {
+        let x509 = self.signer.certificate();
+        let digest = self.signer.digest_algorithm();
+        let scheme = self.signer.signature_scheme()?;
+        scheme.check(&x509, digest)?;
+        let cert = Certificate::from_der(&x509.to_der()?)?;
+        let signed_attrs = self.signed_attrs(&cert, digest, content_type, data)?;
+
+        // The signature covers the DER encoding of the attributes as an explicit SET OF
+        let signature = self.signer.sign_message(&signed_attrs.to_der()?)?;
+
+        let digest_alg = alg::digest_algorithm(digest);
+        let signer_info = SignerInfo {
+            version: CmsVersion::V1,
+            sid: SignerIdentifier::IssuerAndSerialNumber(IssuerAndSerialNumber {
+                issuer: cert.tbs_certificate.issuer.clone(),
+                serial_number: cert.tbs_certificate.serial_number.clone(),
+            }),
+            digest_alg: digest_alg.clone(),
+            signed_attrs: Some(signed_attrs),
+            signature_algorithm: alg::signature_algorithm(scheme, digest)?,
+            signature: OctetString::new(signature)?,
+            unsigned_attrs: None,
+        };
+
+        let mut certificates = SetOfVec::new();
+        certificates.insert(CertificateChoices::Certificate(cert))?;
+        for ca in self.signer.chain() {
+            let ca = Certificate::from_der(&ca.to_der()?)?;
+            certificates.insert(CertificateChoices::Certificate(ca))?;
+        }
+        let mut digest_algorithms = SetOfVec::new();
+        digest_algorithms.insert(digest_alg)?;
+        let mut signer_infos = SetOfVec::new();
+        signer_infos.insert(signer_info)?;
+        let econtent = match encapsulate {
+            true => Some(Any::encode_from(&OctetString::new(data)?)?),
+            false => None,
+        };
+
+        let mut signed_data = SignedData {
+            version: CmsVersion::V1,
+            digest_algorithms,
+            encap_content_info: EncapsulatedContentInfo {
+                econtent_type: content_type,
+                econtent,
+            },
+            certificates: Some(CertificateSet(certificates)),
+            crls: None,
+            signer_infos: SignerInfos(signer_infos),
+        };
+        signed_data.version = signed_data_version(&signed_data);
+        encode_signed_data(&signed_data)
+    }
+}
+
+/// The SignedData version required by RFC 5652 section 5.1, v3 as soon as the
+/// content is not id-data, such as the TSTInfo of a timestamp token.
+fn signed_data_version(signed_data: &SignedData) -> CmsVersion {
+    let other_certs = signed_data.certificates.as_ref().is_some_and(|certs| {
+        certs
+            .0
+            .iter()
+            .any(|cert| matches!(cert, CertificateChoices::Other(_)))
+    });
+    let other_crls = signed_data.crls.as_ref().is_some_and(|crls| {
+        crls.0
+            .iter()
+            .any(|crl| matches!(crl, RevocationInfoChoice::Other(_)))
+    });
+    let v3_signer = signed_data
+        .signer_infos
+        .0
+        .iter()
+        .any(|signer_info| signer_info.version == CmsVersion::V3);
+    if other_certs || other_crls {
+        CmsVersion::V5
+    } else if v3_signer || signed_data.encap_content_info.econtent_type != oid::ID_DATA {
+        CmsVersion::V3
+    } else {
+        CmsVersion::V1
+    }
+}
+
+/// Room for the SignedData structure, the signed attributes (signing-time and
+/// the S/MIME capabilities of OpenSSL included) and the algorithm identifiers,
+/// RSASSA-PSS parameters included.
+const SIGNED_DATA_OVERHEAD: usize = 1024;
+
+/// An upper bound of the DER length of a SignedData by the first of `certs`
+/// that embeds all of them, from their sizes and the key size of the signer
+/// without signing anything. `None` if there are no certificates.
+pub(crate) fn estimate_signed_data_len(certs: &[X509]) -> Result<Option<usize>> {
+    let Some(signer) = certs.first() else {
+        return Ok(None);
+    };
+    // The signature length of RSA and Ed25519 keys, the largest ECDSA-Sig-Value of EC keys
+    let signature = signer.public_key()?.size();
+    // The issuer and serial number identify the signer in the SignerInfo and in ESS
+    let signer_id = signer.issuer_name().to_der()?.len()
+        + signer.serial_number().to_bn()?.num_bytes() as usize
+        + 8;
+    let mut len = SIGNED_DATA_OVERHEAD + signature + 2 * signer_id;
+    for cert in certs {
+        len += cert.to_der()?.len();
+    }
+    Ok(Some(len))
+}
+
+/// Decode a ContentInfo holding SignedData, trailing bytes such as the zero
+/// padding of a `/Contents` placeholder are ignored.
+pub(crate) fn decode_signed_data(der: &[u8]) -> Result<SignedData> {
+    let content_info = ContentInfo::decode(&mut SliceReader::new(der)?)?;
+    if content_info.content_type != oid::ID_SIGNED_DATA {
+        return Err(anyhow::anyhow!("CMS content is not SignedData"));
+    }
+    Ok(content_info.content.decode_as()?)
+}
+
+pub(crate) fn encode_signed_data(signed_data: &SignedData) -> Result<Vec<u8>> {
+    let content_info = ContentInfo {
+        content_type: oid::ID_SIGNED_DATA,
+        content: Any::encode_from(signed_data)?,
+    };
+    Ok(content_info.to_der()?)
+}
+
+/// Timestamp the signature value of every SignerInfo and embed the token as the
+/// unsigned `id-aa-signatureTimeStampToken` attribute (PAdES B-T).
+pub(crate) fn add_signature_timestamp(der: &[u8], tsa: &dyn TimestampAuthority) -> Result<Vec<u8>> {
+    let mut signed_data = decode_signed_data(der)?;
+    let mut signer_infos = SetOfVec::new();
+    for mut signer_info in signed_data.signer_infos.0.into_vec() {
+        let token = tsa.timestamp(signer_info.signature.as_bytes())?;
+        let token = attribute(
+            oid::ID_AA_SIGNATURE_TIME_STAMP_TOKEN,
+            Any::from_der(&token)?,
+        )?;
+        let mut unsigned_attrs = signer_info.unsigned_attrs.take().unwrap_or_default();
+        unsigned_attrs.insert(token)?;
+        signer_info.unsigned_attrs = Some(unsigned_attrs);
+        signer_infos.insert(signer_info)?;
+    }
+    signed_data.signer_infos = SignerInfos(signer_infos);
+    encode_signed_data(&signed_data)
+}
+
+fn attribute(oid: ObjectIdentifier, value: Any) -> Result<Attribute> {
+    let mut values = SetOfVec::new();
+    values.insert(value)?;
+    Ok(Attribute { oid, values })
+}
@@ -0,0 +1,172 @@
+//! OCSP
+//! The parts of OCSP responses (RFC 6960) and of the Adobe revocation
+//! archival attribute that openssl does not expose.
+use der::{
+    Decode, Sequence, Tag, TagNumber, Tagged,
+    asn1::{Any, ObjectIdentifier, OctetString},
+};
+use x509_cert::{serial_number::SerialNumber, spki::AlgorithmIdentifierOwned};
+
+use super::oid;
+
+/// ```text
+/// RevocationInfoArchival ::= SEQUENCE {
+///     crl           [0] EXPLICIT SEQUENCE of CRLs OPTIONAL,
+///     ocsp          [1] EXPLICIT SEQUENCE of OCSPResponse OPTIONAL,
+///     otherRevInfo  [2] EXPLICIT SEQUENCE of OtherRevInfo OPTIONAL }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+pub(crate) struct RevocationInfoArchival {
+    #[asn1(context_specific = "0", tag_mode = "EXPLICIT", optional = "true")]
+    pub(crate) crl: Option<Vec<Any>>,
+    #[asn1(context_specific = "1", tag_mode = "EXPLICIT", optional = "true")]
+    pub(crate) ocsp: Option<Vec<Any>>,
+    #[asn1(context_specific = "2", tag_mode = "EXPLICIT", optional = "true")]
+    pub(crate) other_rev_info: Option<Vec<Any>>,
+}
+
+/// ```text
+/// OCSPResponse ::= SEQUENCE {
+///     responseStatus   OCSPResponseStatus,
+///     responseBytes    [0] EXPLICIT ResponseBytes OPTIONAL }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+struct OcspResponse {
+    response_status: Any,
+    #[asn1(context_specific = "0", tag_mode = "EXPLICIT", optional = "true")]
+    response_bytes: Option<ResponseBytes>,
+}
+
+/// ```text
+/// ResponseBytes ::= SEQUENCE {
+///     responseType   OBJECT IDENTIFIER,
+///     response       OCTET STRING }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+struct ResponseBytes {
+    response_type: ObjectIdentifier,
+    response: OctetString,
+}
+
+/// ```text
+/// BasicOCSPResponse ::= SEQUENCE {
+///     tbsResponseData      ResponseData,
+///     signatureAlgorithm   AlgorithmIdentifier,
+///     signature            BIT STRING,
+///     certs            [0] EXPLICIT SEQUENCE OF Certificate OPTIONAL }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+struct BasicOcspResponse {
+    tbs_response_data: ResponseData,
+    signature_algorithm: AlgorithmIdentifierOwned,
+    signature: Any,
+    #[asn1(context_specific = "0", tag_mode = "EXPLICIT", optional = "true")]
+    certs: Option<Vec<Any>>,
+}
+
+/// ```text
+/// ResponseData ::= SEQUENCE {
+///     version              [0] EXPLICIT Version DEFAULT v1,
+///     responderID              ResponderID,
+///     producedAt               GeneralizedTime,
+///     responses                SEQUENCE OF SingleResponse,
+///     responseExtensions   [1] EXPLICIT Extensions OPTIONAL }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+struct ResponseData {
+    #[asn1(context_specific = "0", tag_mode = "EXPLICIT", optional = "true")]
+    version: Option<u8>,
+    responder_id: Any,
+    produced_at: Any,
+    responses: Vec<SingleResponse>,
+    #[asn1(context_specific = "1", tag_mode = "EXPLICIT", optional = "true")]
+    response_extensions: Option<Any>,
+}
+
+/// ```text
+/// SingleResponse ::= SEQUENCE {
+///     certID                       CertID,
+///     certStatus                   CertStatus,
+///     thisUpdate                   GeneralizedTime,
+///     nextUpdate         [0]       EXPLICIT GeneralizedTime OPTIONAL,
+///     singleExtensions   [1]       EXPLICIT Extensions OPTIONAL }
+/// ```
+///
+/// `cert_status` is the raw CHOICE, `revoked [1] IMPLICIT RevokedInfo` is the
+/// only alternative with content.
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+struct SingleResponse {
+    cert_id: CertId,
+    cert_status: Any,
+    this_update: Any,
+    #[asn1(context_specific = "0", tag_mode = "EXPLICIT", optional = "true")]
+    next_update: Option<Any>,
+    #[asn1(context_specific = "1", tag_mode = "EXPLICIT", optional = "true")]
+    single_extensions: Option<Any>,
+}
+
+/// ```text
+/// CertID ::= SEQUENCE {
+///     hashAlgorithm    AlgorithmIdentifier,
+///     issuerNameHash   OCTET STRING,
+///     issuerKeyHash    OCTET STRING,
+///     serialNumber     CertificateSerialNumber }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+struct CertId {
+    hash_algorithm: AlgorithmIdentifierOwned,
+    issuer_name_hash: OctetString,
+    issuer_key_hash: OctetString,
+    serial_number: SerialNumber,
+}
+
+/// ```text
+/// RevokedInfo ::= SEQUENCE {
+///     revocationTime     GeneralizedTime,
+///     revocationReason   [0] EXPLICIT CRLReason OPTIONAL }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+struct RevokedInfo {
+    revocation_time: Any,
+    #[asn1(context_specific = "0", tag_mode = "EXPLICIT", optional = "true")]
+    revocation_reason: Option<Any>,
+}
+
+/// The CRLReason of the certificate with `serial_number` if `response` says it
+/// is revoked and names a reason.
+pub(crate) fn revocation_reason(
+    response: &[u8],
+    serial_number: &SerialNumber,
+) -> der::Result<Option<i64>> {
+    let response = OcspResponse::from_der(response)?;
+    let Some(bytes) = response.response_bytes else {
+        return Ok(None);
+    };
+    if bytes.response_type != oid::ID_PKIX_OCSP_BASIC {
+        return Ok(None);
+    }
+    let basic = BasicOcspResponse::from_der(bytes.response.as_bytes())?;
+    for single in basic.tbs_response_data.responses {
+        let status = &single.cert_status;
+        if &single.cert_id.serial_number != serial_number
+            || status.tag()
+                != (Tag::ContextSpecific {
+                    constructed: true,
+                    number: TagNumber::N1,
+                })
+        {
+            continue;
+        }
+        // Read the IMPLICIT [1] content as the SEQUENCE it replaces
+        let info = Any::new(Tag::Sequence, status.value())?.decode_as::<RevokedInfo>()?;
+        let reason = info.revocation_reason.and_then(|reason| {
+            let value = reason.value();
+            match (reason.tag(), value) {
+                (Tag::Enumerated, [value]) => Some(i64::from(*value)),
+                _ => None,
+            }
+        });
+        return Ok(reason);
+    }
+    Ok(None)
+}
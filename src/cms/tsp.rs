@@ -0,0 +1,143 @@
+//! TSP
+//! Time-Stamp Protocol structures (RFC 3161).
+use anyhow::Result;
+use chrono::{DateTime, NaiveDateTime, TimeZone, Utc};
+use der::{
+    Sequence, Tag, Tagged,
+    asn1::{Any, BitString, Int, ObjectIdentifier, OctetString},
+};
+use x509_cert::{ext::pkix::name::GeneralName, spki::AlgorithmIdentifierOwned};
+
+/// ```text
+/// MessageImprint ::= SEQUENCE {
+///     hashAlgorithm   AlgorithmIdentifier,
+///     hashedMessage   OCTET STRING }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+pub(crate) struct MessageImprint {
+    pub(crate) hash_algorithm: AlgorithmIdentifierOwned,
+    pub(crate) hashed_message: OctetString,
+}
+
+/// ```text
+/// TimeStampReq ::= SEQUENCE {
+///     version          INTEGER { v1(1) },
+///     messageImprint   MessageImprint,
+///     reqPolicy        TSAPolicyId OPTIONAL,
+///     nonce            INTEGER OPTIONAL,
+///     certReq          BOOLEAN DEFAULT FALSE,
+///     extensions       [0] IMPLICIT Extensions OPTIONAL }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+pub(crate) struct TimeStampReq {
+    pub(crate) version: u8,
+    pub(crate) message_imprint: MessageImprint,
+    #[asn1(optional = "true")]
+    pub(crate) req_policy: Option<ObjectIdentifier>,
+    #[asn1(optional = "true")]
+    pub(crate) nonce: Option<Int>,
+    #[asn1(optional = "true")]
+    pub(crate) cert_req: Option<bool>,
+}
+
+/// ```text
+/// PKIStatusInfo ::= SEQUENCE {
+///     status         PKIStatus,
+///     statusString   PKIFreeText OPTIONAL,
+///     failInfo       PKIFailureInfo OPTIONAL }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+pub(crate) struct PkiStatusInfo {
+    pub(crate) status: u8,
+    #[asn1(optional = "true")]
+    pub(crate) status_string: Option<Vec<String>>,
+    #[asn1(optional = "true")]
+    pub(crate) fail_info: Option<BitString>,
+}
+
+impl PkiStatusInfo {
+    /// `granted` or `grantedWithMods`.
+    pub(crate) fn is_granted(&self) -> bool {
+        self.status <= 1
+    }
+}
+
+/// ```text
+/// TimeStampResp ::= SEQUENCE {
+///     status           PKIStatusInfo,
+///     timeStampToken   TimeStampToken OPTIONAL }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+pub(crate) struct TimeStampResp {
+    pub(crate) status: PkiStatusInfo,
+    #[asn1(optional = "true")]
+    pub(crate) time_stamp_token: Option<Any>,
+}
+
+/// ```text
+/// Accuracy ::= SEQUENCE {
+///     seconds   INTEGER OPTIONAL,
+///     millis    [0] INTEGER (1..999) OPTIONAL,
+///     micros    [1] INTEGER (1..999) OPTIONAL }
+/// ```
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+pub(crate) struct Accuracy {
+    #[asn1(optional = "true")]
+    pub(crate) seconds: Option<u32>,
+    #[asn1(context_specific = "0", tag_mode = "IMPLICIT", optional = "true")]
+    pub(crate) millis: Option<u16>,
+    #[asn1(context_specific = "1", tag_mode = "IMPLICIT", optional = "true")]
+    pub(crate) micros: Option<u16>,
+}
+
+/// ```text
+/// TSTInfo ::= SEQUENCE {
+///     version          INTEGER { v1(1) },
+///     policy           TSAPolicyId,
+///     messageImprint   MessageImprint,
+///     serialNumber     INTEGER,
+///     genTime          GeneralizedTime,
+///     accuracy         Accuracy OPTIONAL,
+///     ordering         BOOLEAN DEFAULT FALSE,
+///     nonce            INTEGER OPTIONAL,
+///     tsa              [0] GeneralName OPTIONAL,
+///     extensions       [1] IMPLICIT Extensions OPTIONAL }
+/// ```
+///
+/// `gen_time` is kept as raw `Any` because TSAs commonly add fractional seconds,
+/// which the strict `GeneralizedTime` type of `der` rejects.
+#[derive(Clone, Debug, Eq, PartialEq, Sequence)]
+pub(crate) struct TstInfo {
+    pub(crate) version: u8,
+    pub(crate) policy: ObjectIdentifier,
+    pub(crate) message_imprint: MessageImprint,
+    pub(crate) serial_number: Int,
+    pub(crate) gen_time: Any,
+    #[asn1(optional = "true")]
+    pub(crate) accuracy: Option<Accuracy>,
+    #[asn1(optional = "true")]
+    pub(crate) ordering: Option<bool>,
+    #[asn1(optional = "true")]
+    pub(crate) nonce: Option<Int>,
+    #[asn1(context_specific = "0", tag_mode = "EXPLICIT", optional = "true")]
+    pub(crate) tsa: Option<GeneralName>,
+    #[asn1(context_specific = "1", tag_mode = "IMPLICIT", optional = "true")]
+    pub(crate) extensions: Option<Vec<Any>>,
+}
+
+impl TstInfo {
+    pub(crate) fn encode_gen_time(time: DateTime<Utc>) -> Result<Any> {
+        let s = time.format("%Y%m%d%H%M%SZ").to_string();
+        Ok(Any::new(Tag::GeneralizedTime, s.into_bytes())?)
+    }
+
+    /// Parse `gen_time`, fractional seconds included.
+    pub(crate) fn gen_time(&self) -> Result<DateTime<Utc>> {
+        if self.gen_time.tag() != Tag::GeneralizedTime {
+            return Err(anyhow::anyhow!("genTime is not a GeneralizedTime"));
+        }
+        let s = std::str::from_utf8(self.gen_time.value())?;
+        let time = NaiveDateTime::parse_from_str(s, "%Y%m%d%H%M%S%.fZ")?;
+        Ok(Utc.from_utc_datetime(&time))
+    }
+}
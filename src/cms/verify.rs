@@ -0,0 +1,350 @@
+//! Verify
+//! Check the SignerInfo of a CMS SignedData against its content.
+use std::time::SystemTime;
+
+use anyhow::Result;
+use cms::{
+    cert::CertificateChoices,
+    signed_data::{SignedData, SignerIdentifier},
+};
+use der::{
+    Decode, Encode,
+    asn1::{ObjectIdentifier, OctetString},
+};
+use openssl::{
+    hash::{MessageDigest, hash},
+    rsa::Padding,
+    sign::{RsaPssSaltlen, Verifier},
+    x509::{X509, X509Ref},
+};
+use x509_cert::{Certificate, attr::Attributes, spki::AlgorithmIdentifierOwned, time::Time};
+
+use crate::verify::SignatureStatus;
+
+use super::{
+    alg::RsaPssParams, decode_signed_data, ocsp::RevocationInfoArchival, oid, tsp::TstInfo,
+};
+
+/// The outcome of checking one SignerInfo.
+pub(crate) struct SignerCheck {
+    /// The certificate identified by the `sid` of the SignerInfo.
+    pub(crate) signer: X509,
+    /// The message-digest attribute matches the content.
+    pub(crate) digest_valid: bool,
+    /// The signature value verifies with the public key of `signer`.
+    pub(crate) signature_valid: bool,
+    /// Every certificate embedded in the SignedData, the signer included.
+    pub(crate) certificates: Vec<X509>,
+    /// The signing-time attribute, the time the signer claims to have signed
+    /// at. The genTime of a timestamp token, see [`check_timestamp_token`].
+    pub(crate) signing_time: Option<SystemTime>,
+    /// The signature value of the SignerInfo.
+    pub(crate) signature: Vec<u8>,
+    /// The DER ContentInfo of the signatureTimeStampToken unsigned attribute.
+    pub(crate) timestamp_token: Option<Vec<u8>>,
+    /// The DER CRLs of the adbe-revocationInfoArchival attribute.
+    pub(crate) crls: Vec<Vec<u8>>,
+    /// The DER OCSPResponses of the adbe-revocationInfoArchival attribute.
+    pub(crate) ocsps: Vec<Vec<u8>>,
+}
+
+fn embedded_certificates(signed_data: &SignedData) -> impl Iterator<Item = &Certificate> {
+    signed_data
+        .certificates
+        .iter()
+        .flat_map(|set| set.0.iter())
+        .filter_map(|choice| match choice {
+            CertificateChoices::Certificate(cert) => Some(cert),
+            _ => None,
+        })
+}
+
+impl SignerCheck {
+    /// The status of the signature, a digest mismatch takes precedence.
+    pub(crate) fn status(&self) -> SignatureStatus {
+        match (self.digest_valid, self.signature_valid) {
+            (false, _) => SignatureStatus::DigestMismatch,
+            (true, false) => SignatureStatus::InvalidSignature,
+            (true, true) => SignatureStatus::Valid,
+        }
+    }
+}
+
+/// The certificates embedded in the SignedData.
+pub(crate) fn certificates(signed_data: &SignedData) -> Result<Vec<X509>> {
+    embedded_certificates(signed_data)
+        .map(|cert| Ok(X509::from_der(&cert.to_der()?)?))
+        .collect()
+}
+
+/// Check the first SignerInfo of `signed_data` over `detached` content, or over
+/// the encapsulated content when `detached` is `None`.
+pub(crate) fn verify_signed_data(
+    signed_data: &SignedData,
+    detached: Option<&[u8]>,
+) -> Result<SignerCheck> {
+    let signer_info = signed_data
+        .signer_infos
+        .0
+        .iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("CMS has no SignerInfo"))?;
+    let content = match detached {
+        Some(content) => content,
+        None => signed_data
+            .encap_content_info
+            .econtent
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("CMS has no encapsulated content"))?
+            .value(),
+    };
+    let signer = find_signer(signed_data, &signer_info.sid)?;
+    let md = message_digest(&signer_info.digest_alg.oid)?;
+
+    let (digest_valid, message) = match &signer_info.signed_attrs {
+        Some(signed_attrs) => {
+            let content_type = attribute(signer_info.signed_attrs.as_ref(), oid::ID_CONTENT_TYPE)?
+                .ok_or_else(|| anyhow::anyhow!("CMS has no content-type attribute"))?
+                .decode_as::<ObjectIdentifier>()?;
+            if content_type != signed_data.encap_content_info.econtent_type {
+                return Err(anyhow::anyhow!(
+                    "The content-type attribute does not match the content"
+                ));
+            }
+            let message_digest =
+                attribute(signer_info.signed_attrs.as_ref(), oid::ID_MESSAGE_DIGEST)?
+                    .ok_or_else(|| anyhow::anyhow!("CMS has no message-digest attribute"))?
+                    .decode_as::<OctetString>()?;
+            let digest = hash(md, content)?;
+            // The signature covers the DER encoding of the attributes as an explicit SET OF
+            (
+                message_digest.as_bytes() == &*digest,
+                signed_attrs.to_der()?,
+            )
+        }
+        None => (true, content.to_vec()),
+    };
+    let signature_valid = verify_signature(
+        &signer,
+        md,
+        &signer_info.signature_algorithm,
+        &message,
+        signer_info.signature.as_bytes(),
+    )?;
+    let signing_time = attribute(signer_info.signed_attrs.as_ref(), oid::ID_SIGNING_TIME)?
+        .map(|time| Time::from_der(&time.to_der()?))
+        .transpose()?
+        .map(|time| SystemTime::UNIX_EPOCH + time.to_unix_duration());
+    let archival = attribute(
+        signer_info.signed_attrs.as_ref(),
+        oid::ID_ADBE_REVOCATION_INFO_ARCHIVAL,
+    )?
+    .map(|archival| archival.decode_as::<RevocationInfoArchival>())
+    .transpose()?;
+    let to_der = |items: Option<Vec<der::Any>>| {
+        items
+            .into_iter()
+            .flatten()
+            .map(|item| item.to_der())
+            .collect::<der::Result<Vec<_>>>()
+    };
+    let (crls, ocsps) = match archival {
+        Some(archival) => (to_der(archival.crl)?, to_der(archival.ocsp)?),
+        None => (Vec::new(), Vec::new()),
+    };
+    let timestamp_token = attribute(
+        signer_info.unsigned_attrs.as_ref(),
+        oid::ID_AA_SIGNATURE_TIME_STAMP_TOKEN,
+    )?
+    .map(|token| token.to_der())
+    .transpose()?;
+    Ok(SignerCheck {
+        signer,
+        digest_valid,
+        signature_valid,
+        certificates: certificates(signed_data)?,
+        signing_time,
+        signature: signer_info.signature.as_bytes().to_vec(),
+        timestamp_token,
+        crls,
+        ocsps,
+    })
+}
+
+/// Check a timestamp token: the TSA signature over its TSTInfo, and that the
+/// message imprint is the digest of `data`. A wrong imprint is reported as an
+/// invalid digest, and `signing_time` is the genTime of the token.
+pub(crate) fn check_timestamp_token(token: &SignedData, data: &[u8]) -> Result<SignerCheck> {
+    if token.encap_content_info.econtent_type != oid::ID_CT_TST_INFO {
+        return Err(anyhow::anyhow!("Timestamp token does not hold a TSTInfo"));
+    }
+    let mut check = verify_signed_data(token, None)?;
+    let econtent = token.encap_content_info.econtent.as_ref();
+    let tst_info = TstInfo::from_der(econtent.map(|e| e.value()).unwrap_or_default())?;
+    let imprint = &tst_info.message_imprint;
+    let md = message_digest(&imprint.hash_algorithm.oid)?;
+    if imprint.hashed_message.as_bytes() != &*hash(md, data)? {
+        check.digest_valid = false;
+    }
+    check.signing_time = Some(tst_info.gen_time()?.into());
+    Ok(check)
+}
+
+fn attribute(attrs: Option<&Attributes>, oid: ObjectIdentifier) -> Result<Option<&der::Any>> {
+    let Some(attr) = attrs
+        .into_iter()
+        .flat_map(|attrs| attrs.iter())
+        .find(|attr| attr.oid == oid)
+    else {
+        return Ok(None);
+    };
+    match attr.values.len() {
+        1 => Ok(attr.values.iter().next()),
+        _ => Err(anyhow::anyhow!(
+            "Attribute {} must have a single value",
+            oid
+        )),
+    }
+}
+
+/// The digest algorithm and the message-digest attribute of the first
+/// SignerInfo, the digest of the content it signs.
+pub(crate) fn signed_digest(signed_data: &SignedData) -> Result<(MessageDigest, Vec<u8>)> {
+    let signer_info = signed_data
+        .signer_infos
+        .0
+        .iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("CMS has no SignerInfo"))?;
+    let digest = attribute(signer_info.signed_attrs.as_ref(), oid::ID_MESSAGE_DIGEST)?
+        .ok_or_else(|| anyhow::anyhow!("CMS has no message-digest attribute"))?
+        .decode_as::<OctetString>()?;
+    Ok((
+        message_digest(&signer_info.digest_alg.oid)?,
+        digest.as_bytes().to_vec(),
+    ))
+}
+
+/// The certificate of the first SignerInfo, without checking the signature.
+pub(crate) fn signer_certificate(signed_data: &SignedData) -> Result<X509> {
+    let signer_info = signed_data
+        .signer_infos
+        .0
+        .iter()
+        .next()
+        .ok_or_else(|| anyhow::anyhow!("CMS has no SignerInfo"))?;
+    find_signer(signed_data, &signer_info.sid)
+}
+
+/// The signatureTimeStampToken attributes of every SignerInfo.
+pub(crate) fn signature_timestamp_tokens(signed_data: &SignedData) -> Result<Vec<SignedData>> {
+    let mut tokens = Vec::new();
+    for signer_info in signed_data.signer_infos.0.iter() {
+        let token = attribute(
+            signer_info.unsigned_attrs.as_ref(),
+            oid::ID_AA_SIGNATURE_TIME_STAMP_TOKEN,
+        )?;
+        if let Some(token) = token {
+            tokens.push(decode_signed_data(&token.to_der()?)?);
+        }
+    }
+    Ok(tokens)
+}
+
+fn find_signer(signed_data: &SignedData, sid: &SignerIdentifier) -> Result<X509> {
+    for cert in embedded_certificates(signed_data) {
+        let x509 = X509::from_der(&cert.to_der()?)?;
+        if matches_sid(cert, &x509, sid) {
+            return Ok(x509);
+        }
+    }
+    Err(anyhow::anyhow!(
+        "The signer certificate is not embedded in the CMS"
+    ))
+}
+
+fn matches_sid(cert: &Certificate, x509: &X509Ref, sid: &SignerIdentifier) -> bool {
+    match sid {
+        SignerIdentifier::IssuerAndSerialNumber(isn) => {
+            cert.tbs_certificate.issuer == isn.issuer
+                && cert.tbs_certificate.serial_number == isn.serial_number
+        }
+        SignerIdentifier::SubjectKeyIdentifier(ski) => x509
+            .subject_key_id()
+            .is_some_and(|id| id.as_slice() == ski.0.as_bytes()),
+    }
+}
+
+/// The digest of a digest algorithm identifier.
+pub(crate) fn message_digest(algorithm: &ObjectIdentifier) -> Result<MessageDigest> {
+    match *algorithm {
+        oid::ID_SHA1 => Ok(MessageDigest::sha1()),
+        oid::ID_SHA224 => Ok(MessageDigest::sha224()),
+        oid::ID_SHA256 => Ok(MessageDigest::sha256()),
+        oid::ID_SHA384 => Ok(MessageDigest::sha384()),
+        oid::ID_SHA512 => Ok(MessageDigest::sha512()),
+        oid => Err(anyhow::anyhow!("Unsupported digest algorithm {}", oid)),
+    }
+}
+
+/// Verify `signature` over `message`, `md` is the digest of the SignerInfo used
+/// by the algorithms that do not name their own, such as rsaEncryption.
+pub(crate) fn verify_signature(
+    cert: &X509Ref,
+    md: MessageDigest,
+    algorithm: &AlgorithmIdentifierOwned,
+    message: &[u8],
+    signature: &[u8],
+) -> Result<bool> {
+    let pkey = cert.public_key()?;
+    // A malformed signature value fails verification rather than the whole check
+    let valid = match algorithm.oid {
+        oid::ID_ED25519 => Verifier::new_without_digest(&pkey)?
+            .verify_oneshot(signature, message)
+            .unwrap_or(false),
+        oid::ID_RSASSA_PSS => {
+            let params = algorithm
+                .parameters
+                .as_ref()
+                .ok_or_else(|| anyhow::anyhow!("RSASSA-PSS parameters are missing"))?
+                .decode_as::<RsaPssParams>()?;
+            let md = match &params.hash_algorithm {
+                Some(hash_algorithm) => message_digest(&hash_algorithm.oid)?,
+                None => MessageDigest::sha1(),
+            };
+            let mgf1_md = match &params.mask_gen_algorithm {
+                Some(mgf) if mgf.oid != oid::ID_MGF1 => {
+                    return Err(anyhow::anyhow!("Unsupported mask generation function"));
+                }
+                Some(mgf) => match &mgf.parameters {
+                    Some(params) => {
+                        message_digest(&params.decode_as::<AlgorithmIdentifierOwned>()?.oid)?
+                    }
+                    None => MessageDigest::sha1(),
+                },
+                None => MessageDigest::sha1(),
+            };
+            let mut verifier = Verifier::new(md, &pkey)?;
+            verifier.set_rsa_padding(Padding::PKCS1_PSS)?;
+            verifier.set_rsa_mgf1_md(mgf1_md)?;
+            verifier.set_rsa_pss_saltlen(RsaPssSaltlen::custom(
+                params.salt_length.unwrap_or(20) as i32,
+            ))?;
+            verifier.verify_oneshot(signature, message).unwrap_or(false)
+        }
+        oid => {
+            let md = match oid {
+                oid::RSA_ENCRYPTION | oid::ID_EC_PUBLIC_KEY => md,
+                oid::SHA1_WITH_RSA_ENCRYPTION | oid::ECDSA_WITH_SHA1 => MessageDigest::sha1(),
+                oid::SHA224_WITH_RSA_ENCRYPTION | oid::ECDSA_WITH_SHA224 => MessageDigest::sha224(),
+                oid::SHA256_WITH_RSA_ENCRYPTION | oid::ECDSA_WITH_SHA256 => MessageDigest::sha256(),
+                oid::SHA384_WITH_RSA_ENCRYPTION | oid::ECDSA_WITH_SHA384 => MessageDigest::sha384(),
+                oid::SHA512_WITH_RSA_ENCRYPTION | oid::ECDSA_WITH_SHA512 => MessageDigest::sha512(),
+                oid => return Err(anyhow::anyhow!("Unsupported signature algorithm {}", oid)),
+            };
+            let mut verifier = Verifier::new(md, &pkey)?;
+            verifier.verify_oneshot(signature, message).unwrap_or(false)
+        }
+    };
+    Ok(valid)
+}
@@ -1 +1,4 @@
-pub(crate) const SIG_CONTENTS_PLACEHOLDER_LEN: usize = 4096; // 4096 bytes, pdf hex string will be 8192 bytes
+pub(crate) const SIG_CONTENTS_PLACEHOLDER_LEN: usize = 4096; // signature bytes reserved by prepare and for signers without certificates to estimate from
+pub(crate) const SIG_CONTENTS_MARGIN: usize = 64; // DER lengths of ECDSA signatures and timestamps vary slightly
+pub(crate) const TIMESTAMP_TOKEN_RESERVE: usize = 8192; // room for the first token of a TSA whose token size is unknown, with its TSA chain
+pub(crate) const REVOCATION_FRESHNESS_SECS: u64 = 24 * 60 * 60; // how long a CRL or OCSP response without nextUpdate stays current after its thisUpdate
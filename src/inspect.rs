@@ -0,0 +1,397 @@
+//! Inspect
+//! Describe the pages, form fields, signatures and images of a document
+//! without changing or verifying it.
+use std::{collections::HashSet, path::Path};
+
+use anyhow::Result;
+use lopdf::{Dictionary, Document, Object, ObjectId, decode_text_string};
+
+use crate::{
+    cms::{decode_signed_data, verify::signer_certificate},
+    manager::{DocMdpPermission, mdp::doc_mdp_permission},
+    utils::{revision_ends, terminal_fields},
+    verify::{is_signature, name_to_string},
+};
+
+/// What a document contains.
+#[derive(Debug, Clone)]
+pub struct DocumentInfo {
+    /// The version of the header, such as `1.7`.
+    pub version: String,
+    /// The number of incremental revisions, the original document included.
+    pub revisions: usize,
+    pub pages: Vec<PageInfo>,
+    /// The terminal fields of the AcroForm.
+    pub fields: Vec<FieldInfo>,
+    /// Every signature and document timestamp dictionary.
+    pub signatures: Vec<SignatureInfo>,
+}
+
+#[derive(Debug, Clone)]
+pub struct PageInfo {
+    /// 1-based.
+    pub number: u32,
+    pub id: ObjectId,
+    /// Inherited from the page tree if the page has none.
+    pub media_box: Option<Vec<f32>>,
+    /// The MediaBox if the page has none.
+    pub crop_box: Option<Vec<f32>>,
+    /// Degrees clockwise, a multiple of 90.
+    pub rotate: i64,
+    /// The number of annotations, widgets included.
+    pub annotations: usize,
+    /// The image XObjects of the page resources, such as seals.
+    pub images: Vec<ImageInfo>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ImageInfo {
+    /// The resource name, such as `X12`.
+    pub name: String,
+    pub id: ObjectId,
+    pub width: i64,
+    pub height: i64,
+    pub bits_per_component: Option<i64>,
+    pub color_space: Option<String>,
+    /// The image has a soft mask, such as the alpha channel of a PNG.
+    pub has_soft_mask: bool,
+    /// The transformation matrix of every place the page content draws the
+    /// image, `[w 0 0 h x y]` for an unrotated image of size `w`×`h` at `x`,`y`.
+    pub placements: Vec<[f32; 6]>,
+}
+
+#[derive(Debug, Clone)]
+pub struct FieldInfo {
+    /// The fully qualified name.
+    pub name: String,
+    pub id: Option<ObjectId>,
+    /// The `/FT`, such as `Sig` or `Tx`.
+    pub field_type: Option<String>,
+    /// The `/V` of a non-signature field, as text.
+    pub value: Option<String>,
+    /// A signature field has a signature value.
+    pub signed: bool,
+    /// The page of the first widget.
+    pub page: Option<u32>,
+    /// The `/Rect` of the first widget.
+    pub rect: Option<Vec<f32>>,
+}
+
+#[derive(Debug, Clone)]
+pub struct SignatureInfo {
+    pub id: ObjectId,
+    /// The field whose value the dictionary is, `None` if no field refers to it.
+    pub field_name: Option<String>,
+    /// `Sig` or `DocTimeStamp`.
+    pub sig_type: Option<String>,
+    pub sub_filter: Option<String>,
+    pub name: Option<String>,
+    pub reason: Option<String>,
+    pub location: Option<String>,
+    pub contact_info: Option<String>,
+    /// The `/M` date string, such as `D:20240102030405+08'00'`.
+    pub signing_time: Option<String>,
+    pub byte_range: Vec<i64>,
+    /// The size of the `/Contents` string, the space reserved for the CMS.
+    pub contents_len: usize,
+    /// The subject of the signer certificate, or of the TSA certificate of a
+    /// document timestamp. `None` for an empty placeholder.
+    pub signer_subject: Option<String>,
+    /// The DocMDP permission of a certification signature.
+    pub doc_mdp: Option<DocMdpPermission>,
+}
+
+impl DocumentInfo {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_bytes(&std::fs::read(path)?)
+    }
+
+    pub fn from_bytes(data: &[u8]) -> Result<Self> {
+        let doc = Document::load_mem(data)?;
+        let pages = doc
+            .get_pages()
+            .into_iter()
+            .map(|(number, id)| page_info(&doc, number, id))
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut field_sigs = HashSet::new();
+        let mut signatures = Vec::new();
+        let mut fields = Vec::new();
+        for field in terminal_fields(&doc) {
+            let value = field.dict.get(b"V").ok();
+            let sig = value
+                .and_then(|value| doc.dereference(value).ok())
+                .filter(|_| field.field_type == Some(b"Sig"));
+            if let Some((Some(id), Object::Dictionary(sig))) = sig {
+                field_sigs.insert(id);
+                signatures.push(signature_info(&doc, id, Some(field.name.clone()), sig));
+            }
+            let widget = field
+                .widget_ids(&doc)
+                .into_iter()
+                .find_map(|id| Some((id, doc.get_dictionary(id).ok()?)));
+            fields.push(FieldInfo {
+                value: match field.field_type {
+                    Some(b"Sig") => None,
+                    _ => value.map(|value| text(&doc, value)),
+                },
+                signed: field.field_type == Some(b"Sig") && sig.is_some(),
+                field_type: field
+                    .field_type
+                    .map(|name| String::from_utf8_lossy(name).into_owned()),
+                page: widget.and_then(|(id, widget)| widget_page(&doc, &pages, id, widget)),
+                rect: widget.and_then(|(_, widget)| numbers(&doc, widget.get(b"Rect").ok()?)),
+                id: field.id,
+                name: field.name,
+            });
+        }
+        for (id, obj) in &doc.objects {
+            match obj {
+                Object::Dictionary(sig) if !field_sigs.contains(id) && is_signature(sig) => {
+                    signatures.push(signature_info(&doc, *id, None, sig));
+                }
+                _ => {}
+            }
+        }
+        // In file order, the order the signatures were added in
+        signatures.sort_by_key(|sig| match sig.byte_range[..] {
+            [.., start, len] => start.saturating_add(len),
+            _ => 0,
+        });
+
+        Ok(DocumentInfo {
+            version: doc.version.clone(),
+            revisions: revision_ends(data).len(),
+            pages,
+            fields,
+            signatures,
+        })
+    }
+}
+
+fn page_info(doc: &Document, number: u32, id: ObjectId) -> Result<PageInfo> {
+    let page = doc.get_dictionary(id)?;
+    let media_box = inherited(doc, page, b"MediaBox").and_then(|value| numbers(doc, value));
+    let crop_box = inherited(doc, page, b"CropBox")
+        .and_then(|value| numbers(doc, value))
+        .or_else(|| media_box.clone());
+    let rotate = inherited(doc, page, b"Rotate")
+        .and_then(|value| value.as_i64().ok())
+        .unwrap_or(0);
+    let annotations = page
+        .get_deref(b"Annots", doc)
+        .and_then(Object::as_array)
+        .map(Vec::len)
+        .unwrap_or(0);
+    Ok(PageInfo {
+        number,
+        id,
+        media_box,
+        crop_box,
+        rotate,
+        annotations,
+        images: page_images(doc, id),
+    })
+}
+
+/// A page attribute, looked up through the `/Parent` chain if the page has none.
+fn inherited<'a>(doc: &'a Document, page: &'a Dictionary, key: &[u8]) -> Option<&'a Object> {
+    let mut node = page;
+    // Bound the walk in case of a cycle in a malformed page tree
+    for _ in 0..32 {
+        if let Ok(value) = node.get(key) {
+            return Some(value);
+        }
+        node = node
+            .get(b"Parent")
+            .and_then(Object::as_reference)
+            .and_then(|parent| doc.get_dictionary(parent))
+            .ok()?;
+    }
+    None
+}
+
+fn page_images(doc: &Document, page_id: ObjectId) -> Vec<ImageInfo> {
+    let Ok((direct, ids)) = doc.get_page_resources(page_id) else {
+        return Vec::new();
+    };
+    let resources = direct
+        .into_iter()
+        .chain(ids.into_iter().filter_map(|id| doc.get_dictionary(id).ok()));
+    let mut images = Vec::<ImageInfo>::new();
+    for resources in resources {
+        let Ok(xobjects) = resources
+            .get_deref(b"XObject", doc)
+            .and_then(Object::as_dict)
+        else {
+            continue;
+        };
+        for (name, value) in xobjects {
+            let Ok(id) = value.as_reference() else {
+                continue;
+            };
+            let Ok(image) = doc.get_object(id).and_then(Object::as_stream) else {
+                continue;
+            };
+            let dict = &image.dict;
+            let name = String::from_utf8_lossy(name).into_owned();
+            if dict.get(b"Subtype").and_then(Object::as_name).ok() != Some(b"Image")
+                || images.iter().any(|image| image.name == name)
+            {
+                continue;
+            }
+            let int = |key: &[u8]| dict.get(key).and_then(Object::as_i64).ok();
+            images.push(ImageInfo {
+                width: int(b"Width").unwrap_or_default(),
+                height: int(b"Height").unwrap_or_default(),
+                bits_per_component: int(b"BitsPerComponent"),
+                color_space: dict.get(b"ColorSpace").ok().map(|value| match value {
+                    Object::Name(name) => String::from_utf8_lossy(name).into_owned(),
+                    value => format!("{:?}", value),
+                }),
+                has_soft_mask: dict.has(b"SMask"),
+                placements: Vec::new(),
+                name,
+                id,
+            });
+        }
+    }
+    if let Ok(content) = doc.get_and_decode_page_content(page_id) {
+        // Follow the transformation matrix through `q`, `Q` and `cm` up to each `Do`
+        let mut ctm = [1.0, 0.0, 0.0, 1.0, 0.0, 0.0];
+        let mut saved = Vec::new();
+        for operation in content.operations {
+            match (operation.operator.as_str(), &operation.operands[..]) {
+                ("q", _) => saved.push(ctm),
+                ("Q", _) => ctm = saved.pop().unwrap_or(ctm),
+                ("cm", operands) => {
+                    let matrix = operands
+                        .iter()
+                        .map(|n| n.as_float())
+                        .collect::<Result<Vec<_>, _>>();
+                    if let Ok(Ok(matrix)) = matrix.map(<[f32; 6]>::try_from) {
+                        ctm = multiply(&matrix, &ctm);
+                    }
+                }
+                ("Do", [Object::Name(name)]) => {
+                    let name = String::from_utf8_lossy(name);
+                    if let Some(image) = images.iter_mut().find(|image| image.name == name) {
+                        image.placements.push(ctm);
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+    images
+}
+
+/// The product `m × ctm` of two PDF transformation matrices.
+fn multiply(m: &[f32; 6], ctm: &[f32; 6]) -> [f32; 6] {
+    [
+        m[0] * ctm[0] + m[1] * ctm[2],
+        m[0] * ctm[1] + m[1] * ctm[3],
+        m[2] * ctm[0] + m[3] * ctm[2],
+        m[2] * ctm[1] + m[3] * ctm[3],
+        m[4] * ctm[0] + m[5] * ctm[2] + ctm[4],
+        m[4] * ctm[1] + m[5] * ctm[3] + ctm[5],
+    ]
+}
+
+/// The page of a widget, from its `/P` or else the page whose `/Annots` lists it.
+fn widget_page(
+    doc: &Document,
+    pages: &[PageInfo],
+    widget_id: ObjectId,
+    widget: &Dictionary,
+) -> Option<u32> {
+    let page_of = |page_id: ObjectId| {
+        pages
+            .iter()
+            .find(|page| page.id == page_id)
+            .map(|page| page.number)
+    };
+    if let Some(number) = widget
+        .get(b"P")
+        .and_then(Object::as_reference)
+        .ok()
+        .and_then(page_of)
+    {
+        return Some(number);
+    }
+    pages.iter().find_map(|page| {
+        let annots = doc
+            .get_dictionary(page.id)
+            .and_then(|dict| dict.get_deref(b"Annots", doc))
+            .and_then(Object::as_array)
+            .ok()?;
+        annots
+            .iter()
+            .any(|annot| annot.as_reference().ok() == Some(widget_id))
+            .then_some(page.number)
+    })
+}
+
+fn signature_info(
+    doc: &Document,
+    id: ObjectId,
+    field_name: Option<String>,
+    sig: &Dictionary,
+) -> SignatureInfo {
+    let name = |key: &[u8]| {
+        sig.get(key)
+            .and_then(Object::as_name)
+            .ok()
+            .map(|name| String::from_utf8_lossy(name).into_owned())
+    };
+    let string = |key: &[u8]| sig.get(key).ok().map(|value| text(doc, value));
+    let contents = sig
+        .get(b"Contents")
+        .and_then(Object::as_str)
+        .unwrap_or_default();
+    let signer_subject = decode_signed_data(contents)
+        .and_then(|signed_data| signer_certificate(&signed_data))
+        .ok()
+        .map(|cert| name_to_string(cert.subject_name()));
+    SignatureInfo {
+        id,
+        field_name,
+        sig_type: name(b"Type"),
+        sub_filter: name(b"SubFilter"),
+        name: string(b"Name"),
+        reason: string(b"Reason"),
+        location: string(b"Location"),
+        contact_info: string(b"ContactInfo"),
+        signing_time: string(b"M"),
+        byte_range: sig
+            .get(b"ByteRange")
+            .and_then(Object::as_array)
+            .map(|range| range.iter().filter_map(|n| n.as_i64().ok()).collect())
+            .unwrap_or_default(),
+        contents_len: contents.len(),
+        signer_subject,
+        doc_mdp: doc_mdp_permission(sig, doc),
+    }
+}
+
+/// A text string decoded, a name as `/Name`, anything else in PDF syntax.
+fn text(doc: &Document, value: &Object) -> String {
+    let value = doc
+        .dereference(value)
+        .map(|(_, value)| value)
+        .unwrap_or(value);
+    match value {
+        Object::String(..) => decode_text_string(value).unwrap_or_else(|_| format!("{:?}", value)),
+        value => format!("{:?}", value),
+    }
+}
+
+fn numbers(doc: &Document, value: &Object) -> Option<Vec<f32>> {
+    let (_, value) = doc.dereference(value).ok()?;
+    value
+        .as_array()
+        .ok()?
+        .iter()
+        .map(|n| n.as_float().ok())
+        .collect()
+}
@@ -1,12 +1,31 @@
 pub(crate) mod byte_range;
+pub(crate) mod cms;
 pub(crate) mod config;
+pub(crate) mod inspect;
 pub(crate) mod manager;
 pub(crate) mod parser;
+pub(crate) mod revocation;
 pub(crate) mod signer;
+pub(crate) mod tsa;
 pub(crate) mod utils;
+pub(crate) mod verify;
 
+pub use inspect::{DocumentInfo, FieldInfo, ImageInfo, PageInfo, SignatureInfo};
 pub use manager::{
-    ImageRect, PDFSignManager,
+    DocMdpPermission, FieldLock, ImageRect, MissingRevocation, PDFSignManager, PreparedSignature,
+    SeedValue, SignatureFieldOptions, ValidationData,
     sign_info::{SignerInfo, SignerInfoBuilder},
 };
-pub use signer::{P12Signer, Sign};
+pub use revocation::{HttpRevocationFetcher, RevocationFetcher};
+pub use signer::{
+    CmsSigner, DigestAlgorithm, P12Signer, PemSigner, Pkcs11Signer, Pkcs11SignerBuilder, RawSigner,
+    Sign, SignatureScheme, SubFilter,
+};
+pub use tsa::{HttpTimestampAuthority, LocalTimestampAuthority, TimestampAuthority};
+pub use verify::{
+    CertificateReport, CertificateRevocation, ContentChange, IntegrityReport, IntegrityStatus,
+    KeyChange, Modification, ModificationKind, ModificationReport, ObjectChange, ObjectDiff,
+    PdfVerifier, RevisionDiff, RevocationReport, RevocationSource, RevocationState,
+    RevocationStatus, SignatureReport, SignatureStatus, SignatureVerification, TimestampReport,
+    TimestampVerification, TrustReport, TrustStatus, TrustStore, Verdict, VerificationReport,
+};
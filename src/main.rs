@@ -1,16 +1,23 @@
 use std::{
-    fs::{File, create_dir_all},
-    io::Read,
+    fs::create_dir_all,
     path::{Path, PathBuf},
-    process::Command,
+    str::FromStr,
 };
 
 use anyhow::Result;
 
 use chrono::{DateTime, Utc};
-use clap::{Args, Parser, Subcommand, arg, command};
-use lopdf::{Document, Object};
-use pdf_modify::{ImageRect, P12Signer, PDFSignManager, SignerInfo};
+use clap::{Args, Parser, Subcommand};
+use lopdf::Object;
+use openssl::x509::X509;
+use pdf_modify::{
+    CmsSigner, ContentChange, DigestAlgorithm, DocMdpPermission, DocumentInfo, FieldLock,
+    HttpRevocationFetcher, HttpTimestampAuthority, ImageRect, ModificationKind, ObjectChange,
+    P12Signer, PDFSignManager, PdfVerifier, PemSigner, Pkcs11Signer, PreparedSignature,
+    RevisionDiff, RevocationFetcher, RevocationSource, RevocationStatus, SeedValue, Sign,
+    SignatureFieldOptions, SignatureScheme, SignatureStatus, SignerInfo, SubFilter,
+    TimestampAuthority, TrustStatus, TrustStore, ValidationData, Verdict, VerificationReport,
+};
 
 fn main() -> Result<()> {
     let cli = Cli::parse();
@@ -19,6 +26,17 @@ fn main() -> Result<()> {
             input,
             output,
             cert,
+            key,
+            chain,
+            password,
+            pkcs11,
+            pades,
+            digest,
+            scheme,
+            certify,
+            field,
+            tsa,
+            placeholder_size,
             signer_info,
             image,
             pos,
@@ -27,26 +45,333 @@ fn main() -> Result<()> {
             cross_page_size,
         } => {
             let mut manager = PDFSignManager::load(input)?;
+            if let Some(url) = tsa {
+                manager.set_timestamp_authority(HttpTimestampAuthority::new(url)?);
+            }
+            if let Some(len) = placeholder_size {
+                manager.set_placeholder_size(len);
+            }
 
             if let Some(img) = cross_page_image {
                 manager.add_cross_page_seal(img, cross_page_size)?;
             };
 
-            if let Some(cert) = cert {
-                let signer = P12Signer::load(cert)?;
-                let img = image.map(|p| ImageRect::new(p, pos, size));
-                manager.sign(signer_info.into(), img, &signer)?;
+            let sub_filter = match pades {
+                true => SubFilter::CadesDetached,
+                false => SubFilter::Pkcs7Detached,
+            };
+            let signer: Option<Box<dyn Sign>> = match (cert, pkcs11.module) {
+                (Some(cert), _) => match key {
+                    Some(key) => {
+                        let mut signer = match &password {
+                            Some(password) => PemSigner::load_with_password(cert, key, password)?,
+                            None => PemSigner::load(cert, key)?,
+                        };
+                        if let Some(chain) = chain {
+                            signer = signer.with_chain_file(chain)?;
+                        }
+                        if let Some(digest) = digest {
+                            signer = signer.with_digest(digest);
+                        }
+                        if let Some(scheme) = scheme {
+                            signer = signer.with_scheme(scheme);
+                        }
+                        Some(Box::new(signer.with_sub_filter(sub_filter)))
+                    }
+                    None => {
+                        let password = password.as_deref().unwrap_or_default();
+                        let mut signer = P12Signer::load_with_password(cert, password)?;
+                        if let Some(digest) = digest {
+                            signer = signer.with_digest(digest);
+                        }
+                        if let Some(scheme) = scheme {
+                            signer = signer.with_scheme(scheme);
+                        }
+                        Some(Box::new(signer.with_sub_filter(sub_filter)))
+                    }
+                },
+                (None, Some(module)) => {
+                    let mut builder = Pkcs11Signer::builder(module);
+                    if let Some(token) = pkcs11.token {
+                        builder = builder.token_label(token);
+                    }
+                    if let Some(key) = pkcs11.key_label {
+                        builder = builder.key_label(key);
+                    }
+                    if let Some(pin) = pkcs11.pin {
+                        builder = builder.pin(pin);
+                    }
+                    if let Some(path) = pkcs11.pkcs11_cert {
+                        builder = builder.certificate(X509::from_pem(&std::fs::read(path)?)?);
+                    }
+                    if let Some(digest) = digest {
+                        builder = builder.digest(digest);
+                    }
+                    if let Some(scheme) = scheme {
+                        builder = builder.scheme(scheme);
+                    }
+                    Some(Box::new(
+                        CmsSigner::new(builder.open()?).with_sub_filter(sub_filter),
+                    ))
+                }
+                (None, None) => None,
+            };
+
+            if let Some(signer) = signer {
+                let signer = signer.as_ref();
+                let permission = certify.map(DocMdpPermission::try_from).transpose()?;
+                match (field, permission) {
+                    (Some(name), Some(permission)) => manager.certify_field(
+                        &name,
+                        permission,
+                        signer_info.into(),
+                        image,
+                        signer,
+                    )?,
+                    (Some(name), None) => {
+                        manager.sign_field(&name, signer_info.into(), image, signer)?
+                    }
+                    (None, Some(permission)) => {
+                        let img = image.map(|p| ImageRect::new(p, pos, size));
+                        manager.certify(permission, signer_info.into(), img, signer)?;
+                    }
+                    (None, None) => {
+                        let img = image.map(|p| ImageRect::new(p, pos, size));
+                        manager.sign(signer_info.into(), img, signer)?;
+                    }
+                }
+            }
+            if let Some(dir) = output.parent() {
+                create_dir_all(dir)?;
+            };
+            manager.save(output)?;
+        }
+        Commands::Timestamp {
+            input,
+            output,
+            tsa,
+            placeholder_size,
+        } => {
+            let mut manager = PDFSignManager::load(input)?;
+            if let Some(len) = placeholder_size {
+                manager.set_placeholder_size(len);
             }
+            manager.timestamp(&HttpTimestampAuthority::new(tsa)?)?;
             if let Some(dir) = output.parent() {
                 create_dir_all(dir)?;
             };
             manager.save(output)?;
         }
-        Commands::Verify { input } => verify(input)?,
+        Commands::Dss {
+            input,
+            output,
+            cert,
+            crl,
+            ocsp,
+            fetch,
+        } => {
+            let mut data = ValidationData::new();
+            for path in cert {
+                data.add_cert_file(path)?;
+            }
+            for path in crl {
+                data.add_crl_file(path)?;
+            }
+            for path in ocsp {
+                data.add_ocsp_file(path)?;
+            }
+            let fetcher = match fetch {
+                true => Some(HttpRevocationFetcher::new()?),
+                false => None,
+            };
+
+            let mut manager = PDFSignManager::load(input)?;
+            let missing =
+                manager.add_dss(data, fetcher.as_ref().map(|f| f as &dyn RevocationFetcher))?;
+            for missing in missing {
+                eprintln!("未能获取吊销信息: {} ({})", missing.subject, missing.reason);
+            }
+            if let Some(dir) = output.parent() {
+                create_dir_all(dir)?;
+            };
+            manager.save(output)?;
+        }
+        Commands::PrepareFields {
+            input,
+            output,
+            field,
+            require_pades,
+            reason,
+            digest_method,
+            issuer,
+            lock,
+            lock_all,
+        } => {
+            let mut seed_value = SeedValue {
+                reasons: reason,
+                digest_methods: digest_method,
+                ..Default::default()
+            };
+            if require_pades {
+                seed_value.sub_filters.push(SubFilter::CadesDetached);
+            }
+            for path in issuer {
+                let data = std::fs::read(path)?;
+                seed_value.issuers.extend(X509::stack_from_pem(&data)?);
+            }
+            let seed_value = match seed_value.sub_filters.is_empty()
+                && seed_value.reasons.is_empty()
+                && seed_value.digest_methods.is_empty()
+                && seed_value.issuers.is_empty()
+            {
+                true => None,
+                false => Some(seed_value),
+            };
+            let lock = match (lock_all, lock.is_empty()) {
+                (true, _) => Some(FieldLock::All),
+                (false, false) => Some(FieldLock::Include(lock)),
+                (false, true) => None,
+            };
+
+            let mut manager = PDFSignManager::load(input)?;
+            for spec in field {
+                let options = SignatureFieldOptions {
+                    tooltip: spec.tooltip,
+                    seed_value: seed_value.clone(),
+                    lock: lock.clone(),
+                };
+                manager.add_signature_field(spec.page, spec.rect, &spec.name, options)?;
+            }
+            if let Some(dir) = output.parent() {
+                create_dir_all(dir)?;
+            };
+            manager.save(output)?;
+        }
+        Commands::Prepare {
+            input,
+            output,
+            pades,
+            digest,
+            data,
+            placeholder_size,
+            signer_info,
+        } => {
+            let sub_filter = match pades {
+                true => SubFilter::CadesDetached,
+                false => SubFilter::Pkcs7Detached,
+            };
+            let mut manager = PDFSignManager::load(input)?;
+            if let Some(len) = placeholder_size {
+                manager.set_placeholder_size(len);
+            }
+            let prepared = manager.prepare(
+                signer_info.into(),
+                None::<ImageRect<&Path>>,
+                sub_filter,
+                digest,
+            )?;
+            if let Some(data) = data {
+                std::fs::write(data, prepared.data_to_sign())?;
+            }
+            if let Some(dir) = output.parent() {
+                create_dir_all(dir)?;
+            };
+            std::fs::write(output, prepared.as_bytes())?;
+            println!("{}", hex::encode(prepared.digest()));
+        }
+        Commands::Complete {
+            input,
+            output,
+            cms,
+            digest,
+            tsa,
+        } => {
+            let prepared = PreparedSignature::from_bytes(std::fs::read(input)?, digest)?;
+            let tsa = tsa.map(HttpTimestampAuthority::new).transpose()?;
+            let signed = prepared.complete(
+                std::fs::read(cms)?,
+                tsa.as_ref().map(|tsa| tsa as &dyn TimestampAuthority),
+            )?;
+            if let Some(dir) = output.parent() {
+                create_dir_all(dir)?;
+            };
+            std::fs::write(output, signed)?;
+        }
+        Commands::Verify {
+            input,
+            trust,
+            revocation,
+            cert,
+            crl,
+            ocsp,
+            fetch,
+            format,
+        } => {
+            let revocation = match revocation {
+                true => {
+                    let mut data = ValidationData::new();
+                    for path in cert {
+                        data.add_cert_file(path)?;
+                    }
+                    for path in crl {
+                        data.add_crl_file(path)?;
+                    }
+                    for path in ocsp {
+                        data.add_ocsp_file(path)?;
+                    }
+                    let fetcher: Option<Box<dyn RevocationFetcher>> = match fetch {
+                        true => Some(Box::new(HttpRevocationFetcher::new()?)),
+                        false => None,
+                    };
+                    Some((data, fetcher))
+                }
+                false => None,
+            };
+            let verdict = verify(input, &trust, revocation, format)?;
+            if let Some(code) = exit_code(verdict) {
+                std::process::exit(code);
+            }
+        }
+        Commands::ExtractRevision {
+            input,
+            output,
+            field,
+            revision,
+        } => {
+            let verifier = PdfVerifier::load(input)?;
+            let data = match (field, revision) {
+                (Some(field), _) => verifier.signed_revision(&field)?,
+                (None, Some(n)) => verifier.revision(n)?,
+                (None, None) => unreachable!("clap requires --field or --revision"),
+            };
+            if let Some(dir) = output.parent() {
+                create_dir_all(dir)?;
+            };
+            std::fs::write(output, data)?;
+        }
+        Commands::Diff { input, from, to } => {
+            let verifier = PdfVerifier::load(input)?;
+            let to = to.unwrap_or(verifier.revision_count());
+            let from = from.unwrap_or(to.saturating_sub(1));
+            print_diff(&verifier.diff(from, to)?);
+        }
+        Commands::Inspect { input } => inspect(input)?,
     };
     Ok(())
 }
 
+/// The exit code of `verify` for a document that does not pass. 1 is left to
+/// errors and 2 to usage errors.
+fn exit_code(verdict: Verdict) -> Option<i32> {
+    match verdict {
+        Verdict::Valid => None,
+        Verdict::Invalid => Some(3),
+        Verdict::Untrusted => Some(4),
+        Verdict::Modified => Some(5),
+        Verdict::Unsigned => Some(6),
+    }
+}
+
 #[derive(Debug, Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
@@ -64,9 +389,50 @@ enum Commands {
         /// Path to the output signed PDF file
         #[arg(short, long, default_value = "output/signed.pdf")]
         output: PathBuf,
-        /// Path to the certificate file
-        #[arg(short, long)]
+        /// Path to the certificate file, a PKCS#12 bundle unless `--key` is given
+        #[arg(short, long, conflicts_with = "module")]
         cert: Option<PathBuf>,
+        /// Path to the PKCS#8 private key (PEM or DER) of a PEM or DER certificate
+        #[arg(long, requires = "cert")]
+        key: Option<PathBuf>,
+        /// Path to the intermediate certificates (PEM bundle or DER) of `--key` signing
+        #[arg(long, requires = "key")]
+        chain: Option<PathBuf>,
+        /// Password of the PKCS#12 bundle or of the encrypted private key
+        #[arg(
+            long,
+            requires = "cert",
+            env = "PDF_SIGN_PASSWORD",
+            hide_env_values = true
+        )]
+        password: Option<String>,
+        /// Sign with a key on a PKCS#11 token instead of a PKCS#12 file
+        #[command(flatten)]
+        pkcs11: Box<Pkcs11Args>,
+        /// Create a PAdES baseline signature (ETSI.CAdES.detached)
+        #[arg(long)]
+        pades: bool,
+        /// Digest algorithm: sha256, sha384 or sha512. Defaults to sha256, sha512 for Ed25519
+        #[arg(long)]
+        digest: Option<DigestAlgorithm>,
+        /// Signature scheme: pkcs1, pss, ecdsa or ed25519. Defaults to the key type,
+        /// pkcs1 for RSA keys
+        #[arg(long)]
+        scheme: Option<SignatureScheme>,
+        /// Create a certification signature with the given DocMDP permission level:
+        /// 1 = no changes, 2 = form filling and signing, 3 = also annotations
+        #[arg(long, value_parser = clap::value_parser!(i64).range(1..=3))]
+        certify: Option<i64>,
+        /// Sign into this existing empty signature field instead of creating a new one,
+        /// the image is fitted to the field and `--pos`/`--size` are ignored
+        #[arg(long)]
+        field: Option<String>,
+        /// URL of an RFC 3161 time-stamping authority used to timestamp the signature
+        #[arg(long)]
+        tsa: Option<String>,
+        /// Bytes reserved for the signature, estimated from the certificates and the TSA if omitted
+        #[arg(long)]
+        placeholder_size: Option<usize>,
         /// Path to the seal image file
         #[arg(long)]
         image: Option<PathBuf>,
@@ -90,14 +456,221 @@ enum Commands {
         #[command(flatten)]
         signer_info: Box<SignerInfoArgs>,
     },
+    /// Add a document timestamp to a PDF document
+    Timestamp {
+        /// Path to the input PDF file
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Path to the output timestamped PDF file
+        #[arg(short, long, default_value = "output/timestamped.pdf")]
+        output: PathBuf,
+        /// URL of the RFC 3161 time-stamping authority
+        #[arg(long)]
+        tsa: String,
+        /// Bytes reserved for the timestamp token
+        #[arg(long)]
+        placeholder_size: Option<usize>,
+    },
+    /// Embed validation material of the signatures in a Document Security Store
+    Dss {
+        /// Path to the signed PDF file
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Path to the output PDF file
+        #[arg(short, long, default_value = "output/ltv.pdf")]
+        output: PathBuf,
+        /// Extra certificate files (PEM or DER) to embed, such as the issuer chain
+        #[arg(long)]
+        cert: Vec<PathBuf>,
+        /// CRL files (PEM or DER) to embed
+        #[arg(long)]
+        crl: Vec<PathBuf>,
+        /// OCSP response files (DER) to embed
+        #[arg(long)]
+        ocsp: Vec<PathBuf>,
+        /// Fetch missing CRLs and OCSP responses from the URLs in the certificates
+        #[arg(long)]
+        fetch: bool,
+    },
+    /// Add empty signature fields to be signed later
+    PrepareFields {
+        /// Path to the input PDF file
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Path to the output PDF file
+        #[arg(short, long, default_value = "output/prepared.pdf")]
+        output: PathBuf,
+        /// Field to add as `NAME:PAGE:X1,Y1,X2,Y2[:TOOLTIP]`, may be repeated
+        #[arg(long, required = true, value_parser = parse_field_spec)]
+        field: Vec<FieldSpec>,
+        /// Require PAdES signatures (ETSI.CAdES.detached) in the fields
+        #[arg(long)]
+        require_pades: bool,
+        /// Accepted reason for signing, may be repeated
+        #[arg(long)]
+        reason: Vec<String>,
+        /// Accepted digest method such as SHA256, may be repeated
+        #[arg(long)]
+        digest_method: Vec<String>,
+        /// PEM file of accepted issuers of the signer certificate, may be repeated
+        #[arg(long)]
+        issuer: Vec<PathBuf>,
+        /// Field locked once the new fields are signed, may be repeated
+        #[arg(long, conflicts_with = "lock_all")]
+        lock: Vec<String>,
+        /// Lock every field once the new fields are signed
+        #[arg(long)]
+        lock_all: bool,
+    },
+    /// Write a signature placeholder and print the digest to sign externally
+    Prepare {
+        /// Path to the input PDF file
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Path to the prepared PDF file, the state passed to `complete`
+        #[arg(short, long, default_value = "output/unsigned.pdf")]
+        output: PathBuf,
+        /// The signature will be a PAdES signature (ETSI.CAdES.detached)
+        #[arg(long)]
+        pades: bool,
+        /// Digest algorithm of the printed digest: sha256, sha384 or sha512
+        #[arg(long, default_value = "sha256")]
+        digest: DigestAlgorithm,
+        /// Also write the bytes covered by the ByteRange to this file
+        #[arg(long)]
+        data: Option<PathBuf>,
+        /// Bytes reserved for the signature, a fixed default if omitted
+        #[arg(long)]
+        placeholder_size: Option<usize>,
+        /// Information about the signer
+        #[command(flatten)]
+        signer_info: Box<SignerInfoArgs>,
+    },
+    /// Fill the placeholder of a prepared PDF with an externally created signature
+    Complete {
+        /// Path to the prepared PDF file
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Path to the output signed PDF file
+        #[arg(short, long, default_value = "output/signed.pdf")]
+        output: PathBuf,
+        /// Path to the DER encoded CMS signature
+        #[arg(long)]
+        cms: PathBuf,
+        /// Digest algorithm the document was prepared with: sha256, sha384 or sha512
+        #[arg(long, default_value = "sha256")]
+        digest: DigestAlgorithm,
+        /// URL of an RFC 3161 time-stamping authority used to timestamp the signature
+        #[arg(long)]
+        tsa: Option<String>,
+    },
     /// Verify a signed PDF document
     Verify {
         /// Path to the signed PDF file
         #[arg(short, long)]
         input: PathBuf,
+        /// Trust anchors: a PEM bundle, a DER certificate or a directory of them.
+        /// The certificate paths of the signers are validated when given
+        #[arg(long)]
+        trust: Vec<PathBuf>,
+        /// Check the revocation status of the signer certificates with the CRLs and
+        /// OCSP responses embedded in the document and the given files
+        #[arg(long)]
+        revocation: bool,
+        /// Extra certificate files (PEM or DER) to find issuers and OCSP responders in
+        #[arg(long, requires = "revocation")]
+        cert: Vec<PathBuf>,
+        /// CRL files (PEM or DER) to check revocation with
+        #[arg(long, requires = "revocation")]
+        crl: Vec<PathBuf>,
+        /// OCSP response files (DER) to check revocation with
+        #[arg(long, requires = "revocation")]
+        ocsp: Vec<PathBuf>,
+        /// Fetch CRLs and OCSP responses for the certificates the offline material does not cover
+        #[arg(long, requires = "revocation")]
+        fetch: bool,
+        /// Output format: text or json
+        #[arg(long, default_value = "text")]
+        format: ReportFormat,
+    },
+    /// Write out a revision of a signed PDF as a standalone PDF, such as the
+    /// revision a signer saw
+    ExtractRevision {
+        /// Path to the signed PDF file
+        #[arg(short, long)]
+        input: PathBuf,
+        /// Path to the output PDF file
+        #[arg(short, long, default_value = "output/revision.pdf")]
+        output: PathBuf,
+        /// Extract the bytes covered by the signature of this field
+        #[arg(
+            long,
+            conflicts_with = "revision",
+            required_unless_present = "revision"
+        )]
+        field: Option<String>,
+        /// Extract the Nth incremental revision, starting at 1
+        #[arg(long)]
+        revision: Option<usize>,
+    },
+    /// List the objects an incremental update added, modified or removed
+    Diff {
+        /// Path to the PDF file
+        #[arg(short, long)]
+        input: PathBuf,
+        /// The earlier revision, starting at 1. Defaults to the revision before `--to`
+        #[arg(long)]
+        from: Option<usize>,
+        /// The later revision. Defaults to the last revision
+        #[arg(long)]
+        to: Option<usize>,
+    },
+    /// List the pages, form fields, signatures and images of a PDF
+    Inspect {
+        /// Path to the PDF file
+        #[arg(short, long)]
+        input: PathBuf,
     },
 }
 
+#[derive(Debug, Clone, Copy)]
+enum ReportFormat {
+    Text,
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "text" => Ok(ReportFormat::Text),
+            "json" => Ok(ReportFormat::Json),
+            _ => Err(anyhow::anyhow!("Unsupported format: {}", s)),
+        }
+    }
+}
+
+#[derive(Debug, Args)]
+struct Pkcs11Args {
+    /// Path to the PKCS#11 module, such as /usr/lib/softhsm/libsofthsm2.so
+    #[arg(long)]
+    module: Option<PathBuf>,
+    /// Label of the token, the first token is used if omitted
+    #[arg(long, requires = "module")]
+    token: Option<String>,
+    /// Label of the private key. If omitted, the key of --pkcs11-cert is used,
+    /// or the first key with a certificate on the token
+    #[arg(long, requires = "module")]
+    key_label: Option<String>,
+    /// User PIN of the token
+    #[arg(long, requires = "module", env = "PKCS11_PIN", hide_env_values = true)]
+    pin: Option<String>,
+    /// PEM certificate of the key, read from the token if omitted
+    #[arg(long, requires = "module")]
+    pkcs11_cert: Option<PathBuf>,
+}
+
 #[derive(Debug, Args)]
 struct SignerInfoArgs {
     /// Name of the signer
@@ -129,6 +702,36 @@ impl From<Box<SignerInfoArgs>> for SignerInfo {
     }
 }
 
+#[derive(Debug, Clone)]
+struct FieldSpec {
+    name: String,
+    page: u32,
+    rect: [i64; 4],
+    tooltip: Option<String>,
+}
+
+fn parse_field_spec(s: &str) -> Result<FieldSpec, String> {
+    let parts: Vec<&str> = s.splitn(4, ':').collect();
+    if parts.len() < 3 {
+        return Err(format!("Invalid format: {}", s));
+    }
+    let page = parts[1]
+        .parse()
+        .map_err(|_| format!("Invalid page: {}", parts[1]))?;
+    let rect = parts[2]
+        .split(',')
+        .map(|n| n.parse().map_err(|_| format!("Invalid number: {}", n)))
+        .collect::<Result<Vec<i64>, _>>()?
+        .try_into()
+        .map_err(|_| format!("Invalid rectangle: {}", parts[2]))?;
+    Ok(FieldSpec {
+        name: parts[0].to_owned(),
+        page,
+        rect,
+        tooltip: parts.get(3).map(|t| t.to_string()),
+    })
+}
+
 fn parse_number_pair(s: &str) -> Result<(i64, i64), String> {
     let parts: Vec<&str> = s.split(',').collect();
     if parts.len() != 2 {
@@ -143,55 +746,317 @@ fn parse_number_pair(s: &str) -> Result<(i64, i64), String> {
     Ok((x, y))
 }
 
-fn verify(path: impl AsRef<Path>) -> Result<()> {
-    let doc = Document::load(&path)?;
-    let acro_dict = match doc.catalog()?.get(b"AcroForm")? {
-        Object::Reference(id) => doc.get_dictionary(*id)?,
-        Object::Dictionary(d) => d,
-        _ => panic!("Invalid AcroForm id"),
-    };
-    let field_id = acro_dict.get(b"Fields")?.as_array()?[0].as_reference()?;
-    let v_id = doc.get_dictionary(field_id)?.get(b"V")?.as_reference()?;
-    let v_dict = doc.get_dictionary(v_id)?;
-    let sign_contents = v_dict.get(b"Contents")?.as_str()?;
-    let byte_range = v_dict
-        .get(b"ByteRange")?
-        .as_array()?
-        .iter()
-        .map(|obj| obj.as_i64().map(|x| x as usize))
-        .collect::<Result<Vec<_>, _>>()?;
-
-    let mut file_bytes = vec![];
-    File::open(path)?.read_to_end(&mut file_bytes)?;
-
-    let mut signed_bytes = Vec::new();
-    signed_bytes.extend_from_slice(&file_bytes[byte_range[0]..byte_range[0] + byte_range[1]]);
-    signed_bytes.extend_from_slice(&file_bytes[byte_range[2]..byte_range[2] + byte_range[3]]);
-
-    std::fs::write("signature.der", sign_contents)?;
-    std::fs::write("signed_content.bin", &signed_bytes)?;
-
-    let output = Command::new("openssl")
-        .args([
-            "smime",
-            "-verify",
-            "-in",
-            "signature.der",
-            "-inform",
-            "DER",
-            "-content",
-            "signed_content.bin",
-            "-noverify",
-        ])
-        .output()?;
-
-    if output.status.success() {
-        println!("✅ 签名验证成功！");
-    } else {
-        println!("❌ 签名验证失败！");
-    }
-    std::fs::remove_file("signature.der")?;
-    std::fs::remove_file("signed_content.bin")?;
-    println!("{}", String::from_utf8_lossy(&output.stderr));
+fn verify(
+    path: impl AsRef<Path>,
+    trust: &[PathBuf],
+    revocation: Option<(ValidationData, Option<Box<dyn RevocationFetcher>>)>,
+    format: ReportFormat,
+) -> Result<Verdict> {
+    let mut verifier = PdfVerifier::load(path)?;
+    if let Some((data, fetcher)) = revocation {
+        verifier = verifier.with_revocation_check(data, fetcher);
+    }
+    if !trust.is_empty() {
+        let mut trust_store = TrustStore::new();
+        for path in trust {
+            trust_store.add_path(path)?;
+        }
+        verifier = verifier.with_trust_store(trust_store);
+    }
+    let results = verifier.verify()?;
+    let report = VerificationReport::new(&results, verifier.doc_mdp_permission());
+    if let ReportFormat::Json = format {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(report.verdict);
+    }
+    if results.is_empty() {
+        println!("❌ 未找到签名！");
+    }
+    for result in results {
+        let name = format!(
+            "[修订 {}] {}",
+            result.revision,
+            result.field_name.as_deref().unwrap_or("(无字段)")
+        );
+        let signer = result.signer_subject().unwrap_or_default();
+        let suspicious = result.suspicious_modifications().count();
+        match result.status {
+            SignatureStatus::Valid if suspicious > 0 => println!(
+                "⚠️ {} 签名有效，但签名后有 {} 处不允许的修改 {}",
+                name, suspicious, signer
+            ),
+            SignatureStatus::Valid => println!("✅ {} 签名验证成功！ {}", name, signer),
+            SignatureStatus::DigestMismatch => {
+                println!("❌ {} 签名验证失败: 签名后内容被修改 {}", name, signer)
+            }
+            SignatureStatus::InvalidSignature => {
+                println!("❌ {} 签名验证失败: 签名值无效 {}", name, signer)
+            }
+            SignatureStatus::Error(err) => println!("❌ {} 签名验证失败: {}", name, err),
+        }
+        if let Some(timestamp) = &result.timestamp {
+            let time = timestamp
+                .time
+                .map(|time| DateTime::<Utc>::from(time).to_rfc3339())
+                .unwrap_or_else(|| "-".to_owned());
+            let tsa = timestamp.tsa_subject().unwrap_or_default();
+            match (&timestamp.status, &timestamp.trust) {
+                (SignatureStatus::Valid, Some(TrustStatus::Untrusted(reason))) => {
+                    println!(
+                        "    ❌ 时间戳 {} {}: TSA 证书不受信任: {}",
+                        time, tsa, reason
+                    )
+                }
+                (SignatureStatus::Valid, _) => println!("    时间戳 {} {}", time, tsa),
+                (SignatureStatus::DigestMismatch, _) => {
+                    println!("    ❌ 时间戳 {} {}: 与签名值不符", time, tsa)
+                }
+                (SignatureStatus::InvalidSignature, _) => {
+                    println!("    ❌ 时间戳 {} {}: TSA 签名无效", time, tsa)
+                }
+                (SignatureStatus::Error(err), _) => println!("    ❌ 时间戳无效: {}", err),
+            }
+        }
+        if let Some(time) = result.validation_time
+            && (result.trust.is_some() || !result.revocation.is_empty())
+        {
+            println!(
+                "    证书验证时间 {}",
+                DateTime::<Utc>::from(time).to_rfc3339()
+            );
+        }
+        match &result.trust {
+            Some(TrustStatus::Trusted) => println!("    证书可信"),
+            Some(TrustStatus::Untrusted(reason)) => println!("    ❌ 证书不受信任: {}", reason),
+            None => {}
+        }
+        for check in &result.revocation {
+            let subject = check.subject();
+            let source = match check.source {
+                Some(RevocationSource::Ocsp) => "OCSP",
+                Some(RevocationSource::Crl) => "CRL",
+                None => "-",
+            };
+            match &check.status {
+                RevocationStatus::Good => println!("    {} 未吊销 ({})", subject, source),
+                RevocationStatus::Revoked {
+                    time: Some(time), ..
+                } if result
+                    .validation_time
+                    .is_some_and(|validation| *time > validation) =>
+                {
+                    println!(
+                        "    {} 在验证时间之后吊销 ({}), 时间 {}",
+                        subject,
+                        source,
+                        DateTime::<Utc>::from(*time).to_rfc3339()
+                    )
+                }
+                RevocationStatus::Revoked { reason, time } => println!(
+                    "    ❌ {} 已吊销 ({}): 原因 {}, 时间 {}",
+                    subject,
+                    source,
+                    reason.as_deref().unwrap_or("-"),
+                    time.map(|time| DateTime::<Utc>::from(time).to_rfc3339())
+                        .unwrap_or_else(|| "-".to_owned())
+                ),
+                RevocationStatus::Unknown => println!("    ⚠️ {} 吊销状态未知", subject),
+            }
+        }
+        if !result.covers_whole_file {
+            println!("    签名只覆盖到修订 {}，之后的修改:", result.revision);
+        }
+        for modification in &result.modifications {
+            let object = match modification.object_id {
+                Some((num, generation)) => format!("对象 {} {}", num, generation),
+                None => "整个修订".to_owned(),
+            };
+            println!(
+                "    {} 修订 {}: {} ({})",
+                if modification.allowed {
+                    "允许"
+                } else {
+                    "可疑"
+                },
+                modification.revision,
+                modification_text(modification.kind),
+                object
+            );
+        }
+    }
+    Ok(report.verdict)
+}
+
+fn inspect(path: impl AsRef<Path>) -> Result<()> {
+    let info = DocumentInfo::load(path)?;
+    println!("PDF {}, {} 个修订", info.version, info.revisions);
+    println!("页面 ({}):", info.pages.len());
+    for page in &info.pages {
+        println!(
+            "  第 {} 页 (对象 {} {}): MediaBox {}, CropBox {}, Rotate {}, {} 个注释",
+            page.number,
+            page.id.0,
+            page.id.1,
+            numbers_text(page.media_box.as_deref()),
+            numbers_text(page.crop_box.as_deref()),
+            page.rotate,
+            page.annotations
+        );
+        for image in &page.images {
+            println!(
+                "    图片 /{} (对象 {} {}): {}x{}, {} 位, {}{}",
+                image.name,
+                image.id.0,
+                image.id.1,
+                image.width,
+                image.height,
+                image
+                    .bits_per_component
+                    .map(|bits| bits.to_string())
+                    .unwrap_or("-".to_owned()),
+                image.color_space.as_deref().unwrap_or("-"),
+                if image.has_soft_mask {
+                    ", 带透明蒙版"
+                } else {
+                    ""
+                }
+            );
+            for matrix in &image.placements {
+                println!("      绘制于 cm {}", numbers_text(Some(matrix)));
+            }
+        }
+    }
+    println!("表单字段 ({}):", info.fields.len());
+    for field in &info.fields {
+        let state = match (field.field_type.as_deref(), field.signed) {
+            (Some("Sig"), true) => "已签名".to_owned(),
+            (Some("Sig"), false) => "未签名".to_owned(),
+            _ => field.value.clone().unwrap_or_default(),
+        };
+        println!(
+            "  {} [{}] {} 第 {} 页 Rect {}",
+            field.name,
+            field.field_type.as_deref().unwrap_or("-"),
+            state,
+            field
+                .page
+                .map(|page| page.to_string())
+                .unwrap_or("-".to_owned()),
+            numbers_text(field.rect.as_deref())
+        );
+    }
+    println!("签名 ({}):", info.signatures.len());
+    for sig in &info.signatures {
+        println!(
+            "  {} (对象 {} {}): {} {}",
+            sig.field_name.as_deref().unwrap_or("(无字段)"),
+            sig.id.0,
+            sig.id.1,
+            sig.sig_type.as_deref().unwrap_or("-"),
+            sig.sub_filter.as_deref().unwrap_or("-")
+        );
+        let entries = [
+            ("Name", &sig.name),
+            ("Reason", &sig.reason),
+            ("Location", &sig.location),
+            ("ContactInfo", &sig.contact_info),
+            ("M", &sig.signing_time),
+        ];
+        for (key, value) in entries {
+            if let Some(value) = value {
+                println!("    {}: {}", key, value);
+            }
+        }
+        println!(
+            "    ByteRange: {:?}, Contents {} 字节",
+            sig.byte_range, sig.contents_len
+        );
+        match &sig.signer_subject {
+            Some(subject) => println!("    证书: {}", subject),
+            None => println!("    证书: - (空签名)"),
+        }
+        if let Some(permission) = sig.doc_mdp {
+            println!("    认证签名, DocMDP P={}", permission as i64);
+        }
+    }
     Ok(())
 }
+
+fn numbers_text(numbers: Option<&[f32]>) -> String {
+    match numbers {
+        Some(numbers) => format!(
+            "[{}]",
+            numbers
+                .iter()
+                .map(|n| n.to_string())
+                .collect::<Vec<_>>()
+                .join(" ")
+        ),
+        None => "-".to_owned(),
+    }
+}
+
+fn print_diff(diff: &RevisionDiff) {
+    println!(
+        "修订 {} → {}: 新增 {} 个对象, 修改 {} 个, 删除 {} 个",
+        diff.from,
+        diff.to,
+        diff.count(ObjectChange::Added),
+        diff.count(ObjectChange::Modified),
+        diff.count(ObjectChange::Removed)
+    );
+    for object in &diff.objects {
+        let change = match object.change {
+            ObjectChange::Added => "新增",
+            ObjectChange::Modified => "修改",
+            ObjectChange::Removed => "删除",
+        };
+        let kind = object
+            .kind
+            .map(|kind| format!(" [{}]", modification_text(kind)))
+            .unwrap_or_default();
+        println!("  {} 对象 {} {}{}", change, object.id.0, object.id.1, kind);
+        for key in &object.keys {
+            println!(
+                "      /{}: {} → {}",
+                key.key,
+                key.old.as_ref().map(object_text).unwrap_or("-".to_owned()),
+                key.new.as_ref().map(object_text).unwrap_or("-".to_owned())
+            );
+        }
+        for change in &object.content {
+            let (sign, index, operation) = match change {
+                ContentChange::Added { index, operation } => ("+", index, operation),
+                ContentChange::Removed { index, operation } => ("-", index, operation),
+            };
+            let operands = operation.operands.iter().map(object_text);
+            let text = operands
+                .chain([operation.operator.clone()])
+                .collect::<Vec<_>>()
+                .join(" ");
+            println!("      {} {:>5}: {}", sign, index, text);
+        }
+    }
+}
+
+/// PDF syntax of an object, long values such as signature contents are cut.
+fn object_text(obj: &Object) -> String {
+    const MAX_CHARS: usize = 120;
+    let text = format!("{:?}", obj);
+    match text.char_indices().nth(MAX_CHARS) {
+        Some((end, _)) => format!("{}… ({} 字节)", &text[..end], text.len()),
+        None => text,
+    }
+}
+
+fn modification_text(kind: ModificationKind) -> &'static str {
+    match kind {
+        ModificationKind::Signature => "添加签名",
+        ModificationKind::Timestamp => "添加文档时间戳",
+        ModificationKind::Dss => "添加验证数据 (DSS)",
+        ModificationKind::FormFill => "填写表单",
+        ModificationKind::Annotation => "修改注释",
+        ModificationKind::PageContent => "修改页面内容",
+        ModificationKind::Object => "修改对象",
+    }
+}
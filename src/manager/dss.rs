@@ -0,0 +1,239 @@
+use std::path::Path;
+
+use anyhow::Result;
+use openssl::{
+    hash::MessageDigest,
+    ocsp::{OcspCertId, OcspResponse},
+    x509::{X509, X509Crl, X509Ref},
+};
+
+use crate::{
+    cms::{
+        decode_signed_data,
+        verify::{certificates, signature_timestamp_tokens, signer_certificate},
+    },
+    revocation::RevocationFetcher,
+    verify::{name_to_string, revocation::issuer_path},
+};
+
+/// Certificates, CRLs and OCSP responses to embed in the Document Security Store.
+#[derive(Debug, Clone, Default)]
+pub struct ValidationData {
+    pub(crate) certs: Vec<X509>,
+    pub(crate) crls: Vec<Vec<u8>>,
+    pub(crate) ocsps: Vec<Vec<u8>>,
+}
+
+impl ValidationData {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_cert(&mut self, cert: X509) -> Result<()> {
+        let der = cert.to_der()?;
+        for known in &self.certs {
+            if known.to_der()? == der {
+                return Ok(());
+            }
+        }
+        self.certs.push(cert);
+        Ok(())
+    }
+
+    /// Add every certificate of a PEM bundle or a single DER certificate.
+    pub fn add_cert_file(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let data = std::fs::read(path)?;
+        let certs = match X509::stack_from_pem(&data) {
+            Ok(certs) if !certs.is_empty() => certs,
+            _ => vec![X509::from_der(&data)?],
+        };
+        for cert in certs {
+            self.add_cert(cert)?;
+        }
+        Ok(())
+    }
+
+    pub fn add_crl(&mut self, der: Vec<u8>) -> Result<()> {
+        X509Crl::from_der(&der)?;
+        if !self.crls.contains(&der) {
+            self.crls.push(der);
+        }
+        Ok(())
+    }
+
+    /// Add a PEM or DER encoded CRL.
+    pub fn add_crl_file(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let data = std::fs::read(path)?;
+        let crl = match X509Crl::from_pem(&data) {
+            Ok(crl) => crl,
+            Err(_) => X509Crl::from_der(&data)?,
+        };
+        self.add_crl(crl.to_der()?)
+    }
+
+    pub fn add_ocsp(&mut self, der: Vec<u8>) -> Result<()> {
+        OcspResponse::from_der(&der)?;
+        if !self.ocsps.contains(&der) {
+            self.ocsps.push(der);
+        }
+        Ok(())
+    }
+
+    /// Add a DER encoded OCSPResponse.
+    pub fn add_ocsp_file(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        self.add_ocsp(std::fs::read(path)?)
+    }
+
+    /// Add the certificates carried by a CMS signature and by its signature
+    /// timestamp tokens, and return the certificates that signed them.
+    pub(crate) fn add_cms_certs(&mut self, cms: &[u8]) -> Result<Vec<X509>> {
+        let signed_data = decode_signed_data(cms)?;
+        let mut signed_datas = signature_timestamp_tokens(&signed_data)?;
+        signed_datas.insert(0, signed_data);
+        let mut signers = Vec::new();
+        for signed_data in &signed_datas {
+            for cert in certificates(signed_data)? {
+                self.add_cert(cert)?;
+            }
+            signers.push(signer_certificate(signed_data)?);
+        }
+        Ok(signers)
+    }
+
+    /// Gather the material that validates `signers`: their paths through the
+    /// known certificates and the revocation information of every certificate
+    /// of those paths with a known issuer, an OCSP response is preferred over
+    /// CRLs. What is not held already is fetched with `fetcher`, and what
+    /// cannot be fetched is added to `missing`.
+    pub(crate) fn collect(
+        &mut self,
+        signers: &[X509],
+        fetcher: Option<&dyn RevocationFetcher>,
+        missing: &mut Vec<MissingRevocation>,
+    ) -> Result<SignatureMaterial> {
+        let mut material = SignatureMaterial::default();
+        for signer in signers {
+            push_unique(&mut material.certs, signer.clone())?;
+            for (cert, issuer) in issuer_path(signer, &self.certs) {
+                let Some(issuer) = issuer else {
+                    continue;
+                };
+                self.collect_revocation(&cert, &issuer, fetcher, &mut material, missing)?;
+                push_unique(&mut material.certs, issuer)?;
+            }
+        }
+        Ok(material)
+    }
+
+    fn collect_revocation(
+        &mut self,
+        cert: &X509Ref,
+        issuer: &X509Ref,
+        fetcher: Option<&dyn RevocationFetcher>,
+        material: &mut SignatureMaterial,
+        missing: &mut Vec<MissingRevocation>,
+    ) -> Result<()> {
+        let mut ocsps = self
+            .ocsps
+            .iter()
+            .filter(|ocsp| ocsp_covers(ocsp, cert, issuer))
+            .cloned()
+            .collect::<Vec<_>>();
+        let mut crls = match ocsps.is_empty() {
+            true => self
+                .crls
+                .iter()
+                .filter(|crl| crl_covers(crl, cert, issuer))
+                .cloned()
+                .collect(),
+            false => Vec::new(),
+        };
+        if let Some(fetcher) = fetcher
+            && ocsps.is_empty()
+            && crls.is_empty()
+        {
+            let fetched = fetcher
+                .fetch_ocsp(cert, issuer)
+                .and_then(|ocsp| match ocsp {
+                    Some(ocsp) => self.add_ocsp(ocsp.clone()).map(|_| ocsps.push(ocsp)),
+                    None => fetcher.fetch_crls(cert)?.into_iter().try_for_each(|crl| {
+                        self.add_crl(crl.clone())?;
+                        crls.push(crl);
+                        Ok(())
+                    }),
+                });
+            let reason = match fetched {
+                Err(err) => Some(err.to_string()),
+                Ok(()) if ocsps.is_empty() && crls.is_empty() => {
+                    Some("No OCSP responder or CRL distribution point".to_owned())
+                }
+                Ok(()) => None,
+            };
+            if let Some(reason) = reason {
+                missing.push(MissingRevocation {
+                    subject: name_to_string(cert.subject_name()),
+                    reason,
+                });
+            }
+        }
+        for ocsp in ocsps {
+            if !material.ocsps.contains(&ocsp) {
+                material.ocsps.push(ocsp);
+            }
+        }
+        for crl in crls {
+            if !material.crls.contains(&crl) {
+                material.crls.push(crl);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The validation material of one signature, the content of its `/VRI` entry.
+#[derive(Debug, Default)]
+pub(crate) struct SignatureMaterial {
+    pub(crate) certs: Vec<X509>,
+    pub(crate) crls: Vec<Vec<u8>>,
+    pub(crate) ocsps: Vec<Vec<u8>>,
+}
+
+/// Revocation information that could not be obtained for a certificate.
+#[derive(Debug, Clone)]
+pub struct MissingRevocation {
+    /// The subject of the certificate, such as `CN=Alice, O=Example`.
+    pub subject: String,
+    /// Why the fetch failed.
+    pub reason: String,
+}
+
+fn push_unique(certs: &mut Vec<X509>, cert: X509) -> Result<()> {
+    let der = cert.to_der()?;
+    for known in certs.iter() {
+        if known.to_der()? == der {
+            return Ok(());
+        }
+    }
+    certs.push(cert);
+    Ok(())
+}
+
+/// A CRL of the issuer of `cert`, signed by it.
+fn crl_covers(crl: &[u8], cert: &X509Ref, issuer: &X509Ref) -> bool {
+    let (Ok(crl), Ok(key)) = (X509Crl::from_der(crl), issuer.public_key()) else {
+        return false;
+    };
+    crl.issuer_name().to_der().ok() == cert.issuer_name().to_der().ok()
+        && crl.verify(&key).unwrap_or(false)
+}
+
+/// A successful OCSP response with a status for `cert`.
+fn ocsp_covers(response: &[u8], cert: &X509Ref, issuer: &X509Ref) -> bool {
+    let Ok(basic) = OcspResponse::from_der(response).and_then(|ocsp| ocsp.basic()) else {
+        return false;
+    };
+    [MessageDigest::sha1(), MessageDigest::sha256()]
+        .into_iter()
+        .filter_map(|md| OcspCertId::from_cert(md, cert, issuer).ok())
+        .any(|id| basic.find_status(&id).is_some())
+}
@@ -0,0 +1,13 @@
+use super::{mdp::FieldLock, seed::SeedValue};
+
+/// Optional entries of an empty signature field created by
+/// [`PDFSignManager::add_signature_field`](super::PDFSignManager::add_signature_field).
+#[derive(Debug, Clone, Default)]
+pub struct SignatureFieldOptions {
+    /// The `/TU` shown by viewers when hovering over the field.
+    pub tooltip: Option<String>,
+    /// Constraints on the signature put into the field.
+    pub seed_value: Option<SeedValue>,
+    /// Fields locked once the field is signed.
+    pub lock: Option<FieldLock>,
+}
@@ -101,8 +101,8 @@ struct Divider {
 
 impl Divider {
     fn new(total: usize, parts: usize) -> Self {
-        let base = if parts == 0 { 0 } else { total / parts };
-        let remainder = if parts == 0 { 0 } else { total % parts };
+        let base = total.checked_div(parts).unwrap_or(0);
+        let remainder = total.checked_rem(parts).unwrap_or(0);
         Self {
             parts,
             base,
@@ -0,0 +1,160 @@
+use anyhow::Result;
+use lopdf::{Dictionary, Document, Object, decode_text_string, dictionary};
+
+/// The `/P` value of a DocMDP transform, the changes allowed after certification.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DocMdpPermission {
+    /// No changes to the document are permitted.
+    NoChanges = 1,
+    /// Filling in forms, instantiating page templates and signing are permitted.
+    FormFilling = 2,
+    /// Form filling and signing, plus annotation creation, deletion and modification.
+    Annotations = 3,
+}
+
+impl TryFrom<i64> for DocMdpPermission {
+    type Error = anyhow::Error;
+
+    fn try_from(value: i64) -> Result<Self, Self::Error> {
+        match value {
+            1 => Ok(DocMdpPermission::NoChanges),
+            2 => Ok(DocMdpPermission::FormFilling),
+            3 => Ok(DocMdpPermission::Annotations),
+            _ => Err(anyhow::anyhow!("Invalid DocMDP permission: {}", value)),
+        }
+    }
+}
+
+impl DocMdpPermission {
+    /// The signature reference dictionary placed in the `/Reference` array.
+    pub(crate) fn sig_ref(&self) -> Dictionary {
+        dictionary! {
+            "Type" => "SigRef",
+            "TransformMethod" => "DocMDP",
+            "TransformParams" => dictionary! {
+                "Type" => "TransformParams",
+                "P" => Object::Integer(*self as i64),
+                "V" => "1.2",
+            },
+        }
+    }
+}
+
+/// The signature reference dictionary of a signature with the transform `method`.
+fn sig_ref<'a>(sig: &'a Dictionary, doc: &'a Document, method: &[u8]) -> Option<&'a Dictionary> {
+    let references = sig
+        .get_deref(b"Reference", doc)
+        .and_then(Object::as_array)
+        .ok()?;
+    references
+        .iter()
+        .filter_map(|reference| doc.dereference(reference).ok())
+        .filter_map(|(_, reference)| reference.as_dict().ok())
+        .find(|reference| {
+            reference
+                .get(b"TransformMethod")
+                .and_then(Object::as_name)
+                .ok()
+                == Some(method)
+        })
+}
+
+/// The DocMDP permission of a certification signature dictionary, `None` if
+/// its `/Reference` has no DocMDP transform.
+pub(crate) fn doc_mdp_permission(sig: &Dictionary, doc: &Document) -> Option<DocMdpPermission> {
+    sig_ref(sig, doc, b"DocMDP")
+        .map(|reference| {
+            // `/P` defaults to 2 when the transform parameters omit it
+            reference
+                .get_deref(b"TransformParams", doc)
+                .and_then(Object::as_dict)
+                .and_then(|params| params.get(b"P"))
+                .and_then(Object::as_i64)
+                .unwrap_or(2)
+        })
+        .and_then(|p| DocMdpPermission::try_from(p).ok())
+}
+
+/// The `/Lock` dictionary of a signature field, the fields that may no longer
+/// change once the field is signed (FieldMDP). Field names are fully qualified.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum FieldLock {
+    /// Every field of the document.
+    All,
+    /// Only the listed fields.
+    Include(Vec<String>),
+    /// Every field except the listed ones.
+    Exclude(Vec<String>),
+}
+
+impl FieldLock {
+    /// Whether the field with the fully qualified `name` is locked.
+    pub(crate) fn locks(&self, name: &str) -> bool {
+        match self {
+            FieldLock::All => true,
+            FieldLock::Include(fields) => fields.iter().any(|field| field == name),
+            FieldLock::Exclude(fields) => fields.iter().all(|field| field != name),
+        }
+    }
+
+    pub(crate) fn to_dict(&self) -> Dictionary {
+        let (action, fields) = match self {
+            FieldLock::All => ("All", None),
+            FieldLock::Include(fields) => ("Include", Some(fields)),
+            FieldLock::Exclude(fields) => ("Exclude", Some(fields)),
+        };
+        let mut lock = dictionary! {
+            "Type" => "SigFieldLock",
+            "Action" => action,
+        };
+        if let Some(fields) = fields {
+            let fields = fields.iter().map(|f| Object::string_literal(f.as_str()));
+            lock.set("Fields", fields.collect::<Vec<_>>());
+        }
+        lock
+    }
+}
+
+/// The fields locked by the FieldMDP transform of a signature dictionary,
+/// `None` if its `/Reference` has none.
+pub(crate) fn field_mdp_lock(sig: &Dictionary, doc: &Document) -> Option<FieldLock> {
+    let params = sig_ref(sig, doc, b"FieldMDP")?
+        .get_deref(b"TransformParams", doc)
+        .and_then(Object::as_dict)
+        .ok()?;
+    let fields = || {
+        params
+            .get_deref(b"Fields", doc)
+            .and_then(Object::as_array)
+            .map(|fields| {
+                fields
+                    .iter()
+                    .filter_map(|field| decode_text_string(field).ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    };
+    match params.get(b"Action").and_then(Object::as_name).ok()? {
+        b"All" => Some(FieldLock::All),
+        b"Include" => Some(FieldLock::Include(fields())),
+        b"Exclude" => Some(FieldLock::Exclude(fields())),
+        _ => None,
+    }
+}
+
+/// The FieldMDP signature reference dictionary for the `/Lock` of the signed field.
+pub(crate) fn field_mdp_sig_ref(doc: &Document, lock: &Dictionary) -> Result<Dictionary> {
+    let mut params = dictionary! {
+        "Type" => "TransformParams",
+        "Action" => lock.get(b"Action")?.clone(),
+        "V" => "1.2",
+    };
+    if let Ok(fields) = lock.get_deref(b"Fields", doc) {
+        params.set("Fields", fields.clone());
+    }
+    Ok(dictionary! {
+        "Type" => "SigRef",
+        "TransformMethod" => "FieldMDP",
+        "TransformParams" => params,
+    })
+}
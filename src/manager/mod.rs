@@ -1,28 +1,50 @@
-use std::{io::Write, path::Path};
+use std::{borrow::Cow, io::Write, path::Path};
 
 use anyhow::Result;
 use lopdf::{
-    IncrementalDocument, Object, ObjectId, Stream, StringFormat,
+    Dictionary, Document, IncrementalDocument, Object, ObjectId, Stream, StringFormat,
     content::{Content, Operation},
     dictionary,
 };
+use openssl::hash::{MessageDigest, hash};
 
+pub use self::dss::{MissingRevocation, ValidationData};
+pub use self::field::SignatureFieldOptions;
 use self::image::ImageHelper;
 pub use self::image::ImageRect;
+use self::mdp::field_mdp_sig_ref;
+pub use self::mdp::{DocMdpPermission, FieldLock};
+pub use self::prepared::PreparedSignature;
+pub use self::seed::SeedValue;
+use self::seed::apply_seed_value;
 pub use self::sign_info::SignerInfo;
 use crate::{
-    config::SIG_CONTENTS_PLACEHOLDER_LEN,
+    config::{SIG_CONTENTS_MARGIN, SIG_CONTENTS_PLACEHOLDER_LEN, TIMESTAMP_TOKEN_RESERVE},
     parser::RawPdf,
-    signer::Sign,
-    utils::{AcroForm, Page, PageMut},
+    revocation::RevocationFetcher,
+    signer::{DigestAlgorithm, Sign, SubFilter},
+    tsa::{DocumentTimestamper, TimestampAuthority},
+    utils::{
+        AcroForm, Dss, Page, PageMut, field_names, revision, revision_ends, signature_contents,
+        signed_revision, terminal_fields,
+    },
 };
 
+pub(crate) mod dss;
+pub(crate) mod field;
 pub(crate) mod image;
+pub(crate) mod mdp;
+pub(crate) mod prepared;
+pub(crate) mod seed;
 pub(crate) mod sign_info;
 
 pub struct PDFSignManager {
     doc: IncrementalDocument,
     raw_pdf: RawPdf,
+    /// The pending revision was started on top of `raw_pdf`, the signed output.
+    continues_raw_pdf: bool,
+    tsa: Option<Box<dyn TimestampAuthority>>,
+    placeholder_len: Option<usize>,
 }
 
 impl PDFSignManager {
@@ -31,10 +53,50 @@ impl PDFSignManager {
         let mut doc = IncrementalDocument::load(pdf_path)?;
         doc.new_document.version = "1.7".into();
         let raw_pdf = RawPdf::empty();
-        Ok(PDFSignManager { doc, raw_pdf })
+        Ok(PDFSignManager {
+            doc,
+            raw_pdf,
+            continues_raw_pdf: false,
+            tsa: None,
+            placeholder_len: None,
+        })
     }
 
-    fn add_sig_obj(&mut self, signer_info: SignerInfo) -> ObjectId {
+    /// Embed a signature timestamp from `tsa` in every following signature (PAdES B-T).
+    pub fn set_timestamp_authority(&mut self, tsa: impl TimestampAuthority + 'static) {
+        self.tsa = Some(Box::new(tsa));
+    }
+
+    /// Reserve `len` bytes for the DER signature in every following `/Contents`
+    /// placeholder. By default the size is estimated from the certificates and the
+    /// key of the signer and from the timestamp authority, [`PDFSignManager::prepare`]
+    /// reserves a fixed default. A signature larger than an estimate is signed
+    /// again in a placeholder of its size, signing fails if it does not fit `len`.
+    pub fn set_placeholder_size(&mut self, len: usize) {
+        self.placeholder_len = Some(len);
+    }
+
+    /// The number of incremental revisions of the loaded file.
+    pub fn revision_count(&self) -> usize {
+        revision_ends(self.doc.get_prev_documents_bytes()).len()
+    }
+
+    /// The bytes of the 1-based revision `n` of the loaded file, a standalone PDF.
+    pub fn revision(&self, n: usize) -> Result<&[u8]> {
+        revision(self.doc.get_prev_documents_bytes(), n)
+    }
+
+    /// The bytes of the loaded file covered by the signature of the field
+    /// `field_name`, the revision the signer saw.
+    pub fn signed_revision(&self, field_name: &str) -> Result<&[u8]> {
+        signed_revision(
+            self.doc.get_prev_documents_bytes(),
+            self.doc.get_prev_documents(),
+            field_name,
+        )
+    }
+
+    fn add_sig_obj(&mut self, signer_info: SignerInfo, sub_filter: SubFilter) -> ObjectId {
         let byte_range_placeholder = vec![
             Object::Integer(0),
             Object::Name("**********".into()),
@@ -43,10 +105,14 @@ impl PDFSignManager {
         ];
         let sig_placeholder = vec![0; SIG_CONTENTS_PLACEHOLDER_LEN];
 
+        let sig_type = match sub_filter {
+            SubFilter::Rfc3161 => "DocTimeStamp",
+            _ => "Sig",
+        };
         let mut sig_dict = dictionary! {
-            "Type" => "Sig",
+            "Type" => sig_type,
             "Filter" => "Adobe.PPKLite",
-            "SubFilter" => "adbe.pkcs7.detached",
+            "SubFilter" => sub_filter.as_name(),
             "ByteRange" =>  byte_range_placeholder,
             "Contents" => Object::String(sig_placeholder, StringFormat::Hexadecimal),
             "Prop_Build" => dictionary! {
@@ -71,6 +137,15 @@ impl PDFSignManager {
         self.doc.new_document.add_object(sig_dict)
     }
 
+    /// The first `SignatureN` name not used by a top level field.
+    fn next_field_name(&self) -> String {
+        let names = field_names(&self.current_document());
+        (1..)
+            .map(|i| format!("Signature{}", i))
+            .find(|name| !names.contains(name))
+            .expect("field names are finite")
+    }
+
     fn add_sig_annot_obj(
         &mut self,
         ap_normal_id: ObjectId,
@@ -92,7 +167,7 @@ impl PDFSignManager {
             "Subtype" => "Widget",
             "FT" => "Sig",
             "Rect" => rect.into_iter().map(Object::Integer).collect::<Vec<_>>(),
-            "T" => Object::string_literal("Signature1"),
+            "T" => Object::string_literal(self.next_field_name()),
             "V" => sig_id,
             "F" => 4,
             "P" => page_id,
@@ -103,6 +178,24 @@ impl PDFSignManager {
         self.doc.new_document.add_object(sig_annot)
     }
 
+    /// The previous revisions with the pending changes of the new revision applied,
+    /// borrowed as long as there are no pending changes.
+    fn current_document(&self) -> Cow<'_, Document> {
+        let prev = self.doc.get_prev_documents();
+        if self.doc.new_document.objects.is_empty() {
+            return Cow::Borrowed(prev);
+        }
+        let mut current = prev.clone();
+        current.objects.extend(
+            self.doc
+                .new_document
+                .objects
+                .iter()
+                .map(|(id, obj)| (*id, obj.clone())),
+        );
+        Cow::Owned(current)
+    }
+
     fn clone_root(&mut self) -> Result<ObjectId> {
         let root_id = self
             .doc
@@ -114,7 +207,7 @@ impl PDFSignManager {
         Ok(root_id)
     }
 
-    fn get_or_create_acro_form_mut(&mut self) -> Result<AcroForm> {
+    fn get_or_create_acro_form_mut(&mut self) -> Result<AcroForm<'_>> {
         self.clone_root()?;
         let acro_id = match self.doc.new_document.catalog()?.get(b"AcroForm") {
             Ok(acro_id) => {
@@ -138,13 +231,42 @@ impl PDFSignManager {
         Ok(AcroForm::new(acro_form))
     }
 
-    fn get_page_mut(&mut self, page_id: ObjectId) -> Result<PageMut> {
+    /// Clone `/DSS` into the new revision, inlining indirect entries so they can be extended.
+    fn get_or_create_dss_mut(&mut self) -> Result<Dss<'_>> {
+        self.clone_root()?;
+        let dss_id = match self.doc.new_document.catalog()?.get(b"DSS") {
+            Ok(Object::Reference(dss_id)) => {
+                let dss_id = *dss_id;
+                self.doc.opt_clone_object_to_new_document(dss_id)?;
+                dss_id
+            }
+            Ok(Object::Dictionary(dss)) => {
+                let dss = dss.clone();
+                self.doc.new_document.add_object(dss)
+            }
+            _ => self.doc.new_document.add_object(Dictionary::new()),
+        };
+        self.doc.new_document.catalog_mut()?.set("DSS", dss_id);
+
+        let prev = self.doc.get_prev_documents();
+        let mut dss = self.doc.new_document.get_dictionary(dss_id)?.clone();
+        for (_, value) in dss.iter_mut() {
+            if let Object::Reference(id) = value {
+                *value = prev.get_object(*id)?.clone();
+            }
+        }
+        let dss_dict = self.doc.new_document.get_dictionary_mut(dss_id)?;
+        *dss_dict = dss;
+        Ok(Dss::new(dss_dict))
+    }
+
+    fn get_page_mut(&mut self, page_id: ObjectId) -> Result<PageMut<'_>> {
         self.doc.opt_clone_object_to_new_document(page_id)?;
         let page = self.doc.new_document.get_dictionary_mut(page_id)?;
         Ok(PageMut::new(page))
     }
 
-    fn get_page(&self, page_id: ObjectId) -> Result<Page> {
+    fn get_page(&self, page_id: ObjectId) -> Result<Page<'_>> {
         let page = self.doc.get_prev_documents().get_dictionary(page_id)?;
         Ok(Page::new(page))
     }
@@ -276,17 +398,190 @@ impl PDFSignManager {
         page_id: ObjectId,
         signer_info: SignerInfo,
         img: Option<ImageRect<impl AsRef<Path>>>,
-    ) -> Result<()> {
-        let sig_id = self.add_sig_obj(signer_info);
+        sub_filter: SubFilter,
+    ) -> Result<ObjectId> {
+        let sig_id = self.add_sig_obj(signer_info, sub_filter);
         let ap_normal_id = self.add_ap_normal(img.as_ref())?;
         let sig_annot_id = self.add_sig_annot_obj(ap_normal_id, sig_id, page_id, img.as_ref());
+        self.add_widget_field(page_id, sig_annot_id)?;
+        let mut acro_form = self.get_or_create_acro_form_mut()?;
+        acro_form.set(b"SigFlags", Object::Integer(3));
+        Ok(sig_id)
+    }
+
+    /// Register a merged field/widget dictionary in the page `/Annots` and the AcroForm `/Fields`.
+    fn add_widget_field(&mut self, page_id: ObjectId, widget_id: ObjectId) -> Result<()> {
         let mut page = self.get_page_mut(page_id)?;
         let annots = page.get_or_create_annots_mut()?;
-        annots.push(sig_annot_id.into());
+        annots.push(widget_id.into());
         let mut acro_form = self.get_or_create_acro_form_mut()?;
-        acro_form.set(b"SigFlags", Object::Integer(3));
         let fields = acro_form.get_fields_mut()?;
-        fields.push(sig_annot_id.into());
+        fields.push(widget_id.into());
+        Ok(())
+    }
+
+    /// Add an empty signature field to `page` (1-based) for a later signer,
+    /// `rect` is `[x1, y1, x2, y2]` in default user space.
+    pub fn add_signature_field(
+        &mut self,
+        page: u32,
+        rect: [i64; 4],
+        name: &str,
+        options: SignatureFieldOptions,
+    ) -> Result<()> {
+        if name.is_empty() || name.contains('.') {
+            return Err(anyhow::anyhow!("Invalid field name: {:?}", name));
+        }
+        self.revise(|manager| {
+            if field_names(&manager.current_document())
+                .iter()
+                .any(|known| known == name)
+            {
+                return Err(anyhow::anyhow!("Field {} already exists", name));
+            }
+            let page_id = *manager
+                .doc
+                .get_prev_documents()
+                .get_pages()
+                .get(&page)
+                .ok_or_else(|| anyhow::anyhow!("Page {} not found", page))?;
+
+            let mut field = dictionary! {
+                "Type" => "Annot",
+                "Subtype" => "Widget",
+                "FT" => "Sig",
+                "Rect" => rect.into_iter().map(Object::Integer).collect::<Vec<_>>(),
+                "T" => Object::string_literal(name),
+                "F" => 4,
+                "P" => page_id,
+            };
+            if let Some(tooltip) = options.tooltip {
+                field.set("TU", Object::string_literal(tooltip));
+            }
+            if let Some(seed_value) = options.seed_value {
+                field.set("SV", seed_value.to_dict()?);
+            }
+            if let Some(lock) = options.lock {
+                field.set("Lock", lock.to_dict());
+            }
+            let field_id = manager.doc.new_document.add_object(field);
+            manager.add_widget_field(page_id, field_id)
+        })
+    }
+
+    /// Point the `/V` of an existing empty signature field at a new signature
+    /// dictionary, the widget of the field is reused. The seed values of the
+    /// field are enforced and its `/Lock` becomes a FieldMDP reference.
+    fn add_field_placeholder(
+        &mut self,
+        name: &str,
+        mut signer_info: SignerInfo,
+        img: Option<impl AsRef<Path>>,
+        signer: &dyn Sign,
+    ) -> Result<ObjectId> {
+        let current = self.current_document();
+        let field = terminal_fields(&current)
+            .into_iter()
+            .find(|field| field.name == name)
+            .ok_or_else(|| anyhow::anyhow!("Field {} not found", name))?;
+        if field.field_type != Some(b"Sig") {
+            return Err(anyhow::anyhow!("Field {} is not a signature field", name));
+        }
+        if field.dict.has(b"V") {
+            return Err(anyhow::anyhow!("Field {} is already signed", name));
+        }
+        let field_id = field
+            .id
+            .ok_or_else(|| anyhow::anyhow!("Field {} is not an indirect object", name))?;
+        let widget_id = *field
+            .widget_ids(&current)
+            .first()
+            .ok_or_else(|| anyhow::anyhow!("Field {} has no widget annotation", name))?;
+        let widget = current.get_dictionary(widget_id)?;
+        let rect = widget
+            .get_deref(b"Rect", &current)
+            .and_then(Object::as_array)
+            .ok()
+            .and_then(|rect| {
+                rect.iter()
+                    .map(|n| current.dereference(n).and_then(|(_, n)| n.as_float()).ok())
+                    .collect::<Option<Vec<_>>>()
+            });
+        let Some(&[x1, y1, x2, y2]) = rect.as_deref() else {
+            return Err(anyhow::anyhow!(
+                "The widget of field {} has no /Rect of 4 numbers",
+                name
+            ));
+        };
+        let has_ap = widget.has(b"AP");
+        if let Ok(sv) = field
+            .dict
+            .get_deref(b"SV", &current)
+            .and_then(Object::as_dict)
+        {
+            apply_seed_value(&current, sv, signer, &mut signer_info)?;
+        }
+        let field_mdp = match field.dict.get_deref(b"Lock", &current) {
+            Ok(lock) => Some(field_mdp_sig_ref(&current, lock.as_dict()?)?),
+            Err(_) => None,
+        };
+
+        let sig_id = self.add_sig_obj(signer_info, signer.sub_filter());
+        if let Some(field_mdp) = field_mdp {
+            self.add_sig_ref(sig_id, field_mdp)?;
+        }
+        self.doc.opt_clone_object_to_new_document(field_id)?;
+        self.doc
+            .new_document
+            .get_dictionary_mut(field_id)?
+            .set("V", sig_id);
+
+        if img.is_some() || !has_ap {
+            let origin = (x1.min(x2).round() as i64, y1.min(y2).round() as i64);
+            let size = (
+                (x1 - x2).abs().round() as i64,
+                (y1 - y2).abs().round() as i64,
+            );
+            let img = img.map(|path| ImageRect::new(path, origin, size));
+            let ap_normal_id = self.add_ap_normal(img.as_ref())?;
+            self.doc.opt_clone_object_to_new_document(widget_id)?;
+            self.doc.new_document.get_dictionary_mut(widget_id)?.set(
+                "AP",
+                dictionary! {
+                    "N" => ap_normal_id,
+                },
+            );
+        }
+
+        let mut acro_form = self.get_or_create_acro_form_mut()?;
+        acro_form.set(b"SigFlags", Object::Integer(3));
+        Ok(sig_id)
+    }
+
+    /// Turn the signature into a certification signature: add the DocMDP
+    /// `/Reference` and point the catalog `/Perms` at it.
+    fn add_doc_mdp(&mut self, sig_id: ObjectId, permission: DocMdpPermission) -> Result<()> {
+        self.add_sig_ref(sig_id, permission.sig_ref())?;
+        self.clone_root()?;
+        self.doc.new_document.catalog_mut()?.set(
+            "Perms",
+            dictionary! {
+                "DocMDP" => sig_id,
+            },
+        );
+        Ok(())
+    }
+
+    /// Append a signature reference dictionary to the `/Reference` of the signature.
+    fn add_sig_ref(&mut self, sig_id: ObjectId, sig_ref: Dictionary) -> Result<()> {
+        self.doc
+            .new_document
+            .get_dictionary_mut(sig_id)?
+            .as_hashmap_mut()
+            .entry(b"Reference".into())
+            .or_insert_with(|| Object::Array(vec![]))
+            .as_array_mut()?
+            .push(sig_ref.into());
         Ok(())
     }
 
@@ -301,27 +596,324 @@ impl PDFSignManager {
         Ok(page_id)
     }
 
+    /// Run `edit` in the pending revision, started on top of the already signed
+    /// output so the previous signature stays valid. The signed output only
+    /// changes once a signature is in place, a failed `edit` also drops its
+    /// changes to the pending revision.
+    fn revise<T>(&mut self, edit: impl FnOnce(&mut Self) -> Result<T>) -> Result<T> {
+        let doc = self.doc.clone();
+        let continues_raw_pdf = self.continues_raw_pdf;
+        let result = self.start_revision().and_then(|_| edit(self));
+        if result.is_err() {
+            self.doc = doc;
+            self.continues_raw_pdf = continues_raw_pdf;
+        }
+        result
+    }
+
+    fn start_revision(&mut self) -> Result<()> {
+        if self.raw_pdf.is_empty() || self.continues_raw_pdf {
+            return Ok(());
+        }
+        let bytes = self.raw_pdf.data().to_vec();
+        let prev_documents = Document::load_mem(&bytes)?;
+        self.doc = IncrementalDocument::create_from(bytes, prev_documents);
+        self.doc.new_document.version = "1.7".into();
+        self.continues_raw_pdf = true;
+        Ok(())
+    }
+
+    /// Serialize the pending revision so its placeholder can be signed, see
+    /// [`PDFSignManager::commit_revision`].
+    fn write_revision(&mut self) -> Result<RawPdf> {
+        let mut buffer = Vec::new();
+        self.doc.save_to(&mut buffer)?;
+        let offset = self.doc.get_prev_documents_bytes().len();
+        let mut raw_pdf = RawPdf::empty();
+        raw_pdf.load_data(buffer, offset)?;
+        Ok(raw_pdf)
+    }
+
+    /// Make a written revision the output, the next revision starts on top of it.
+    fn commit_revision(&mut self, raw_pdf: RawPdf) {
+        self.raw_pdf = raw_pdf;
+        self.continues_raw_pdf = false;
+    }
+
     pub fn sign(
         &mut self,
         signer_info: SignerInfo,
         img: Option<ImageRect<impl AsRef<Path>>>,
         signer: &dyn Sign,
     ) -> Result<()> {
-        let page_id = self.clone_sig_page()?;
-        self.add_placeholder(page_id, signer_info, img)?;
+        self.revise(|manager| {
+            let page_id = manager.clone_sig_page()?;
+            let sig_id = manager.add_placeholder(page_id, signer_info, img, signer.sub_filter())?;
+            manager.complete_signature(sig_id, signer, None)
+        })
+    }
 
-        let mut buffer = Vec::new();
-        self.doc.save_to(&mut buffer)?;
-        self.raw_pdf.load_data(buffer)?;
+    /// Create a certification signature, later changes are limited to `permission`.
+    ///
+    /// A certification signature must be the first signature of the document.
+    pub fn certify(
+        &mut self,
+        permission: DocMdpPermission,
+        signer_info: SignerInfo,
+        img: Option<ImageRect<impl AsRef<Path>>>,
+        signer: &dyn Sign,
+    ) -> Result<()> {
+        self.revise(|manager| {
+            manager.ensure_unsigned()?;
+            let page_id = manager.clone_sig_page()?;
+            let sig_id = manager.add_placeholder(page_id, signer_info, img, signer.sub_filter())?;
+            manager.complete_signature(sig_id, signer, Some(permission))
+        })
+    }
+
+    /// Sign into the existing empty signature field `name`, the fully qualified
+    /// field name. The image, if any, is fitted to the widget rectangle.
+    pub fn sign_field(
+        &mut self,
+        name: &str,
+        signer_info: SignerInfo,
+        img: Option<impl AsRef<Path>>,
+        signer: &dyn Sign,
+    ) -> Result<()> {
+        self.revise(|manager| {
+            let sig_id = manager.add_field_placeholder(name, signer_info, img, signer)?;
+            manager.complete_signature(sig_id, signer, None)
+        })
+    }
+
+    /// Create a certification signature in the existing empty signature field `name`.
+    pub fn certify_field(
+        &mut self,
+        name: &str,
+        permission: DocMdpPermission,
+        signer_info: SignerInfo,
+        img: Option<impl AsRef<Path>>,
+        signer: &dyn Sign,
+    ) -> Result<()> {
+        self.revise(|manager| {
+            manager.ensure_unsigned()?;
+            let sig_id = manager.add_field_placeholder(name, signer_info, img, signer)?;
+            manager.complete_signature(sig_id, signer, Some(permission))
+        })
+    }
+
+    fn ensure_unsigned(&self) -> Result<()> {
+        if !signature_contents(self.doc.get_prev_documents()).is_empty() {
+            return Err(anyhow::anyhow!(
+                "A certification signature must be the first signature of the document"
+            ));
+        }
+        Ok(())
+    }
+
+    fn complete_signature(
+        &mut self,
+        sig_id: ObjectId,
+        signer: &dyn Sign,
+        permission: Option<DocMdpPermission>,
+    ) -> Result<()> {
+        if let Some(permission) = permission {
+            self.add_doc_mdp(sig_id, permission)?;
+        }
 
-        self.raw_pdf.sign(signer)?;
+        self.sign_placeholder(sig_id, signer, true)
+    }
+
+    /// Write the revision and sign its placeholder, `timestamp` applies the
+    /// timestamp authority of the manager. An estimated placeholder too small
+    /// for the signature is resized to it and signed once more.
+    fn sign_placeholder(
+        &mut self,
+        sig_id: ObjectId,
+        signer: &dyn Sign,
+        timestamp: bool,
+    ) -> Result<()> {
+        let mut len = match self.placeholder_len {
+            Some(len) => len,
+            None => self.estimate_placeholder_len(signer, timestamp)?,
+        };
+        let mut resized = self.placeholder_len.is_some();
+        loop {
+            self.resize_placeholder(sig_id, len)?;
+            let mut raw_pdf = self.write_revision()?;
+            let tsa = self.tsa.as_deref().filter(|_| timestamp);
+            let contents = raw_pdf.signed_contents(signer, tsa)?;
+            if contents.len() <= raw_pdf.capacity() {
+                raw_pdf.write_contents(&contents)?;
+                self.commit_revision(raw_pdf);
+                return Ok(());
+            }
+            // The placeholder is part of the signed bytes, a larger one needs a new signature
+            if resized {
+                return Err(anyhow::anyhow!(
+                    "The signature needs {} bytes but {} were reserved, set a larger placeholder size",
+                    contents.len(),
+                    raw_pdf.capacity()
+                ));
+            }
+            len = contents.len() + SIG_CONTENTS_MARGIN;
+            resized = true;
+        }
+    }
+
+    /// Size the placeholder without signing, from the estimate of the signer
+    /// and of the timestamp authority.
+    fn estimate_placeholder_len(&self, signer: &dyn Sign, timestamp: bool) -> Result<usize> {
+        let mut len = signer.estimated_len()?;
+        if timestamp && let Some(tsa) = &self.tsa {
+            len += tsa.estimated_token_len().unwrap_or(TIMESTAMP_TOKEN_RESERVE);
+        }
+        Ok(len + SIG_CONTENTS_MARGIN)
+    }
 
+    fn resize_placeholder(&mut self, sig_id: ObjectId, len: usize) -> Result<()> {
+        self.doc.new_document.get_dictionary_mut(sig_id)?.set(
+            "Contents",
+            Object::String(
+                vec![0; len.max(SIG_CONTENTS_MARGIN)],
+                StringFormat::Hexadecimal,
+            ),
+        );
         Ok(())
     }
 
+    /// Write the signature placeholder without signing, for keys that cannot sign
+    /// synchronously. The returned state carries the `digest_algorithm` digest to
+    /// sign, hand the resulting CMS to [`PreparedSignature::complete`].
+    pub fn prepare(
+        &mut self,
+        signer_info: SignerInfo,
+        img: Option<ImageRect<impl AsRef<Path>>>,
+        sub_filter: SubFilter,
+        digest_algorithm: DigestAlgorithm,
+    ) -> Result<PreparedSignature> {
+        self.revise(|manager| {
+            let page_id = manager.clone_sig_page()?;
+            let sig_id = manager.add_placeholder(page_id, signer_info, img, sub_filter)?;
+            let len = manager
+                .placeholder_len
+                .unwrap_or(SIG_CONTENTS_PLACEHOLDER_LEN);
+            manager.resize_placeholder(sig_id, len)?;
+            let raw_pdf = manager.write_revision()?;
+            let prepared = PreparedSignature::new(
+                RawPdf::load_prepared(raw_pdf.data().to_vec())?,
+                digest_algorithm,
+            )?;
+            manager.commit_revision(raw_pdf);
+            Ok(prepared)
+        })
+    }
+
+    /// Append a document timestamp (`/Type /DocTimeStamp`) whose `/Contents`
+    /// is a bare RFC 3161 token over the ByteRange.
+    pub fn timestamp(&mut self, tsa: &dyn TimestampAuthority) -> Result<()> {
+        self.revise(|manager| {
+            let page_id = manager.clone_sig_page()?;
+            let signer = DocumentTimestamper::new(tsa);
+            let sig_id = manager.add_placeholder(
+                page_id,
+                SignerInfo::empty(),
+                None::<ImageRect<&Path>>,
+                signer.sub_filter(),
+            )?;
+            manager.sign_placeholder(sig_id, &signer, false)
+        })
+    }
+
+    fn add_der_streams(&mut self, items: Vec<Vec<u8>>) -> Result<Vec<Object>> {
+        items
+            .into_iter()
+            .map(|der| {
+                let mut stream = Stream::new(Dictionary::new(), der);
+                stream.compress()?;
+                Ok(self.doc.new_document.add_object(stream).into())
+            })
+            .collect()
+    }
+
+    /// Append a revision with a Document Security Store that holds the validation
+    /// material of every signature in the document (PAdES B-LT), and a `/VRI`
+    /// entry per signature with the material of its own signer and TSA paths.
+    /// The entries carry no `/TU`, so the same input writes the same revision.
+    ///
+    /// The certificates embedded in the signatures and their timestamp tokens are
+    /// always included, `fetcher` optionally retrieves the missing CRLs and OCSP
+    /// responses. The certificates whose revocation information could not be
+    /// fetched are returned, the store is written without it.
+    pub fn add_dss(
+        &mut self,
+        mut data: ValidationData,
+        fetcher: Option<&dyn RevocationFetcher>,
+    ) -> Result<Vec<MissingRevocation>> {
+        self.revise(|manager| {
+            let signatures = signature_contents(manager.doc.get_prev_documents());
+            if signatures.is_empty() {
+                return Err(anyhow::anyhow!("No signature found in the document"));
+            }
+            let signers = signatures
+                .iter()
+                .map(|contents| data.add_cms_certs(contents))
+                .collect::<Result<Vec<_>>>()?;
+            let mut missing = Vec::new();
+            let materials = signers
+                .iter()
+                .map(|signers| data.collect(signers, fetcher, &mut missing))
+                .collect::<Result<Vec<_>>>()?;
+
+            let certs = data
+                .certs
+                .iter()
+                .map(|cert| cert.to_der())
+                .collect::<Result<Vec<_>, _>>()?;
+            let cert_refs = manager.add_der_streams(certs.clone())?;
+            let crl_refs = manager.add_der_streams(data.crls.clone())?;
+            let ocsp_refs = manager.add_der_streams(data.ocsps.clone())?;
+
+            let mut vri = Dictionary::new();
+            for (contents, material) in signatures.iter().zip(materials) {
+                let key = hex::encode_upper(hash(MessageDigest::sha1(), contents)?);
+                let material_certs = material
+                    .certs
+                    .iter()
+                    .map(|cert| cert.to_der())
+                    .collect::<Result<Vec<_>, _>>()?;
+                let mut entry = Dictionary::new();
+                for (name, items, all, refs) in [
+                    ("Cert", &material_certs, &certs, &cert_refs),
+                    ("CRL", &material.crls, &data.crls, &crl_refs),
+                    ("OCSP", &material.ocsps, &data.ocsps, &ocsp_refs),
+                ] {
+                    let refs = items
+                        .iter()
+                        .filter_map(|item| all.iter().position(|known| known == item))
+                        .map(|index| refs[index].clone())
+                        .collect::<Vec<_>>();
+                    if !refs.is_empty() {
+                        entry.set(name, refs);
+                    }
+                }
+                vri.set(key, entry);
+            }
+
+            let mut dss = manager.get_or_create_dss_mut()?;
+            dss.extend_array(b"Certs", cert_refs)?;
+            dss.extend_array(b"CRLs", crl_refs)?;
+            dss.extend_array(b"OCSPs", ocsp_refs)?;
+            dss.get_or_create_vri_mut()?.extend(&vri);
+            Ok(missing)
+        })
+    }
+
     #[inline]
     pub fn save_to<W: Write>(&mut self, target: &mut W) -> Result<()> {
-        if !self.raw_pdf.is_empty() {
+        // Unsigned changes made after the last signature live only in `doc`
+        let pending = self.continues_raw_pdf && !self.doc.new_document.objects.is_empty();
+        if !self.raw_pdf.is_empty() && !pending {
             self.raw_pdf.save_to(target)?;
         } else {
             self.doc.save_to(target)?;
@@ -0,0 +1,87 @@
+use anyhow::Result;
+use openssl::hash::hash;
+
+use crate::{
+    cms::{decode_signed_data, verify::signed_digest},
+    parser::RawPdf,
+    signer::DigestAlgorithm,
+    tsa::TimestampAuthority,
+};
+
+/// A document whose signature placeholder is written but not yet filled, the
+/// state between [`PDFSignManager::prepare`](super::PDFSignManager::prepare)
+/// and [`PreparedSignature::complete`].
+///
+/// The state is the prepared PDF and its digest algorithm, store the PDF with
+/// [`PreparedSignature::as_bytes`] next to [`PreparedSignature::digest_algorithm`]
+/// and restore both with [`PreparedSignature::from_bytes`].
+pub struct PreparedSignature {
+    raw_pdf: RawPdf,
+    digest_algorithm: DigestAlgorithm,
+    digest: Vec<u8>,
+}
+
+impl PreparedSignature {
+    pub(crate) fn new(raw_pdf: RawPdf, digest_algorithm: DigestAlgorithm) -> Result<Self> {
+        let digest = hash(
+            digest_algorithm.message_digest(),
+            &raw_pdf.get_data_to_sign(),
+        )?
+        .to_vec();
+        Ok(PreparedSignature {
+            raw_pdf,
+            digest_algorithm,
+            digest,
+        })
+    }
+
+    /// Restore a prepared PDF, `digest_algorithm` is the one it was prepared with.
+    pub fn from_bytes(data: Vec<u8>, digest_algorithm: DigestAlgorithm) -> Result<Self> {
+        Self::new(RawPdf::load_prepared(data)?, digest_algorithm)
+    }
+
+    pub fn as_bytes(&self) -> &[u8] {
+        self.raw_pdf.data()
+    }
+
+    pub fn digest_algorithm(&self) -> DigestAlgorithm {
+        self.digest_algorithm
+    }
+
+    /// The digest of the ByteRange with [`PreparedSignature::digest_algorithm`],
+    /// the message digest of the CMS signature.
+    pub fn digest(&self) -> &[u8] {
+        &self.digest
+    }
+
+    /// The bytes covered by the ByteRange, for signers that hash the data themselves.
+    pub fn data_to_sign(&self) -> Vec<u8> {
+        self.raw_pdf.get_data_to_sign()
+    }
+
+    /// Fill the placeholder with the DER encoded CMS `cms` and return the signed
+    /// PDF, `tsa` embeds a signature timestamp as in
+    /// [`PDFSignManager::sign`](super::PDFSignManager::sign).
+    ///
+    /// The message-digest attribute of the CMS must be [`PreparedSignature::digest`],
+    /// and the signature must fit the placeholder, prepare again with a larger
+    /// [`PDFSignManager::set_placeholder_size`](super::PDFSignManager::set_placeholder_size)
+    /// otherwise.
+    pub fn complete(self, cms: Vec<u8>, tsa: Option<&dyn TimestampAuthority>) -> Result<Vec<u8>> {
+        let (md, digest) = signed_digest(&decode_signed_data(&cms)?)?;
+        if md.type_() != self.digest_algorithm.message_digest().type_() {
+            return Err(anyhow::anyhow!(
+                "The CMS digest algorithm is not the {} the document was prepared with",
+                self.digest_algorithm.as_name()
+            ));
+        }
+        if digest != self.digest {
+            return Err(anyhow::anyhow!(
+                "The CMS signs other data, its message digest is not the ByteRange digest"
+            ));
+        }
+        let mut raw_pdf = self.raw_pdf;
+        raw_pdf.inject(cms, tsa)?;
+        Ok(raw_pdf.data().to_vec())
+    }
+}
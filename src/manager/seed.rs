@@ -0,0 +1,200 @@
+use anyhow::Result;
+use lopdf::{Dictionary, Document, Object, StringFormat, decode_text_string, dictionary};
+use openssl::x509::{X509, X509VerifyResult};
+
+use super::sign_info::SignerInfo;
+use crate::signer::{Sign, SubFilter};
+
+// `/Ff` bits of the seed value dictionary
+const SV_SUB_FILTER: i64 = 1 << 1;
+const SV_REASONS: i64 = 1 << 3;
+const SV_DIGEST_METHOD: i64 = 1 << 6;
+// `/Ff` bits of the certificate seed value dictionary
+const SV_CERT_SUBJECT: i64 = 1;
+const SV_CERT_ISSUER: i64 = 1 << 1;
+
+/// The `/SV` seed value dictionary of a signature field, constraints on the
+/// signature a later signer puts into the field. Every non-empty constraint is
+/// written as required.
+#[derive(Debug, Clone, Default)]
+pub struct SeedValue {
+    /// Acceptable signature formats.
+    pub sub_filters: Vec<SubFilter>,
+    /// Acceptable digest algorithms, such as `SHA256`.
+    pub digest_methods: Vec<String>,
+    /// Acceptable reasons for signing, the first one is used if the signer gives none.
+    pub reasons: Vec<String>,
+    /// The signer certificate must be one of these certificates.
+    pub subjects: Vec<X509>,
+    /// The signer certificate must be issued by one of these certificates.
+    pub issuers: Vec<X509>,
+}
+
+impl SeedValue {
+    pub(crate) fn to_dict(&self) -> Result<Dictionary> {
+        let mut ff = 0;
+        let mut sv = dictionary! { "Type" => "SV" };
+        if !self.sub_filters.is_empty() {
+            ff |= SV_SUB_FILTER;
+            let names = self.sub_filters.iter().map(|s| s.as_name().into());
+            sv.set("SubFilter", names.collect::<Vec<Object>>());
+        }
+        if !self.digest_methods.is_empty() {
+            ff |= SV_DIGEST_METHOD;
+            let names = self.digest_methods.iter().map(|d| d.as_str().into());
+            sv.set("DigestMethod", names.collect::<Vec<Object>>());
+        }
+        if !self.reasons.is_empty() {
+            ff |= SV_REASONS;
+            let reasons = self
+                .reasons
+                .iter()
+                .map(|r| Object::string_literal(r.as_str()));
+            sv.set("Reasons", reasons.collect::<Vec<_>>());
+        }
+        if !self.subjects.is_empty() || !self.issuers.is_empty() {
+            let mut cert_ff = 0;
+            let mut cert = dictionary! { "Type" => "SVCert" };
+            for (key, bit, certs) in [
+                ("Subject", SV_CERT_SUBJECT, &self.subjects),
+                ("Issuer", SV_CERT_ISSUER, &self.issuers),
+            ] {
+                if certs.is_empty() {
+                    continue;
+                }
+                cert_ff |= bit;
+                let ders = certs
+                    .iter()
+                    .map(|c| Ok(Object::String(c.to_der()?, StringFormat::Hexadecimal)))
+                    .collect::<Result<Vec<_>>>()?;
+                cert.set(key, ders);
+            }
+            cert.set("Ff", cert_ff);
+            sv.set("Cert", cert);
+        }
+        sv.set("Ff", ff);
+        Ok(sv)
+    }
+}
+
+/// Check the required constraints of the seed value dictionary `sv` against the
+/// signer, a missing reason is taken from the seed values.
+pub(crate) fn apply_seed_value(
+    doc: &Document,
+    sv: &Dictionary,
+    signer: &dyn Sign,
+    signer_info: &mut SignerInfo,
+) -> Result<()> {
+    let ff = sv.get(b"Ff").and_then(Object::as_i64).unwrap_or(0);
+
+    let sub_filters = names(doc, sv, b"SubFilter");
+    if ff & SV_SUB_FILTER != 0
+        && !sub_filters.is_empty()
+        && !sub_filters.contains(&signer.sub_filter().as_name().as_bytes())
+    {
+        return Err(anyhow::anyhow!(
+            "The field requires one of the SubFilters {}",
+            join(&sub_filters)
+        ));
+    }
+    let digest_methods = names(doc, sv, b"DigestMethod");
+    if ff & SV_DIGEST_METHOD != 0
+        && !digest_methods.is_empty()
+        && !digest_methods.contains(&signer.digest_algorithm().as_name().as_bytes())
+    {
+        return Err(anyhow::anyhow!(
+            "The field requires one of the digest methods {}",
+            join(&digest_methods)
+        ));
+    }
+
+    let reasons = array(doc, sv, b"Reasons")
+        .iter()
+        .filter_map(|r| decode_text_string(r).ok())
+        .collect::<Vec<_>>();
+    // A single "." means the reason must be omitted
+    let no_reason = reasons.len() == 1 && reasons[0] == ".";
+    match &signer_info.reason {
+        Some(reason)
+            if ff & SV_REASONS != 0
+                && !reasons.is_empty()
+                && (no_reason || !reasons.contains(reason)) =>
+        {
+            return Err(anyhow::anyhow!(
+                "The field does not accept the reason {}",
+                reason
+            ));
+        }
+        None if !no_reason => signer_info.reason = reasons.into_iter().next(),
+        _ => {}
+    }
+
+    if let Ok(cert_sv) = sv.get_deref(b"Cert", doc).and_then(Object::as_dict) {
+        check_cert_seed_value(doc, cert_sv, &signer.certificates())?;
+    }
+    Ok(())
+}
+
+fn check_cert_seed_value(doc: &Document, cert_sv: &Dictionary, chain: &[X509]) -> Result<()> {
+    let ff = cert_sv.get(b"Ff").and_then(Object::as_i64).unwrap_or(0);
+    let certs = |key: &[u8]| {
+        array(doc, cert_sv, key)
+            .iter()
+            .filter_map(|der| X509::from_der(der.as_str().ok()?).ok())
+            .collect::<Vec<_>>()
+    };
+    let subjects = certs(b"Subject");
+    let issuers = certs(b"Issuer");
+    if (ff & SV_CERT_SUBJECT != 0 && !subjects.is_empty())
+        || (ff & SV_CERT_ISSUER != 0 && !issuers.is_empty())
+    {
+        let Some(cert) = chain.first() else {
+            return Err(anyhow::anyhow!(
+                "The field restricts the certificate but the signer provides none"
+            ));
+        };
+        if ff & SV_CERT_SUBJECT != 0
+            && !subjects.is_empty()
+            && !subjects.iter().any(|subject| subject == cert)
+        {
+            return Err(anyhow::anyhow!(
+                "The signer certificate is not accepted by the field"
+            ));
+        }
+        if ff & SV_CERT_ISSUER != 0
+            && !issuers.is_empty()
+            && !chain.iter().any(|cert| {
+                issuers
+                    .iter()
+                    .any(|issuer| issuer.issued(cert) == X509VerifyResult::OK)
+            })
+        {
+            return Err(anyhow::anyhow!(
+                "The signer certificate is not issued by an issuer accepted by the field"
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn array<'a>(doc: &'a Document, dict: &'a Dictionary, key: &[u8]) -> &'a [Object] {
+    dict.get_deref(key, doc)
+        .and_then(Object::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+fn names<'a>(doc: &'a Document, dict: &'a Dictionary, key: &[u8]) -> Vec<&'a [u8]> {
+    array(doc, dict, key)
+        .iter()
+        .filter_map(|name| name.as_name().ok())
+        .collect()
+}
+
+fn join(names: &[&[u8]]) -> String {
+    names
+        .iter()
+        .map(|name| String::from_utf8_lossy(name))
+        .collect::<Vec<_>>()
+        .join(", ")
+}
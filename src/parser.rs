@@ -4,7 +4,9 @@ use std::io::Write;
 
 use anyhow::Result;
 
-use crate::{byte_range::ByteRange, config::SIG_CONTENTS_PLACEHOLDER_LEN, signer::Sign};
+use crate::{
+    byte_range::ByteRange, cms::add_signature_timestamp, signer::Sign, tsa::TimestampAuthority,
+};
 
 pub(crate) struct RawPdf {
     data: Vec<u8>,
@@ -23,16 +25,33 @@ impl RawPdf {
         self.data.is_empty()
     }
 
-    pub(crate) fn load_data(&mut self, data: Vec<u8>) -> Result<()> {
+    pub(crate) fn data(&self) -> &[u8] {
+        &self.data
+    }
+
+    /// Load a serialized revision, `offset` is where its incremental section starts.
+    pub(crate) fn load_data(&mut self, data: Vec<u8>, offset: usize) -> Result<()> {
         self.data = data;
-        self.byte_range = ByteRange::from_bytes(&self.data)?;
+        self.byte_range = ByteRange::from_bytes(&self.data, offset)?;
         let bytes = self.byte_range.get_bytes();
         self.data[self.byte_range.byte_range_start..self.byte_range.byte_range_end]
             .copy_from_slice(&bytes);
         Ok(())
     }
 
-    fn get_data_to_sign(&self) -> Vec<u8> {
+    /// Load a revision written by [`RawPdf::load_data`] whose `/Contents` is still empty,
+    /// the ByteRange is read back from the last signature dictionary.
+    pub(crate) fn load_prepared(data: Vec<u8>) -> Result<Self> {
+        let byte_range = ByteRange::from_patched(&data)?;
+        let [_, contents_start, contents_end, _] = byte_range.value;
+        let contents = &data[contents_start + 1..contents_end - 1];
+        if data[contents_start] != b'<' || contents.iter().any(|c| *c != b'0') {
+            return Err(anyhow::anyhow!("No empty signature placeholder found"));
+        }
+        Ok(RawPdf { data, byte_range })
+    }
+
+    pub(crate) fn get_data_to_sign(&self) -> Vec<u8> {
         let mut data_to_sign = Vec::new();
         data_to_sign.extend_from_slice(
             &self.data
@@ -45,13 +64,45 @@ impl RawPdf {
         data_to_sign
     }
 
-    pub fn sign(&mut self, signer: &dyn Sign) -> Result<()> {
-        let data_to_sign = self.get_data_to_sign();
-        let signed_data = signer.sign(&data_to_sign)?;
-        if signed_data.len() > SIG_CONTENTS_PLACEHOLDER_LEN {
-            return Err(anyhow::anyhow!("Signed data is too long"));
+    /// The number of bytes the `/Contents` placeholder can hold.
+    pub(crate) fn capacity(&self) -> usize {
+        (self.byte_range.value[2] - self.byte_range.value[1] - 2) / 2
+    }
+
+    /// Sign the ByteRange and timestamp the signature, without writing it.
+    pub(crate) fn signed_contents(
+        &self,
+        signer: &dyn Sign,
+        tsa: Option<&dyn TimestampAuthority>,
+    ) -> Result<Vec<u8>> {
+        let signed_data = signer.sign(&self.get_data_to_sign())?;
+        match tsa {
+            Some(tsa) => add_signature_timestamp(&signed_data, tsa),
+            None => Ok(signed_data),
+        }
+    }
+
+    /// Write a CMS signature created elsewhere into the `/Contents` placeholder.
+    pub(crate) fn inject(
+        &mut self,
+        mut signed_data: Vec<u8>,
+        tsa: Option<&dyn TimestampAuthority>,
+    ) -> Result<()> {
+        if let Some(tsa) = tsa {
+            signed_data = add_signature_timestamp(&signed_data, tsa)?;
+        }
+        self.write_contents(&signed_data)
+    }
+
+    pub(crate) fn write_contents(&mut self, signed_data: &[u8]) -> Result<()> {
+        if signed_data.len() > self.capacity() {
+            return Err(anyhow::anyhow!(
+                "Signed data is too long: {} bytes do not fit the {} byte placeholder",
+                signed_data.len(),
+                self.capacity()
+            ));
         }
-        let contents_hex = hex::encode(&signed_data);
+        let contents_hex = hex::encode(signed_data);
         let contents_start = self.byte_range.value[1] + 1;
         self.data[contents_start..contents_start + contents_hex.len()]
             .copy_from_slice(contents_hex.as_bytes());
@@ -0,0 +1,63 @@
+use std::{io::Read, sync::Arc};
+
+use anyhow::Result;
+use openssl::{
+    hash::MessageDigest,
+    ocsp::{OcspCertId, OcspRequest},
+    x509::X509Ref,
+};
+
+use super::RevocationFetcher;
+
+/// Fetch CRLs from the CRL distribution points and OCSP responses from the
+/// Authority Information Access responders named in the certificates.
+pub struct HttpRevocationFetcher {
+    agent: ureq::Agent,
+}
+
+impl HttpRevocationFetcher {
+    pub fn new() -> Result<Self> {
+        let agent = ureq::AgentBuilder::new()
+            .tls_connector(Arc::new(native_tls::TlsConnector::new()?))
+            .build();
+        Ok(HttpRevocationFetcher { agent })
+    }
+
+    fn read_body(response: ureq::Response) -> Result<Vec<u8>> {
+        let mut body = Vec::new();
+        response.into_reader().read_to_end(&mut body)?;
+        Ok(body)
+    }
+}
+
+impl RevocationFetcher for HttpRevocationFetcher {
+    fn fetch_crls(&self, cert: &X509Ref) -> Result<Vec<Vec<u8>>> {
+        let mut urls = Vec::new();
+        for dp in cert.crl_distribution_points().iter().flatten() {
+            let names = dp.distpoint().and_then(|name| name.fullname());
+            for uri in names.into_iter().flatten().filter_map(|name| name.uri()) {
+                if uri.starts_with("http://") || uri.starts_with("https://") {
+                    urls.push(uri.to_owned());
+                }
+            }
+        }
+        urls.iter()
+            .map(|url| Self::read_body(self.agent.get(url).call()?))
+            .collect()
+    }
+
+    fn fetch_ocsp(&self, cert: &X509Ref, issuer: &X509Ref) -> Result<Option<Vec<u8>>> {
+        let responders = cert.ocsp_responders()?;
+        let Some(url) = responders.iter().next() else {
+            return Ok(None);
+        };
+        let mut request = OcspRequest::new()?;
+        request.add_id(OcspCertId::from_cert(MessageDigest::sha1(), cert, issuer)?)?;
+        let response = self
+            .agent
+            .post(url)
+            .set("Content-Type", "application/ocsp-request")
+            .send_bytes(&request.to_der()?)?;
+        Ok(Some(Self::read_body(response)?))
+    }
+}
@@ -0,0 +1,17 @@
+//! Revocation
+//! Sources of revocation information (CRLs and OCSP responses) for certificates.
+use anyhow::Result;
+use openssl::x509::X509Ref;
+
+pub use self::http::HttpRevocationFetcher;
+
+mod http;
+
+pub trait RevocationFetcher {
+    /// Return the DER encoded CRLs that cover `cert`.
+    fn fetch_crls(&self, cert: &X509Ref) -> Result<Vec<Vec<u8>>>;
+
+    /// Return a DER encoded OCSPResponse for `cert`, or `None` if the
+    /// certificate does not name an OCSP responder.
+    fn fetch_ocsp(&self, cert: &X509Ref, issuer: &X509Ref) -> Result<Option<Vec<u8>>>;
+}
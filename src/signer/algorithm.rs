@@ -0,0 +1,131 @@
+use std::str::FromStr;
+
+use anyhow::Result;
+use openssl::{
+    hash::MessageDigest,
+    md::{Md, MdRef},
+    pkey::Id,
+    x509::X509Ref,
+};
+
+/// The digest algorithm of the message digest and of the signature.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum DigestAlgorithm {
+    #[default]
+    Sha256,
+    Sha384,
+    Sha512,
+}
+
+impl DigestAlgorithm {
+    pub(crate) fn message_digest(&self) -> MessageDigest {
+        match self {
+            DigestAlgorithm::Sha256 => MessageDigest::sha256(),
+            DigestAlgorithm::Sha384 => MessageDigest::sha384(),
+            DigestAlgorithm::Sha512 => MessageDigest::sha512(),
+        }
+    }
+
+    pub(crate) fn md(&self) -> &'static MdRef {
+        match self {
+            DigestAlgorithm::Sha256 => Md::sha256(),
+            DigestAlgorithm::Sha384 => Md::sha384(),
+            DigestAlgorithm::Sha512 => Md::sha512(),
+        }
+    }
+
+    /// The name used by `/DigestMethod` seed values.
+    pub(crate) fn as_name(&self) -> &'static str {
+        match self {
+            DigestAlgorithm::Sha256 => "SHA256",
+            DigestAlgorithm::Sha384 => "SHA384",
+            DigestAlgorithm::Sha512 => "SHA512",
+        }
+    }
+
+    pub(crate) fn len(&self) -> usize {
+        self.message_digest().size()
+    }
+}
+
+impl FromStr for DigestAlgorithm {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().replace('-', "").as_str() {
+            "sha256" => Ok(DigestAlgorithm::Sha256),
+            "sha384" => Ok(DigestAlgorithm::Sha384),
+            "sha512" => Ok(DigestAlgorithm::Sha512),
+            _ => Err(anyhow::anyhow!("Unsupported digest algorithm: {}", s)),
+        }
+    }
+}
+
+/// How the signature value is computed, it must fit the key type.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SignatureScheme {
+    /// RSA with PKCS#1 v1.5 padding.
+    RsaPkcs1v15,
+    /// RSASSA-PSS with MGF1 over the same digest and a salt of the digest length.
+    RsaPss,
+    /// ECDSA on the curve of the key, such as P-256 or P-384.
+    Ecdsa,
+    /// PureEdDSA over the signed attributes, SHA-512 is the message digest (ISO 32002).
+    Ed25519,
+}
+
+impl SignatureScheme {
+    /// The default scheme for the key of `cert`.
+    pub(crate) fn for_certificate(cert: &X509Ref) -> Result<Self> {
+        match cert.public_key()?.id() {
+            Id::RSA => Ok(SignatureScheme::RsaPkcs1v15),
+            Id::EC => Ok(SignatureScheme::Ecdsa),
+            Id::ED25519 => Ok(SignatureScheme::Ed25519),
+            id => Err(anyhow::anyhow!("Unsupported key type: {:?}", id)),
+        }
+    }
+
+    /// The digest used with the scheme unless another one is chosen.
+    pub(crate) fn default_digest(&self) -> DigestAlgorithm {
+        match self {
+            SignatureScheme::Ed25519 => DigestAlgorithm::Sha512,
+            _ => DigestAlgorithm::Sha256,
+        }
+    }
+
+    /// Check that the scheme fits the key of `cert` and the digest.
+    pub(crate) fn check(&self, cert: &X509Ref, digest: DigestAlgorithm) -> Result<()> {
+        let key_scheme = Self::for_certificate(cert)?;
+        let compatible = match self {
+            SignatureScheme::RsaPkcs1v15 | SignatureScheme::RsaPss => {
+                key_scheme == SignatureScheme::RsaPkcs1v15
+            }
+            scheme => key_scheme == *scheme,
+        };
+        if !compatible {
+            return Err(anyhow::anyhow!(
+                "The {:?} scheme does not fit the {:?} key of the certificate",
+                self,
+                key_scheme
+            ));
+        }
+        if *self == SignatureScheme::Ed25519 && digest != DigestAlgorithm::Sha512 {
+            return Err(anyhow::anyhow!("Ed25519 signatures require SHA-512"));
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SignatureScheme {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.to_ascii_lowercase().as_str() {
+            "pkcs1" | "pkcs1v15" | "rsa" => Ok(SignatureScheme::RsaPkcs1v15),
+            "pss" | "rsa-pss" => Ok(SignatureScheme::RsaPss),
+            "ecdsa" => Ok(SignatureScheme::Ecdsa),
+            "ed25519" | "eddsa" => Ok(SignatureScheme::Ed25519),
+            _ => Err(anyhow::anyhow!("Unsupported signature scheme: {}", s)),
+        }
+    }
+}
@@ -1,9 +1,125 @@
 use anyhow::Result;
+use openssl::{hash::hash, x509::X509};
 
+pub use self::algorithm::{DigestAlgorithm, SignatureScheme};
 pub use self::p12::P12Signer;
+pub use self::pem::PemSigner;
+pub use self::pkcs11::{Pkcs11Signer, Pkcs11SignerBuilder};
+pub use self::raw::CmsSigner;
+pub(crate) use self::raw::PKeySigner;
+use crate::{cms::estimate_signed_data_len, config::SIG_CONTENTS_PLACEHOLDER_LEN};
 
+mod algorithm;
 mod p12;
+mod pem;
+mod pkcs11;
+mod raw;
+
+/// The `/SubFilter` of a signature dictionary, it decides how `/Contents` is encoded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SubFilter {
+    /// `adbe.pkcs7.detached`, a plain detached CMS signature.
+    #[default]
+    Pkcs7Detached,
+    /// `ETSI.CAdES.detached`, a PAdES baseline signature.
+    CadesDetached,
+    /// `ETSI.RFC3161`, a document timestamp token.
+    Rfc3161,
+}
+
+impl SubFilter {
+    pub(crate) fn as_name(&self) -> &'static str {
+        match self {
+            SubFilter::Pkcs7Detached => "adbe.pkcs7.detached",
+            SubFilter::CadesDetached => "ETSI.CAdES.detached",
+            SubFilter::Rfc3161 => "ETSI.RFC3161",
+        }
+    }
+}
 
 pub trait Sign {
     fn sign(&self, data: &[u8]) -> Result<Vec<u8>>;
+
+    /// The format of the CMS returned by [`Sign::sign`].
+    fn sub_filter(&self) -> SubFilter {
+        SubFilter::Pkcs7Detached
+    }
+
+    /// The signer certificate first, then its CA certificates. Empty if unknown.
+    fn certificates(&self) -> Vec<X509> {
+        Vec::new()
+    }
+
+    /// The digest algorithm of the signature.
+    fn digest_algorithm(&self) -> DigestAlgorithm {
+        DigestAlgorithm::default()
+    }
+
+    /// An upper bound of the length of [`Sign::sign`], it sizes the `/Contents`
+    /// placeholder. Estimated from [`Sign::certificates`] and the signer key size,
+    /// the default placeholder size if they are unknown.
+    fn estimated_len(&self) -> Result<usize> {
+        Ok(estimate_signed_data_len(&self.certificates())?.unwrap_or(SIG_CONTENTS_PLACEHOLDER_LEN))
+    }
+}
+
+/// A key that can only sign a digest, such as an HSM, a KMS or a smart card.
+/// The CMS SignedData around the signature is built by [`CmsSigner`].
+pub trait RawSigner {
+    /// The signer certificate, its public key decides the signature algorithm.
+    fn certificate(&self) -> X509;
+
+    /// CA certificates embedded in the signature next to the signer certificate.
+    fn chain(&self) -> Vec<X509> {
+        Vec::new()
+    }
+
+    /// The digest of the signed attributes and of the document.
+    fn digest_algorithm(&self) -> DigestAlgorithm {
+        DigestAlgorithm::default()
+    }
+
+    /// The signature scheme, PKCS#1 v1.5 for RSA keys by default.
+    fn signature_scheme(&self) -> Result<SignatureScheme> {
+        SignatureScheme::for_certificate(&self.certificate())
+    }
+
+    /// Sign the `digest` of the DER encoded signed attributes, computed with
+    /// [`RawSigner::digest_algorithm`]. Return an RSA signature in the padding of
+    /// [`RawSigner::signature_scheme`] or a DER `ECDSA-Sig-Value` for EC keys.
+    fn sign_digest(&self, digest: &[u8]) -> Result<Vec<u8>>;
+
+    /// Sign the DER encoded signed attributes. Hash them and call
+    /// [`RawSigner::sign_digest`] unless the scheme signs the whole message, as
+    /// Ed25519 does.
+    fn sign_message(&self, message: &[u8]) -> Result<Vec<u8>> {
+        let digest = hash(self.digest_algorithm().message_digest(), message)?;
+        self.sign_digest(&digest)
+    }
+}
+
+impl<S: RawSigner + ?Sized> RawSigner for &S {
+    fn certificate(&self) -> X509 {
+        (**self).certificate()
+    }
+
+    fn chain(&self) -> Vec<X509> {
+        (**self).chain()
+    }
+
+    fn digest_algorithm(&self) -> DigestAlgorithm {
+        (**self).digest_algorithm()
+    }
+
+    fn signature_scheme(&self) -> Result<SignatureScheme> {
+        (**self).signature_scheme()
+    }
+
+    fn sign_digest(&self, digest: &[u8]) -> Result<Vec<u8>> {
+        (**self).sign_digest(digest)
+    }
+
+    fn sign_message(&self, message: &[u8]) -> Result<Vec<u8>> {
+        (**self).sign_message(message)
+    }
 }
@@ -2,14 +2,17 @@ use std::path::Path;
 
 use anyhow::Result;
 use openssl::{
-    cms::{CMSOptions, CmsContentInfo},
     pkcs12::{ParsedPkcs12_2, Pkcs12},
+    x509::X509,
 };
 
-use super::Sign;
+use super::{DigestAlgorithm, PKeySigner, RawSigner, Sign, SignatureScheme, SubFilter};
 
 pub struct P12Signer {
     pkcs12: ParsedPkcs12_2,
+    sub_filter: SubFilter,
+    digest: Option<DigestAlgorithm>,
+    scheme: Option<SignatureScheme>,
 }
 
 impl P12Signer {
@@ -19,20 +22,71 @@ impl P12Signer {
     pub fn load_with_password(path: impl AsRef<Path>, password: &str) -> Result<Self> {
         let der = std::fs::read(path.as_ref())?;
         let pkcs12 = Pkcs12::from_der(&der)?.parse2(password)?;
-        Ok(P12Signer { pkcs12 })
+        Ok(P12Signer {
+            pkcs12,
+            sub_filter: SubFilter::default(),
+            digest: None,
+            scheme: None,
+        })
+    }
+
+    pub fn with_sub_filter(mut self, sub_filter: SubFilter) -> Self {
+        self.sub_filter = sub_filter;
+        self
+    }
+
+    /// The digest algorithm, SHA-256 by default and SHA-512 for Ed25519 keys.
+    pub fn with_digest(mut self, digest: DigestAlgorithm) -> Self {
+        self.digest = Some(digest);
+        self
+    }
+
+    /// The signature scheme, it must fit the key. PKCS#1 v1.5 for RSA keys by default.
+    pub fn with_scheme(mut self, scheme: SignatureScheme) -> Self {
+        self.scheme = Some(scheme);
+        self
+    }
+
+    fn key_signer(&self) -> Result<PKeySigner<'_>> {
+        let cert = self
+            .pkcs12
+            .cert
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("No certificate found in PKCS#12"))?;
+        let pkey = self
+            .pkcs12
+            .pkey
+            .as_deref()
+            .ok_or_else(|| anyhow::anyhow!("No private key found in PKCS#12"))?;
+        let chain = self.pkcs12.ca.iter().flatten().map(ToOwned::to_owned);
+        Ok(PKeySigner::new(cert, pkey)
+            .with_chain(chain.collect())
+            .with_algorithms(self.digest, self.scheme))
     }
 }
 
 impl Sign for P12Signer {
     fn sign(&self, data: &[u8]) -> Result<Vec<u8>> {
-        CmsContentInfo::sign(
-            self.pkcs12.cert.as_deref(),
-            self.pkcs12.pkey.as_deref(),
-            self.pkcs12.ca.as_deref(),
-            Some(data),
-            CMSOptions::DETACHED | CMSOptions::BINARY,
-        )?
-        .to_der()
-        .map_err(Into::into)
+        self.key_signer()?.sign_cms(data, self.sub_filter)
+    }
+
+    fn sub_filter(&self) -> SubFilter {
+        self.sub_filter
+    }
+
+    /// The signer certificate followed by the CA certificates of the bundle.
+    fn certificates(&self) -> Vec<X509> {
+        self.pkcs12
+            .cert
+            .iter()
+            .cloned()
+            .chain(self.pkcs12.ca.iter().flatten().map(ToOwned::to_owned))
+            .collect()
+    }
+
+    fn digest_algorithm(&self) -> DigestAlgorithm {
+        self.key_signer()
+            .map(|signer| signer.digest_algorithm())
+            .unwrap_or_default()
     }
 }
@@ -0,0 +1,146 @@
+use std::path::Path;
+
+use anyhow::Result;
+use openssl::{
+    pkey::{PKey, Private},
+    x509::X509,
+};
+
+use super::{DigestAlgorithm, PKeySigner, RawSigner, Sign, SignatureScheme, SubFilter};
+
+/// Signs with a certificate and a separate PKCS#8 private key, both PEM or DER.
+///
+/// The certificate file may be a PEM bundle, the certificate of the key is the
+/// signer and the others are embedded as its chain.
+pub struct PemSigner {
+    cert: X509,
+    pkey: PKey<Private>,
+    chain: Vec<X509>,
+    sub_filter: SubFilter,
+    digest: Option<DigestAlgorithm>,
+    scheme: Option<SignatureScheme>,
+}
+
+impl PemSigner {
+    pub fn load(cert: impl AsRef<Path>, key: impl AsRef<Path>) -> Result<Self> {
+        Self::load_key(cert, key, None)
+    }
+
+    /// Load an encrypted private key.
+    pub fn load_with_password(
+        cert: impl AsRef<Path>,
+        key: impl AsRef<Path>,
+        password: &str,
+    ) -> Result<Self> {
+        Self::load_key(cert, key, Some(password))
+    }
+
+    fn load_key(
+        cert: impl AsRef<Path>,
+        key: impl AsRef<Path>,
+        password: Option<&str>,
+    ) -> Result<Self> {
+        let mut certs = read_certs(cert)?;
+        let key = std::fs::read(key)?;
+        let pkey = match (key.starts_with(b"-----"), password) {
+            (true, Some(password)) => {
+                PKey::private_key_from_pem_passphrase(&key, password.as_bytes())
+            }
+            // Fail instead of prompting on the terminal for an encrypted key
+            (true, None) => PKey::private_key_from_pem_callback(&key, |_| Ok(0)),
+            (false, Some(password)) => {
+                PKey::private_key_from_pkcs8_passphrase(&key, password.as_bytes())
+            }
+            (false, None) => PKey::private_key_from_der(&key),
+        }
+        .map_err(|err| match password {
+            Some(_) => anyhow::anyhow!(
+                "Cannot decrypt the private key, the password may be wrong: {}",
+                err
+            ),
+            None if key.windows(9).any(|window| window == b"ENCRYPTED") => {
+                anyhow::anyhow!("The private key is encrypted, a password is required")
+            }
+            None => err.into(),
+        })?;
+        // The certificate of the key, the rest of a bundle is its chain
+        let mut index = None;
+        for (i, cert) in certs.iter().enumerate() {
+            if cert.public_key()?.public_eq(&pkey) {
+                index = Some(i);
+                break;
+            }
+        }
+        let Some(index) = index else {
+            return Err(anyhow::anyhow!(
+                "The private key does not match the certificate"
+            ));
+        };
+        let cert = certs.remove(index);
+        Ok(PemSigner {
+            cert,
+            pkey,
+            chain: certs,
+            sub_filter: SubFilter::default(),
+            digest: None,
+            scheme: None,
+        })
+    }
+
+    /// Add the intermediate certificates of a PEM bundle or a single DER certificate.
+    pub fn with_chain_file(mut self, path: impl AsRef<Path>) -> Result<Self> {
+        self.chain.extend(read_certs(path)?);
+        Ok(self)
+    }
+
+    pub fn with_sub_filter(mut self, sub_filter: SubFilter) -> Self {
+        self.sub_filter = sub_filter;
+        self
+    }
+
+    /// The digest algorithm, SHA-256 by default and SHA-512 for Ed25519 keys.
+    pub fn with_digest(mut self, digest: DigestAlgorithm) -> Self {
+        self.digest = Some(digest);
+        self
+    }
+
+    /// The signature scheme, it must fit the key. PKCS#1 v1.5 for RSA keys by default.
+    pub fn with_scheme(mut self, scheme: SignatureScheme) -> Self {
+        self.scheme = Some(scheme);
+        self
+    }
+
+    fn key_signer(&self) -> PKeySigner<'_> {
+        PKeySigner::new(&self.cert, &self.pkey)
+            .with_chain(self.chain.clone())
+            .with_algorithms(self.digest, self.scheme)
+    }
+}
+
+fn read_certs(path: impl AsRef<Path>) -> Result<Vec<X509>> {
+    let data = std::fs::read(path)?;
+    match X509::stack_from_pem(&data) {
+        Ok(certs) if !certs.is_empty() => Ok(certs),
+        _ => Ok(vec![X509::from_der(&data)?]),
+    }
+}
+
+impl Sign for PemSigner {
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.key_signer().sign_cms(data, self.sub_filter)
+    }
+
+    fn sub_filter(&self) -> SubFilter {
+        self.sub_filter
+    }
+
+    fn certificates(&self) -> Vec<X509> {
+        let mut certs = vec![self.cert.clone()];
+        certs.extend(self.chain.iter().cloned());
+        certs
+    }
+
+    fn digest_algorithm(&self) -> DigestAlgorithm {
+        self.key_signer().digest_algorithm()
+    }
+}
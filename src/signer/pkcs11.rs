@@ -0,0 +1,416 @@
+//! Pkcs11
+//! Sign with a non-exportable key through a PKCS#11 module, such as SoftHSMv2 or an HSM.
+use std::{
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use anyhow::Result;
+use cryptoki::{
+    context::{CInitializeArgs, CInitializeFlags, Pkcs11},
+    error::{Error, RvError},
+    mechanism::{
+        Mechanism, MechanismType,
+        eddsa::{EddsaParams, EddsaSignatureScheme},
+        rsa::{PkcsMgfType, PkcsPssParams},
+    },
+    object::{Attribute, AttributeType, KeyType, ObjectClass, ObjectHandle},
+    session::{Session, UserType},
+    types::AuthPin,
+};
+use der::{Decode, asn1::OctetStringRef};
+use openssl::{
+    bn::{BigNum, BigNumContext},
+    ec::PointConversionForm,
+    ecdsa::EcdsaSig,
+    hash::hash,
+    pkey::Id,
+    x509::X509,
+};
+
+use super::{DigestAlgorithm, RawSigner, SignatureScheme};
+use crate::verify::name_to_string;
+
+/// The DER `DigestInfo` prefixes of the SHA-2 digests, `CKM_RSA_PKCS` signs the
+/// DigestInfo rather than the bare digest.
+const SHA256_DIGEST_INFO: &[u8] = &[
+    0x30, 0x31, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x01, 0x05,
+    0x00, 0x04, 0x20,
+];
+const SHA384_DIGEST_INFO: &[u8] = &[
+    0x30, 0x41, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x02, 0x05,
+    0x00, 0x04, 0x30,
+];
+const SHA512_DIGEST_INFO: &[u8] = &[
+    0x30, 0x51, 0x30, 0x0d, 0x06, 0x09, 0x60, 0x86, 0x48, 0x01, 0x65, 0x03, 0x04, 0x02, 0x03, 0x05,
+    0x00, 0x04, 0x40,
+];
+
+/// A [`RawSigner`] whose private key never leaves the PKCS#11 token, wrap it in
+/// [`CmsSigner`](super::CmsSigner) to sign documents.
+///
+/// The module is never finalized, other users of it in the process keep their
+/// sessions. Dropping the signer only closes its own session.
+pub struct Pkcs11Signer {
+    // A session must not be used by two threads at once
+    session: Mutex<Session>,
+    key: ObjectHandle,
+    key_type: KeyType,
+    cert: X509,
+    chain: Vec<X509>,
+    digest: Option<DigestAlgorithm>,
+    scheme: Option<SignatureScheme>,
+}
+
+impl Pkcs11Signer {
+    pub fn builder(module: impl AsRef<Path>) -> Pkcs11SignerBuilder {
+        Pkcs11SignerBuilder {
+            module: module.as_ref().to_path_buf(),
+            token_label: None,
+            pin: None,
+            key_label: None,
+            certificate: None,
+            chain: Vec::new(),
+            digest: None,
+            scheme: None,
+        }
+    }
+
+    fn sign_with(&self, mechanism: &Mechanism, data: &[u8]) -> Result<Vec<u8>> {
+        let session = self
+            .session
+            .lock()
+            .map_err(|_| anyhow::anyhow!("PKCS#11 session poisoned"))?;
+        Ok(session.sign(mechanism, self.key, data)?)
+    }
+}
+
+impl RawSigner for Pkcs11Signer {
+    fn certificate(&self) -> X509 {
+        self.cert.clone()
+    }
+
+    fn chain(&self) -> Vec<X509> {
+        self.chain.clone()
+    }
+
+    fn digest_algorithm(&self) -> DigestAlgorithm {
+        self.digest.unwrap_or_else(|| {
+            self.signature_scheme()
+                .map(|scheme| scheme.default_digest())
+                .unwrap_or_default()
+        })
+    }
+
+    fn signature_scheme(&self) -> Result<SignatureScheme> {
+        match (self.scheme, self.key_type) {
+            (Some(scheme), _) => Ok(scheme),
+            (None, KeyType::RSA) => Ok(SignatureScheme::RsaPkcs1v15),
+            (None, KeyType::EC) => Ok(SignatureScheme::Ecdsa),
+            (None, KeyType::EC_EDWARDS) => Ok(SignatureScheme::Ed25519),
+            (None, key_type) => Err(anyhow::anyhow!(
+                "Unsupported PKCS#11 key type: {}",
+                key_type
+            )),
+        }
+    }
+
+    fn sign_digest(&self, digest: &[u8]) -> Result<Vec<u8>> {
+        let algorithm = self.digest_algorithm();
+        match self.signature_scheme()? {
+            SignatureScheme::RsaPkcs1v15 => {
+                let mut digest_info = match algorithm {
+                    DigestAlgorithm::Sha256 => SHA256_DIGEST_INFO,
+                    DigestAlgorithm::Sha384 => SHA384_DIGEST_INFO,
+                    DigestAlgorithm::Sha512 => SHA512_DIGEST_INFO,
+                }
+                .to_vec();
+                digest_info.extend_from_slice(digest);
+                self.sign_with(&Mechanism::RsaPkcs, &digest_info)
+            }
+            SignatureScheme::RsaPss => {
+                let (hash_alg, mgf) = match algorithm {
+                    DigestAlgorithm::Sha256 => (MechanismType::SHA256, PkcsMgfType::MGF1_SHA256),
+                    DigestAlgorithm::Sha384 => (MechanismType::SHA384, PkcsMgfType::MGF1_SHA384),
+                    DigestAlgorithm::Sha512 => (MechanismType::SHA512, PkcsMgfType::MGF1_SHA512),
+                };
+                let params = PkcsPssParams {
+                    hash_alg,
+                    mgf,
+                    s_len: (algorithm.len() as u64).into(),
+                };
+                self.sign_with(&Mechanism::RsaPkcsPss(params), digest)
+            }
+            SignatureScheme::Ecdsa => {
+                // CKM_ECDSA returns r || s, CMS wants a DER ECDSA-Sig-Value
+                let signature = self.sign_with(&Mechanism::Ecdsa, digest)?;
+                let (r, s) = signature.split_at(signature.len() / 2);
+                let signature = EcdsaSig::from_private_components(
+                    BigNum::from_slice(r)?,
+                    BigNum::from_slice(s)?,
+                )?;
+                Ok(signature.to_der()?)
+            }
+            SignatureScheme::Ed25519 => {
+                Err(anyhow::anyhow!("Ed25519 signs the message, not a digest"))
+            }
+        }
+    }
+
+    fn sign_message(&self, message: &[u8]) -> Result<Vec<u8>> {
+        if self.signature_scheme()? == SignatureScheme::Ed25519 {
+            let params = EddsaParams::new(EddsaSignatureScheme::Ed25519);
+            return self.sign_with(&Mechanism::Eddsa(params), message);
+        }
+        let digest = hash(self.digest_algorithm().message_digest(), message)?;
+        self.sign_digest(&digest)
+    }
+}
+
+pub struct Pkcs11SignerBuilder {
+    module: PathBuf,
+    token_label: Option<String>,
+    pin: Option<String>,
+    key_label: Option<String>,
+    certificate: Option<X509>,
+    chain: Vec<X509>,
+    digest: Option<DigestAlgorithm>,
+    scheme: Option<SignatureScheme>,
+}
+
+impl Pkcs11SignerBuilder {
+    /// The label of the token, the first token present is used if unset.
+    pub fn token_label(mut self, label: impl Into<String>) -> Self {
+        self.token_label = Some(label.into());
+        self
+    }
+
+    /// The user PIN, the session is not logged in if unset.
+    pub fn pin(mut self, pin: impl Into<String>) -> Self {
+        self.pin = Some(pin.into());
+        self
+    }
+
+    /// The `CKA_LABEL` of the private key. Without it the key of the given
+    /// certificate is used, or the first key with a certificate on the token.
+    pub fn key_label(mut self, label: impl Into<String>) -> Self {
+        self.key_label = Some(label.into());
+        self
+    }
+
+    /// The signer certificate, by default the certificate object with the
+    /// `CKA_ID` of the key is read from the token.
+    pub fn certificate(mut self, cert: X509) -> Self {
+        self.certificate = Some(cert);
+        self
+    }
+
+    /// CA certificates to embed in the signature.
+    pub fn chain(mut self, chain: Vec<X509>) -> Self {
+        self.chain = chain;
+        self
+    }
+
+    /// The digest algorithm, SHA-256 by default and SHA-512 for Ed25519 keys.
+    pub fn digest(mut self, digest: DigestAlgorithm) -> Self {
+        self.digest = Some(digest);
+        self
+    }
+
+    /// The signature scheme, it must fit the key. PKCS#1 v1.5 for RSA keys by default.
+    pub fn scheme(mut self, scheme: SignatureScheme) -> Self {
+        self.scheme = Some(scheme);
+        self
+    }
+
+    pub fn open(self) -> Result<Pkcs11Signer> {
+        let pkcs11 = Pkcs11::new(&self.module)?;
+        // Another user of the module in this process may have initialized it already
+        match pkcs11.initialize(CInitializeArgs::new(CInitializeFlags::OS_LOCKING_OK)) {
+            Ok(()) | Err(Error::Pkcs11(RvError::CryptokiAlreadyInitialized, _)) => {}
+            Err(err) => return Err(err.into()),
+        }
+
+        let mut slot = None;
+        for candidate in pkcs11.get_slots_with_token()? {
+            let info = pkcs11.get_token_info(candidate)?;
+            match &self.token_label {
+                // Labels are blank padded
+                Some(wanted) if info.label().trim_end() != wanted => continue,
+                _ => {
+                    slot = Some(candidate);
+                    break;
+                }
+            }
+        }
+        let slot = slot.ok_or_else(|| {
+            anyhow::anyhow!(
+                "PKCS#11 token {} not found",
+                self.token_label.as_deref().unwrap_or("(any)")
+            )
+        })?;
+
+        let session = pkcs11.open_rw_session(slot)?;
+        if let Some(pin) = self.pin {
+            match session.login(UserType::User, Some(&AuthPin::new(pin.into()))) {
+                Ok(()) | Err(Error::Pkcs11(RvError::UserAlreadyLoggedIn, _)) => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+
+        let mut template = vec![Attribute::Class(ObjectClass::PRIVATE_KEY)];
+        if let Some(label) = &self.key_label {
+            template.push(Attribute::Label(label.as_bytes().to_vec()));
+        }
+        let label = self.key_label.as_deref().unwrap_or("(any)");
+        let keys = session.find_objects(&template)?;
+        if keys.is_empty() {
+            return Err(anyhow::anyhow!("PKCS#11 private key {} not found", label));
+        }
+        // The first key that is the key of the certificate, or has one on the token
+        let mut found = None;
+        for key in keys {
+            let attributes =
+                session.get_attributes(key, &[AttributeType::KeyType, AttributeType::Id])?;
+            let key_type = attributes
+                .iter()
+                .find_map(|attr| match attr {
+                    Attribute::KeyType(key_type) => Some(*key_type),
+                    _ => None,
+                })
+                .ok_or_else(|| anyhow::anyhow!("Invalid PKCS#11 key type"))?;
+            let id = attributes
+                .into_iter()
+                .find_map(|attr| match attr {
+                    Attribute::Id(id) => Some(id),
+                    _ => None,
+                })
+                .unwrap_or_default();
+            let cert = match &self.certificate {
+                Some(cert) => is_key_of(&session, key, key_type, &id, cert)?.then(|| cert.clone()),
+                None => token_certificate(&session, id)?,
+            };
+            if let Some(cert) = cert {
+                found = Some((key, key_type, cert));
+                break;
+            }
+        }
+        let (key, key_type, cert) = found.ok_or_else(|| match &self.certificate {
+            Some(cert) => anyhow::anyhow!(
+                "No PKCS#11 private key {} is the key of the certificate {}",
+                label,
+                name_to_string(cert.subject_name())
+            ),
+            None => anyhow::anyhow!(
+                "No PKCS#11 private key {} has a certificate with its CKA_ID on the token",
+                label
+            ),
+        })?;
+        Ok(Pkcs11Signer {
+            session: Mutex::new(session),
+            key,
+            key_type,
+            cert,
+            chain: self.chain,
+            digest: self.digest,
+            scheme: self.scheme,
+        })
+    }
+}
+
+/// The certificate object with the `CKA_ID` of a key, `None` if the token has none.
+fn token_certificate(session: &Session, id: Vec<u8>) -> Result<Option<X509>> {
+    if id.is_empty() {
+        return Ok(None);
+    }
+    let template = [
+        Attribute::Class(ObjectClass::CERTIFICATE),
+        Attribute::Id(id),
+    ];
+    let Some(cert) = session.find_objects(&template)?.first().copied() else {
+        return Ok(None);
+    };
+    let value = session
+        .get_attributes(cert, &[AttributeType::Value])?
+        .into_iter()
+        .find_map(|attr| match attr {
+            Attribute::Value(value) => Some(value),
+            _ => None,
+        })
+        .ok_or_else(|| anyhow::anyhow!("PKCS#11 certificate has no value"))?;
+    Ok(Some(X509::from_der(&value)?))
+}
+
+/// Whether the private key `key` is the key of `cert`: the token holds `cert`
+/// with the `CKA_ID` of the key, or the key pair has the public key of `cert`.
+fn is_key_of(
+    session: &Session,
+    key: ObjectHandle,
+    key_type: KeyType,
+    id: &[u8],
+    cert: &X509,
+) -> Result<bool> {
+    if !id.is_empty() {
+        let template = [
+            Attribute::Class(ObjectClass::CERTIFICATE),
+            Attribute::Id(id.to_vec()),
+            Attribute::Value(cert.to_der()?),
+        ];
+        if !session.find_objects(&template)?.is_empty() {
+            return Ok(true);
+        }
+    }
+    let public_key = cert.public_key()?;
+    match key_type {
+        KeyType::RSA => {
+            let modulus = session
+                .get_attributes(key, &[AttributeType::Modulus])?
+                .into_iter()
+                .find_map(|attr| match attr {
+                    Attribute::Modulus(modulus) => Some(modulus),
+                    _ => None,
+                });
+            Ok(public_key
+                .rsa()
+                .is_ok_and(|rsa| modulus == Some(rsa.n().to_vec())))
+        }
+        // A private key has no EC point, only the public key object of its pair
+        KeyType::EC | KeyType::EC_EDWARDS if !id.is_empty() => {
+            let point = match public_key.id() {
+                Id::EC => {
+                    let ec_key = public_key.ec_key()?;
+                    let mut context = BigNumContext::new()?;
+                    ec_key.public_key().to_bytes(
+                        ec_key.group(),
+                        PointConversionForm::UNCOMPRESSED,
+                        &mut context,
+                    )?
+                }
+                _ => public_key.raw_public_key()?,
+            };
+            let template = [
+                Attribute::Class(ObjectClass::PUBLIC_KEY),
+                Attribute::Id(id.to_vec()),
+            ];
+            for public in session.find_objects(&template)? {
+                let ec_point = session
+                    .get_attributes(public, &[AttributeType::EcPoint])?
+                    .into_iter()
+                    .find_map(|attr| match attr {
+                        Attribute::EcPoint(ec_point) => Some(ec_point),
+                        _ => None,
+                    })
+                    .unwrap_or_default();
+                // CKA_EC_POINT is a DER OCTET STRING, some modules store the bare point
+                let bare = OctetStringRef::from_der(&ec_point)
+                    .map(|octets| octets.as_bytes() == point)
+                    .unwrap_or(false);
+                if bare || ec_point == point {
+                    return Ok(true);
+                }
+            }
+            Ok(false)
+        }
+        _ => Ok(false),
+    }
+}
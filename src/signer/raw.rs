@@ -0,0 +1,193 @@
+use anyhow::Result;
+use openssl::{
+    cms::{CMSOptions, CmsContentInfo},
+    hash::hash,
+    pkey::{PKeyRef, Private},
+    pkey_ctx::PkeyCtx,
+    rsa::Padding,
+    sign::{RsaPssSaltlen, Signer},
+    stack::Stack,
+    x509::{X509, X509Ref},
+};
+
+use super::{DigestAlgorithm, RawSigner, Sign, SignatureScheme, SubFilter};
+use crate::cms::CadesBuilder;
+
+/// Signs digests with a private key held in memory.
+pub(crate) struct PKeySigner<'a> {
+    cert: &'a X509Ref,
+    pkey: &'a PKeyRef<Private>,
+    chain: Vec<X509>,
+    digest: Option<DigestAlgorithm>,
+    scheme: Option<SignatureScheme>,
+}
+
+impl<'a> PKeySigner<'a> {
+    pub(crate) fn new(cert: &'a X509Ref, pkey: &'a PKeyRef<Private>) -> Self {
+        PKeySigner {
+            cert,
+            pkey,
+            chain: Vec::new(),
+            digest: None,
+            scheme: None,
+        }
+    }
+
+    pub(crate) fn with_chain(mut self, chain: Vec<X509>) -> Self {
+        self.chain = chain;
+        self
+    }
+
+    /// Override the default algorithms of the key, `None` keeps the default.
+    pub(crate) fn with_algorithms(
+        mut self,
+        digest: Option<DigestAlgorithm>,
+        scheme: Option<SignatureScheme>,
+    ) -> Self {
+        self.digest = digest;
+        self.scheme = scheme;
+        self
+    }
+
+    /// Whether the digest and the scheme are the defaults of an RSA or EC key,
+    /// which OpenSSL uses as well. OpenSSL has no default for Ed25519 keys.
+    fn default_algorithms(&self) -> Result<bool> {
+        let scheme = SignatureScheme::for_certificate(self.cert)?;
+        Ok(scheme != SignatureScheme::Ed25519
+            && self.signature_scheme()? == scheme
+            && self.digest_algorithm() == scheme.default_digest())
+    }
+
+    /// Sign `data` in the CMS format of `sub_filter`.
+    pub(crate) fn sign_cms(&self, data: &[u8], sub_filter: SubFilter) -> Result<Vec<u8>> {
+        match sub_filter {
+            // Keep the OpenSSL output unless other algorithms are chosen
+            SubFilter::Pkcs7Detached if self.default_algorithms()? => {
+                let mut chain = Stack::new()?;
+                for cert in &self.chain {
+                    chain.push(cert.clone())?;
+                }
+                CmsContentInfo::sign(
+                    Some(self.cert),
+                    Some(self.pkey),
+                    Some(&chain),
+                    Some(data),
+                    CMSOptions::DETACHED | CMSOptions::BINARY,
+                )?
+                .to_der()
+                .map_err(Into::into)
+            }
+            SubFilter::Pkcs7Detached => CadesBuilder::new(self)
+                .with_signing_time()
+                .sign_detached(data),
+            SubFilter::CadesDetached => CadesBuilder::new(self).sign_detached(data),
+            SubFilter::Rfc3161 => Err(anyhow::anyhow!(
+                "Document timestamps are issued by a TSA, not a key signer"
+            )),
+        }
+    }
+}
+
+impl RawSigner for PKeySigner<'_> {
+    fn certificate(&self) -> X509 {
+        self.cert.to_owned()
+    }
+
+    fn chain(&self) -> Vec<X509> {
+        self.chain.clone()
+    }
+
+    fn digest_algorithm(&self) -> DigestAlgorithm {
+        self.digest.unwrap_or_else(|| {
+            self.signature_scheme()
+                .map(|scheme| scheme.default_digest())
+                .unwrap_or_default()
+        })
+    }
+
+    fn signature_scheme(&self) -> Result<SignatureScheme> {
+        match self.scheme {
+            Some(scheme) => Ok(scheme),
+            None => SignatureScheme::for_certificate(self.cert),
+        }
+    }
+
+    fn sign_digest(&self, digest: &[u8]) -> Result<Vec<u8>> {
+        let md = self.digest_algorithm().md();
+        let mut ctx = PkeyCtx::new(self.pkey)?;
+        ctx.sign_init()?;
+        match self.signature_scheme()? {
+            SignatureScheme::RsaPkcs1v15 => ctx.set_rsa_padding(Padding::PKCS1)?,
+            SignatureScheme::RsaPss => {
+                ctx.set_rsa_padding(Padding::PKCS1_PSS)?;
+                ctx.set_rsa_mgf1_md(md)?;
+                ctx.set_rsa_pss_saltlen(RsaPssSaltlen::DIGEST_LENGTH)?;
+            }
+            SignatureScheme::Ecdsa => {}
+            SignatureScheme::Ed25519 => {
+                return Err(anyhow::anyhow!("Ed25519 signs the message, not a digest"));
+            }
+        }
+        ctx.set_signature_md(md)?;
+        let mut signature = Vec::new();
+        ctx.sign_to_vec(digest, &mut signature)?;
+        Ok(signature)
+    }
+
+    fn sign_message(&self, message: &[u8]) -> Result<Vec<u8>> {
+        if self.signature_scheme()? == SignatureScheme::Ed25519 {
+            let mut signer = Signer::new_without_digest(self.pkey)?;
+            return Ok(signer.sign_oneshot_to_vec(message)?);
+        }
+        let digest = hash(self.digest_algorithm().message_digest(), message)?;
+        self.sign_digest(&digest)
+    }
+}
+
+/// A [`Sign`] implementation that assembles the CMS SignedData around a [`RawSigner`].
+pub struct CmsSigner<S> {
+    raw: S,
+    sub_filter: SubFilter,
+}
+
+impl<S: RawSigner> CmsSigner<S> {
+    pub fn new(raw: S) -> Self {
+        CmsSigner {
+            raw,
+            sub_filter: SubFilter::default(),
+        }
+    }
+
+    pub fn with_sub_filter(mut self, sub_filter: SubFilter) -> Self {
+        self.sub_filter = sub_filter;
+        self
+    }
+}
+
+impl<S: RawSigner> Sign for CmsSigner<S> {
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>> {
+        match self.sub_filter {
+            SubFilter::Pkcs7Detached => CadesBuilder::new(&self.raw)
+                .with_signing_time()
+                .sign_detached(data),
+            SubFilter::CadesDetached => CadesBuilder::new(&self.raw).sign_detached(data),
+            SubFilter::Rfc3161 => Err(anyhow::anyhow!(
+                "Document timestamps are issued by a TSA, not a CMS signer"
+            )),
+        }
+    }
+
+    fn sub_filter(&self) -> SubFilter {
+        self.sub_filter
+    }
+
+    fn certificates(&self) -> Vec<X509> {
+        let mut certs = vec![self.raw.certificate()];
+        certs.extend(self.raw.chain());
+        certs
+    }
+
+    fn digest_algorithm(&self) -> DigestAlgorithm {
+        self.raw.digest_algorithm()
+    }
+}
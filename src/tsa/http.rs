@@ -0,0 +1,92 @@
+use std::{
+    io::Read,
+    sync::{
+        Arc,
+        atomic::{AtomicUsize, Ordering},
+    },
+};
+
+use anyhow::Result;
+use der::{Decode, Encode};
+
+use super::{TimestampAuthority, message_imprint, random_int};
+use crate::cms::{
+    decode_signed_data,
+    tsp::{TimeStampReq, TimeStampResp, TstInfo},
+};
+
+/// A TSA reached over HTTP(S) with the `application/timestamp-query` protocol.
+pub struct HttpTimestampAuthority {
+    url: String,
+    agent: ureq::Agent,
+    // The length of the last token, 0 before the first request
+    token_len: AtomicUsize,
+}
+
+impl HttpTimestampAuthority {
+    pub fn new(url: impl Into<String>) -> Result<Self> {
+        let agent = ureq::AgentBuilder::new()
+            .tls_connector(Arc::new(native_tls::TlsConnector::new()?))
+            .build();
+        Ok(HttpTimestampAuthority {
+            url: url.into(),
+            agent,
+            token_len: AtomicUsize::new(0),
+        })
+    }
+}
+
+impl TimestampAuthority for HttpTimestampAuthority {
+    fn timestamp(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let imprint = message_imprint(data)?;
+        let nonce = random_int()?;
+        let request = TimeStampReq {
+            version: 1,
+            message_imprint: imprint.clone(),
+            req_policy: None,
+            nonce: Some(nonce.clone()),
+            cert_req: Some(true),
+        };
+
+        let response = self
+            .agent
+            .post(&self.url)
+            .set("Content-Type", "application/timestamp-query")
+            .send_bytes(&request.to_der()?)?;
+        let mut body = Vec::new();
+        response.into_reader().read_to_end(&mut body)?;
+
+        let response = TimeStampResp::from_der(&body)?;
+        if !response.status.is_granted() {
+            return Err(anyhow::anyhow!(
+                "TSA rejected the request: status {} {:?}",
+                response.status.status,
+                response.status.status_string.unwrap_or_default()
+            ));
+        }
+        let token = response
+            .time_stamp_token
+            .ok_or_else(|| anyhow::anyhow!("TSA response has no TimeStampToken"))?
+            .to_der()?;
+
+        // Make sure the token answers this request
+        let econtent = decode_signed_data(&token)?
+            .encap_content_info
+            .econtent
+            .ok_or_else(|| anyhow::anyhow!("TimeStampToken has no TSTInfo"))?;
+        let tst_info = TstInfo::from_der(econtent.value())?;
+        if tst_info.message_imprint != imprint {
+            return Err(anyhow::anyhow!("TimeStampToken message imprint mismatch"));
+        }
+        if tst_info.nonce.as_ref() != Some(&nonce) {
+            return Err(anyhow::anyhow!("TimeStampToken nonce mismatch"));
+        }
+        self.token_len.store(token.len(), Ordering::Relaxed);
+        Ok(token)
+    }
+
+    /// The length of the previous token, the tokens of a TSA barely vary.
+    fn estimated_token_len(&self) -> Option<usize> {
+        Some(self.token_len.load(Ordering::Relaxed)).filter(|len| *len > 0)
+    }
+}
@@ -0,0 +1,99 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use anyhow::Result;
+use chrono::Utc;
+use der::{Decode, Encode, asn1::Int};
+use openssl::{
+    asn1::Asn1Time,
+    bn::{BigNum, MsbOption},
+    ec::{EcGroup, EcKey},
+    hash::MessageDigest,
+    nid::Nid,
+    pkey::{PKey, Private},
+    x509::{
+        X509, X509NameBuilder,
+        extension::{BasicConstraints, ExtendedKeyUsage, KeyUsage},
+    },
+};
+
+use super::{TimestampAuthority, message_imprint};
+use crate::{
+    cms::{CadesBuilder, estimate_signed_data_len, oid, tsp::TstInfo},
+    signer::PKeySigner,
+};
+
+/// An in-process TSA that issues tokens with its own key, the time comes from
+/// the local clock. Intended for tests and offline environments.
+pub struct LocalTimestampAuthority {
+    cert: X509,
+    pkey: PKey<Private>,
+    serial: AtomicU64,
+}
+
+impl LocalTimestampAuthority {
+    pub fn new(cert: X509, pkey: PKey<Private>) -> Self {
+        LocalTimestampAuthority {
+            cert,
+            pkey,
+            serial: AtomicU64::new(1),
+        }
+    }
+
+    /// Create a TSA with a fresh P-256 key and a self-signed time-stamping certificate.
+    pub fn generate() -> Result<Self> {
+        let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1)?;
+        let pkey = PKey::from_ec_key(EcKey::generate(&group)?)?;
+
+        let mut name = X509NameBuilder::new()?;
+        name.append_entry_by_nid(Nid::COMMONNAME, "pdf_modify local TSA")?;
+        let name = name.build();
+
+        let mut serial = BigNum::new()?;
+        serial.rand(64, MsbOption::MAYBE_ZERO, false)?;
+
+        let mut builder = X509::builder()?;
+        builder.set_version(2)?;
+        builder.set_serial_number(serial.to_asn1_integer()?.as_ref())?;
+        builder.set_subject_name(&name)?;
+        builder.set_issuer_name(&name)?;
+        builder.set_pubkey(&pkey)?;
+        builder.set_not_before(Asn1Time::days_from_now(0)?.as_ref())?;
+        builder.set_not_after(Asn1Time::days_from_now(365)?.as_ref())?;
+        builder.append_extension(BasicConstraints::new().critical().build()?)?;
+        builder.append_extension(KeyUsage::new().critical().digital_signature().build()?)?;
+        builder.append_extension(ExtendedKeyUsage::new().critical().time_stamping().build()?)?;
+        builder.sign(&pkey, MessageDigest::sha256())?;
+
+        Ok(Self::new(builder.build(), pkey))
+    }
+
+    pub fn certificate(&self) -> &X509 {
+        &self.cert
+    }
+}
+
+impl TimestampAuthority for LocalTimestampAuthority {
+    fn timestamp(&self, data: &[u8]) -> Result<Vec<u8>> {
+        let serial = self.serial.fetch_add(1, Ordering::Relaxed);
+        let tst_info = TstInfo {
+            version: 1,
+            policy: oid::ANY_POLICY,
+            message_imprint: message_imprint(data)?,
+            serial_number: Int::from_der(&serial.to_der()?)?,
+            gen_time: TstInfo::encode_gen_time(Utc::now())?,
+            accuracy: None,
+            ordering: None,
+            nonce: None,
+            tsa: None,
+            extensions: None,
+        };
+        CadesBuilder::new(&PKeySigner::new(&self.cert, &self.pkey))
+            .sign_encapsulated(oid::ID_CT_TST_INFO, &tst_info.to_der()?)
+    }
+
+    fn estimated_token_len(&self) -> Option<usize> {
+        estimate_signed_data_len(std::slice::from_ref(&self.cert))
+            .ok()
+            .flatten()
+    }
+}
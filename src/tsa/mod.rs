@@ -0,0 +1,76 @@
+//! TSA
+//! RFC 3161 time-stamping authorities used to obtain trusted time for signatures.
+use anyhow::Result;
+use der::asn1::{Int, OctetString};
+use openssl::hash::{MessageDigest, hash};
+use x509_cert::spki::AlgorithmIdentifierOwned;
+
+pub use self::http::HttpTimestampAuthority;
+pub use self::local::LocalTimestampAuthority;
+use crate::{
+    cms::{oid, tsp::MessageImprint},
+    config::TIMESTAMP_TOKEN_RESERVE,
+    signer::{Sign, SubFilter},
+};
+
+mod http;
+mod local;
+
+pub trait TimestampAuthority {
+    /// Return a DER encoded RFC 3161 TimeStampToken over the SHA-256 digest of `data`.
+    fn timestamp(&self, data: &[u8]) -> Result<Vec<u8>>;
+
+    /// An upper bound of the token length, it sizes the signature placeholder.
+    /// `None` if unknown, a fixed amount is reserved then.
+    fn estimated_token_len(&self) -> Option<usize> {
+        None
+    }
+}
+
+/// Adapts a TSA to [`Sign`] so a document timestamp can fill a signature placeholder.
+pub(crate) struct DocumentTimestamper<'a> {
+    tsa: &'a dyn TimestampAuthority,
+}
+
+impl<'a> DocumentTimestamper<'a> {
+    pub(crate) fn new(tsa: &'a dyn TimestampAuthority) -> Self {
+        DocumentTimestamper { tsa }
+    }
+}
+
+impl Sign for DocumentTimestamper<'_> {
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.tsa.timestamp(data)
+    }
+
+    fn sub_filter(&self) -> SubFilter {
+        SubFilter::Rfc3161
+    }
+
+    fn estimated_len(&self) -> Result<usize> {
+        Ok(self
+            .tsa
+            .estimated_token_len()
+            .unwrap_or(TIMESTAMP_TOKEN_RESERVE))
+    }
+}
+
+pub(crate) fn message_imprint(data: &[u8]) -> Result<MessageImprint> {
+    let digest = hash(MessageDigest::sha256(), data)?;
+    Ok(MessageImprint {
+        hash_algorithm: AlgorithmIdentifierOwned {
+            oid: oid::ID_SHA256,
+            parameters: None,
+        },
+        hashed_message: OctetString::new(digest.to_vec())?,
+    })
+}
+
+/// A random positive INTEGER, used for nonces and serial numbers.
+pub(crate) fn random_int() -> Result<Int> {
+    let mut bytes = [0; 8];
+    openssl::rand::rand_bytes(&mut bytes)?;
+    bytes[0] &= 0x7f;
+    bytes[0] |= 0x01;
+    Ok(Int::new(&bytes)?)
+}
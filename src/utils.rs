@@ -1,5 +1,7 @@
+use std::collections::BTreeSet;
+
 use anyhow::Result;
-use lopdf::{Dictionary, Object};
+use lopdf::{Dictionary, Document, Object, ObjectId, decode_text_string};
 
 pub(crate) struct Page<'a> {
     dict: &'a Dictionary,
@@ -64,3 +66,368 @@ impl<'a> AcroForm<'a> {
         self.dict.set(key, value);
     }
 }
+
+pub(crate) struct Dss<'a> {
+    dict: &'a mut Dictionary,
+}
+
+impl<'a> Dss<'a> {
+    pub(crate) fn new(dict: &'a mut Dictionary) -> Self {
+        Dss { dict }
+    }
+
+    pub(crate) fn extend_array(&mut self, key: &[u8], items: Vec<Object>) -> Result<()> {
+        if items.is_empty() {
+            return Ok(());
+        }
+        self.dict
+            .as_hashmap_mut()
+            .entry(key.into())
+            .or_insert_with(|| Object::Array(vec![]))
+            .as_array_mut()?
+            .extend(items);
+        Ok(())
+    }
+
+    pub(crate) fn get_or_create_vri_mut(&mut self) -> Result<&mut Dictionary> {
+        self.dict
+            .as_hashmap_mut()
+            .entry(b"VRI".into())
+            .or_insert_with(|| Object::Dictionary(Dictionary::new()))
+            .as_dict_mut()
+            .map_err(Into::into)
+    }
+}
+
+/// The `/Contents` of every signature and document timestamp dictionary.
+pub(crate) fn signature_contents(doc: &Document) -> Vec<Vec<u8>> {
+    doc.objects
+        .values()
+        .filter_map(|obj| obj.as_dict().ok())
+        .filter(|dict| {
+            matches!(
+                dict.get(b"Type").and_then(Object::as_name),
+                Ok(b"Sig" | b"DocTimeStamp")
+            )
+        })
+        .filter_map(|dict| dict.get(b"Contents").and_then(Object::as_str).ok())
+        .map(<[u8]>::to_vec)
+        .collect()
+}
+
+/// The offset just past the `%%EOF` line of every revision, in file order.
+pub(crate) fn revision_ends(data: &[u8]) -> Vec<usize> {
+    let marker = b"%%EOF";
+    let mut ends = Vec::new();
+    let mut from = 0;
+    while let Some(pos) = data[from..]
+        .windows(marker.len())
+        .position(|window| window == marker)
+    {
+        let mut end = from + pos + marker.len();
+        while matches!(data.get(end), Some(b'\r' | b'\n')) {
+            end += 1;
+        }
+        ends.push(end);
+        from = end;
+    }
+    ends
+}
+
+/// The bytes of the 1-based revision `n`, the file up to its `%%EOF` line.
+pub(crate) fn revision(data: &[u8], n: usize) -> Result<&[u8]> {
+    let ends = revision_ends(data);
+    match n.checked_sub(1).and_then(|index| ends.get(index)) {
+        Some(end) => Ok(&data[..*end]),
+        None => Err(anyhow::anyhow!(
+            "Revision {} does not exist, the document has {} revisions",
+            n,
+            ends.len()
+        )),
+    }
+}
+
+/// Load a revision without the objects its cross-reference sections free.
+/// lopdf skips free entries, so an object freed by an update would otherwise
+/// keep the value of an earlier revision.
+pub(crate) fn load_revision(data: &[u8]) -> Result<Document> {
+    let mut doc = Document::load_mem(data)?;
+    let freed = freed_objects(data, &doc);
+    doc.objects.retain(|(number, _), _| !freed.contains(number));
+    Ok(doc)
+}
+
+/// The numbers of the objects whose latest cross-reference entry is free,
+/// following `/Prev` from the last section.
+fn freed_objects(data: &[u8], doc: &Document) -> BTreeSet<u32> {
+    let mut decided = BTreeSet::new();
+    let mut freed = BTreeSet::new();
+    let mut visited = BTreeSet::new();
+    let mut offset = last_startxref(data);
+    while let Some(at) = offset.filter(|at| visited.insert(*at)) {
+        let Some((entries, prev)) = xref_table(data, at).or_else(|| xref_stream(data, at, doc))
+        else {
+            break;
+        };
+        for (number, in_use) in entries {
+            if decided.insert(number) && !in_use && number != 0 {
+                freed.insert(number);
+            }
+        }
+        offset = prev;
+    }
+    freed
+}
+
+fn last_startxref(data: &[u8]) -> Option<usize> {
+    let marker = b"startxref";
+    let at = data
+        .windows(marker.len())
+        .rposition(|window| window == marker)?;
+    tokens(&data[at + marker.len()..]).next()?.parse().ok()
+}
+
+/// The whitespace separated tokens of `data`, up to its first non UTF-8 byte.
+fn tokens(data: &[u8]) -> impl Iterator<Item = &str> {
+    let text = match std::str::from_utf8(data) {
+        Ok(text) => text,
+        Err(err) => std::str::from_utf8(&data[..err.valid_up_to()]).unwrap_or_default(),
+    };
+    text.split_ascii_whitespace()
+}
+
+/// The entries of a cross-reference section, the object number and whether
+/// it is in use, and its `/Prev`.
+type XrefSection = (Vec<(u32, bool)>, Option<usize>);
+
+/// The cross-reference table at `offset`.
+fn xref_table(data: &[u8], offset: usize) -> Option<XrefSection> {
+    let section = data.get(offset..)?.strip_prefix(b"xref")?;
+    let mut tokens = tokens(section);
+    let mut entries = Vec::new();
+    loop {
+        let start = tokens.next()?;
+        if start.starts_with("trailer") {
+            break;
+        }
+        let start = start.parse::<u32>().ok()?;
+        let count = tokens.next()?.parse::<u32>().ok()?;
+        for number in start..start.checked_add(count)? {
+            let (_, _, kind) = (tokens.next()?, tokens.next()?, tokens.next()?);
+            entries.push((number, kind == "n"));
+        }
+    }
+    let mut prev = None;
+    while let Some(token) = tokens.next() {
+        match token {
+            "startxref" => break,
+            "/Prev" => prev = tokens.next().and_then(|n| n.parse().ok()),
+            _ => {}
+        }
+    }
+    Some((entries, prev))
+}
+
+/// The cross-reference stream at `offset`.
+fn xref_stream(data: &[u8], offset: usize, doc: &Document) -> Option<XrefSection> {
+    let mut header = tokens(data.get(offset..)?);
+    let id = (header.next()?.parse().ok()?, header.next()?.parse().ok()?);
+    let stream = doc.get_object(id).ok()?.as_stream().ok()?;
+    let int = |obj: &Object| obj.as_i64().ok().and_then(|n| usize::try_from(n).ok());
+    let widths = stream
+        .dict
+        .get(b"W")
+        .and_then(Object::as_array)
+        .ok()?
+        .iter()
+        .map(int)
+        .collect::<Option<Vec<_>>>()?;
+    let [type_width, _, _] = widths[..] else {
+        return None;
+    };
+    let index = match stream.dict.get(b"Index").and_then(Object::as_array) {
+        Ok(index) => index.iter().map(int).collect::<Option<Vec<_>>>()?,
+        Err(_) => vec![0, int(stream.dict.get(b"Size").ok()?)?],
+    };
+    let content = stream
+        .decompressed_content()
+        .unwrap_or_else(|_| stream.content.clone());
+    let row = widths.iter().sum::<usize>();
+    let mut rows = content.chunks_exact(row.max(1));
+    let mut entries = Vec::new();
+    for range in index.chunks_exact(2) {
+        for number in range[0]..range[0].checked_add(range[1])? {
+            let row = rows.next()?;
+            // A missing type field means type 1, an object in use
+            let kind = match type_width {
+                0 => 1,
+                width => row[..width].iter().fold(0, |n, b| n << 8 | *b as usize),
+            };
+            entries.push((u32::try_from(number).ok()?, kind != 0));
+        }
+    }
+    let prev = stream.dict.get(b"Prev").ok().and_then(int);
+    Some((entries, prev))
+}
+
+/// The bytes up to the end of the ByteRange of the signature field
+/// `field_name`, the revision as the signer saw it.
+pub(crate) fn signed_revision<'a>(
+    data: &'a [u8],
+    doc: &Document,
+    field_name: &str,
+) -> Result<&'a [u8]> {
+    let field = terminal_fields(doc)
+        .into_iter()
+        .find(|field| field.name == field_name && field.field_type == Some(b"Sig"))
+        .ok_or_else(|| anyhow::anyhow!("Signature field {} not found", field_name))?;
+    let (_, sig) = doc.dereference(field.dict.get(b"V")?)?;
+    let byte_range = sig
+        .as_dict()?
+        .get(b"ByteRange")?
+        .as_array()?
+        .iter()
+        .map(|n| Ok(usize::try_from(n.as_i64()?)?))
+        .collect::<Result<Vec<_>>>()?;
+    let end = match byte_range[..] {
+        [0, _, _, _] => covered_end(&byte_range),
+        _ => None,
+    };
+    match end {
+        Some(end) if end <= data.len() => Ok(&data[..end]),
+        _ => Err(anyhow::anyhow!(
+            "Invalid ByteRange {:?} of {}",
+            byte_range,
+            field_name
+        )),
+    }
+}
+
+/// The offset just past the bytes a ByteRange covers.
+pub(crate) fn covered_end(byte_range: &[usize]) -> Option<usize> {
+    match byte_range {
+        [.., start, len] => start.checked_add(*len),
+        _ => None,
+    }
+}
+
+fn root_fields(doc: &Document) -> &[Object] {
+    doc.catalog()
+        .and_then(|catalog| catalog.get_deref(b"AcroForm", doc))
+        .and_then(Object::as_dict)
+        .and_then(|acro_form| acro_form.get_deref(b"Fields", doc))
+        .and_then(Object::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default()
+}
+
+/// The partial names (`/T`) of the top level AcroForm fields.
+pub(crate) fn field_names(doc: &Document) -> Vec<String> {
+    root_fields(doc)
+        .iter()
+        .filter_map(|field| doc.dereference(field).ok())
+        .filter_map(|(_, field)| field.as_dict().ok())
+        .filter_map(|field| field.get(b"T").and_then(decode_text_string).ok())
+        .collect()
+}
+
+/// A terminal field of the AcroForm field tree.
+pub(crate) struct Field<'a> {
+    /// The fully qualified name, partial names joined with `.`.
+    pub(crate) name: String,
+    /// `None` if the field is a direct dictionary.
+    pub(crate) id: Option<ObjectId>,
+    pub(crate) dict: &'a Dictionary,
+    /// The `/FT` of the field, inherited from its ancestors if absent.
+    pub(crate) field_type: Option<&'a [u8]>,
+}
+
+impl Field<'_> {
+    /// The widget annotations of the field, the field itself if it is merged with its widget.
+    pub(crate) fn widget_ids(&self, doc: &Document) -> Vec<ObjectId> {
+        if matches!(
+            self.dict.get(b"Subtype").and_then(Object::as_name),
+            Ok(b"Widget")
+        ) {
+            return self.id.into_iter().collect();
+        }
+        self.dict
+            .get_deref(b"Kids", doc)
+            .and_then(Object::as_array)
+            .map(|kids| {
+                kids.iter()
+                    .filter_map(|kid| kid.as_reference().ok())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+}
+
+/// Walk the AcroForm `Fields`/`Kids` tree and return every terminal field.
+pub(crate) fn terminal_fields(doc: &Document) -> Vec<Field<'_>> {
+    let mut fields = Vec::new();
+    for field in root_fields(doc) {
+        walk_field(doc, field, "", None, 0, &mut fields);
+    }
+    fields
+}
+
+fn walk_field<'a>(
+    doc: &'a Document,
+    obj: &'a Object,
+    parent_name: &str,
+    parent_type: Option<&'a [u8]>,
+    depth: usize,
+    fields: &mut Vec<Field<'a>>,
+) {
+    // Guard against reference cycles in malformed files
+    if depth > 32 {
+        return;
+    }
+    let (id, dict) = match obj {
+        Object::Reference(id) => match doc.get_dictionary(*id) {
+            Ok(dict) => (Some(*id), dict),
+            Err(_) => return,
+        },
+        Object::Dictionary(dict) => (None, dict),
+        _ => return,
+    };
+    let name = match dict.get(b"T").and_then(decode_text_string) {
+        Ok(partial) if parent_name.is_empty() => partial,
+        Ok(partial) => format!("{}.{}", parent_name, partial),
+        Err(_) => parent_name.to_owned(),
+    };
+    let field_type = dict
+        .get(b"FT")
+        .and_then(Object::as_name)
+        .ok()
+        .or(parent_type);
+
+    // Kids without `/T` are widget annotations rather than child fields
+    let kids = dict
+        .get_deref(b"Kids", doc)
+        .and_then(Object::as_array)
+        .map(Vec::as_slice)
+        .unwrap_or_default();
+    let child_fields = kids
+        .iter()
+        .filter(|kid| {
+            doc.dereference(kid)
+                .ok()
+                .and_then(|(_, kid)| kid.as_dict().ok())
+                .is_some_and(|kid| kid.has(b"T"))
+        })
+        .collect::<Vec<_>>();
+    if child_fields.is_empty() {
+        fields.push(Field {
+            name,
+            id,
+            dict,
+            field_type,
+        });
+        return;
+    }
+    for kid in child_fields {
+        walk_field(doc, kid, &name, field_type, depth + 1, fields);
+    }
+}
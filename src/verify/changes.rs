@@ -0,0 +1,377 @@
+//! Changes
+//! Compare the objects of two revisions and classify what a later incremental
+//! update changed.
+use std::collections::BTreeSet;
+
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use serde::Serialize;
+
+use crate::manager::DocMdpPermission;
+
+/// How an object differs between two revisions.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ObjectChange {
+    Added,
+    Modified,
+    Removed,
+}
+
+/// What an incremental update did to the document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModificationKind {
+    /// A signature was added or a signature field was signed.
+    Signature,
+    /// A document timestamp was added.
+    Timestamp,
+    /// Validation data was added to the `/DSS`.
+    Dss,
+    /// A form field value or its appearance changed, or a field was added.
+    FormFill,
+    /// An annotation other than a form field widget was added, removed or changed.
+    Annotation,
+    /// The content, resources or tree of the pages changed.
+    PageContent,
+    /// Any other object changed, or the revision could not be read.
+    Object,
+}
+
+impl ModificationKind {
+    /// Whether the change is permitted after signing, `permission` is the
+    /// DocMDP level of the certification signature, if any.
+    pub(crate) fn is_allowed(&self, permission: Option<DocMdpPermission>) -> bool {
+        let permission = permission.unwrap_or(DocMdpPermission::FormFilling);
+        match self {
+            ModificationKind::Timestamp | ModificationKind::Dss => true,
+            ModificationKind::Signature | ModificationKind::FormFill => {
+                permission != DocMdpPermission::NoChanges
+            }
+            ModificationKind::Annotation => permission == DocMdpPermission::Annotations,
+            ModificationKind::PageContent | ModificationKind::Object => false,
+        }
+    }
+}
+
+/// A change made by an incremental update after a signature.
+#[derive(Debug, Clone)]
+pub struct Modification {
+    /// The 1-based revision that made the change.
+    pub revision: usize,
+    /// The changed object, `None` if the revision as a whole is unreadable.
+    pub object_id: Option<ObjectId>,
+    pub change: ObjectChange,
+    pub kind: ModificationKind,
+    /// Permitted by the DocMDP level of the document, see [`ModificationKind`].
+    pub allowed: bool,
+}
+
+/// The objects added, modified or removed from `old` to `new`, by object number.
+pub(crate) fn object_changes(old: &Document, new: &Document) -> Vec<(ObjectId, ObjectChange)> {
+    let ids = old
+        .objects
+        .keys()
+        .chain(new.objects.keys())
+        .collect::<BTreeSet<_>>();
+    ids.into_iter()
+        .filter_map(|id| {
+            match (old.objects.get(id), new.objects.get(id)) {
+                (None, Some(_)) => Some(ObjectChange::Added),
+                (Some(_), None) => Some(ObjectChange::Removed),
+                (Some(old), Some(new)) if !same_object(old, new) => Some(ObjectChange::Modified),
+                _ => None,
+            }
+            .map(|change| (*id, change))
+        })
+        .collect()
+}
+
+/// Compare two objects, ignoring where a stream sits in the file.
+fn same_object(old: &Object, new: &Object) -> bool {
+    match (old, new) {
+        (Object::Stream(old), Object::Stream(new)) => {
+            old.dict == new.dict && old.content == new.content
+        }
+        (old, new) => old == new,
+    }
+}
+
+/// Classify every change from `old` to `new`, the revision before and the
+/// revision `revision`.
+pub(crate) fn classify_changes(
+    old: &Document,
+    new: &Document,
+    revision: usize,
+    permission: Option<DocMdpPermission>,
+) -> Vec<Modification> {
+    let mut modifications = Vec::new();
+    let mut push = |object_id, change, kind: ModificationKind| {
+        modifications.push(Modification {
+            revision,
+            object_id,
+            change,
+            kind,
+            allowed: kind.is_allowed(permission),
+        })
+    };
+
+    // A new catalog replaces the document, whatever it points at
+    let old_root = old.trailer.get(b"Root").and_then(Object::as_reference).ok();
+    let new_root = new.trailer.get(b"Root").and_then(Object::as_reference).ok();
+    if old_root != new_root {
+        push(new_root, ObjectChange::Modified, ModificationKind::Object);
+    }
+
+    let changes = object_changes(old, new);
+    let filled = filled_fields(old, new, &changes);
+    let dss = dss_objects(new);
+    let contents = page_contents(new);
+    for (id, change) in changes {
+        let obj = match change {
+            ObjectChange::Removed => &old.objects[&id],
+            _ => &new.objects[&id],
+        };
+        let kind = match change {
+            _ if contents.contains(&id) => Some(ModificationKind::PageContent),
+            // The DSS cannot vouch for a page, an annotation or another structure object
+            _ if dss.contains(&id) && !dict_of(obj).is_some_and(is_structure) => {
+                Some(ModificationKind::Dss)
+            }
+            ObjectChange::Added => classify_added(obj),
+            ObjectChange::Modified => Some(classify_modified(
+                id,
+                &old.objects[&id],
+                obj,
+                old,
+                new,
+                &filled,
+            )),
+            ObjectChange::Removed => Some(classify_removed(obj)),
+        };
+        if let Some(kind) = kind {
+            push(Some(id), change, kind);
+        }
+    }
+    modifications
+}
+
+/// New objects only matter once an existing object refers to them, so plain
+/// resources such as appearance streams and fonts are not reported.
+fn classify_added(obj: &Object) -> Option<ModificationKind> {
+    let dict = dict_of(obj)?;
+    match type_of(dict) {
+        Some(b"Sig") => Some(ModificationKind::Signature),
+        Some(b"DocTimeStamp") => Some(ModificationKind::Timestamp),
+        Some(b"Page" | b"Pages") => Some(ModificationKind::PageContent),
+        _ if is_widget(dict) || is_field(dict) => Some(ModificationKind::FormFill),
+        _ if is_annotation(dict) => Some(ModificationKind::Annotation),
+        _ => None,
+    }
+}
+
+fn classify_removed(obj: &Object) -> ModificationKind {
+    match dict_of(obj) {
+        Some(dict) if is_annotation(dict) && !is_widget(dict) => ModificationKind::Annotation,
+        _ => ModificationKind::Object,
+    }
+}
+
+fn classify_modified(
+    id: ObjectId,
+    old: &Object,
+    new: &Object,
+    old_doc: &Document,
+    new_doc: &Document,
+    filled: &BTreeSet<ObjectId>,
+) -> ModificationKind {
+    let (Some(old), Some(new)) = (dict_of(old), dict_of(new)) else {
+        return ModificationKind::Object;
+    };
+    let changed = changed_keys(old, new);
+    let only = |allowed: &[&[u8]]| changed.iter().all(|key| allowed.contains(key));
+    match type_of(new) {
+        // An existing signature must never change
+        Some(b"Sig" | b"DocTimeStamp") => ModificationKind::Object,
+        Some(b"Catalog") if only(&[b"DSS", b"Extensions"]) => ModificationKind::Dss,
+        Some(b"Catalog") if only(&[b"DSS", b"Extensions", b"AcroForm"]) => {
+            ModificationKind::FormFill
+        }
+        Some(b"Page") if only(&[b"Annots"]) => classify_annots(old, new, old_doc, new_doc),
+        Some(b"Page" | b"Pages") => ModificationKind::PageContent,
+        _ if is_widget(new) || is_field(new) => {
+            if !only(&[b"V", b"AS", b"AP", b"M", b"Kids"]) {
+                return ModificationKind::Object;
+            }
+            // A signature field is signed once, its value is never replaced or removed
+            if changed.contains(&b"V".as_slice()) && field_type(new, new_doc) == Some(b"Sig") {
+                return match old.has(b"V") {
+                    false => ModificationKind::Signature,
+                    true => ModificationKind::Object,
+                };
+            }
+            // A new appearance must show a value filled in by this revision
+            let field_filled = filled.contains(&id)
+                || new
+                    .get(b"Parent")
+                    .and_then(Object::as_reference)
+                    .is_ok_and(|parent| filled.contains(&parent));
+            let appearance_changed = changed.iter().any(|key| matches!(*key, b"AP" | b"AS"));
+            match appearance_changed && !field_filled {
+                true => ModificationKind::Object,
+                false => ModificationKind::FormFill,
+            }
+        }
+        _ if is_annotation(new) => ModificationKind::Annotation,
+        // The AcroForm gains the fields of new signatures
+        _ if new.has(b"Fields")
+            && only(&[b"Fields", b"SigFlags", b"DR", b"DA", b"NeedAppearances"]) =>
+        {
+            ModificationKind::FormFill
+        }
+        _ => ModificationKind::Object,
+    }
+}
+
+/// A page whose `/Annots` changed: new widgets belong to new fields, any other
+/// added or removed annotation is reported as such.
+fn classify_annots(
+    old: &Dictionary,
+    new: &Dictionary,
+    old_doc: &Document,
+    new_doc: &Document,
+) -> ModificationKind {
+    let annots = |dict: &Dictionary, doc: &Document| {
+        dict.get_deref(b"Annots", doc)
+            .and_then(Object::as_array)
+            .map(|annots| {
+                annots
+                    .iter()
+                    .filter_map(|a| a.as_reference().ok())
+                    .collect()
+            })
+            .unwrap_or_else(|_| BTreeSet::new())
+    };
+    let old_annots: BTreeSet<ObjectId> = annots(old, old_doc);
+    let new_annots: BTreeSet<ObjectId> = annots(new, new_doc);
+    let only_widgets_added = old_annots.is_subset(&new_annots)
+        && new_annots
+            .difference(&old_annots)
+            .all(|id| new_doc.get_dictionary(*id).is_ok_and(is_widget));
+    match only_widgets_added {
+        true => ModificationKind::FormFill,
+        false => ModificationKind::Annotation,
+    }
+}
+
+/// The fields that are added or modified with a new `/V`.
+fn filled_fields(
+    old: &Document,
+    new: &Document,
+    changes: &[(ObjectId, ObjectChange)],
+) -> BTreeSet<ObjectId> {
+    changes
+        .iter()
+        .filter(|(_, change)| *change != ObjectChange::Removed)
+        .filter(|(id, _)| {
+            let value = field_value(new, *id);
+            value.is_some() && field_value(old, *id) != value
+        })
+        .map(|(id, _)| *id)
+        .collect()
+}
+
+fn field_value(doc: &Document, id: ObjectId) -> Option<&Object> {
+    doc.objects
+        .get(&id)
+        .and_then(dict_of)
+        .filter(|dict| is_field(dict))
+        .and_then(|dict| dict.get(b"V").ok())
+}
+
+/// The `/DSS` dictionary, its `/Certs`, `/CRLs` and `/OCSPs` arrays, the `/VRI`
+/// dictionary and the streams those arrays name directly.
+fn dss_objects(doc: &Document) -> BTreeSet<ObjectId> {
+    let mut ids = BTreeSet::new();
+    let Ok(catalog) = doc.catalog() else {
+        return ids;
+    };
+    let Ok(dss) = catalog.get_deref(b"DSS", doc).and_then(Object::as_dict) else {
+        return ids;
+    };
+    ids.extend(catalog.get(b"DSS").and_then(Object::as_reference));
+    ids.extend(dss.get(b"VRI").and_then(Object::as_reference));
+    for key in [b"Certs".as_slice(), b"CRLs", b"OCSPs"] {
+        ids.extend(dss.get(key).and_then(Object::as_reference));
+        let Ok(items) = dss.get_deref(key, doc).and_then(Object::as_array) else {
+            continue;
+        };
+        ids.extend(
+            items
+                .iter()
+                .filter_map(|item| item.as_reference().ok())
+                .filter(|id| doc.get_object(*id).and_then(Object::as_stream).is_ok()),
+        );
+    }
+    ids
+}
+
+/// The content streams of every page.
+pub(crate) fn page_contents(doc: &Document) -> BTreeSet<ObjectId> {
+    doc.get_pages()
+        .into_values()
+        .flat_map(|page_id| doc.get_page_contents(page_id))
+        .collect()
+}
+
+pub(crate) fn changed_keys<'a>(old: &'a Dictionary, new: &'a Dictionary) -> Vec<&'a [u8]> {
+    let mut keys = old
+        .iter()
+        .chain(new.iter())
+        .map(|(key, _)| key.as_slice())
+        .collect::<BTreeSet<_>>();
+    keys.retain(|key| old.get(key).ok() != new.get(key).ok());
+    keys.into_iter().collect()
+}
+
+pub(crate) fn dict_of(obj: &Object) -> Option<&Dictionary> {
+    match obj {
+        Object::Dictionary(dict) => Some(dict),
+        Object::Stream(stream) => Some(&stream.dict),
+        _ => None,
+    }
+}
+
+fn type_of(dict: &Dictionary) -> Option<&[u8]> {
+    dict.get(b"Type").and_then(Object::as_name).ok()
+}
+
+fn is_widget(dict: &Dictionary) -> bool {
+    dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Widget")
+}
+
+/// `/Type /Annot` is optional, every annotation has a `/Subtype` and a `/Rect`.
+fn is_annotation(dict: &Dictionary) -> bool {
+    type_of(dict) == Some(b"Annot") || (dict.has(b"Subtype") && dict.has(b"Rect"))
+}
+
+/// The `/FT` of a field, inherited from its parent if it has none.
+fn field_type<'a>(dict: &'a Dictionary, doc: &'a Document) -> Option<&'a [u8]> {
+    dict.get(b"FT")
+        .or_else(|_| {
+            dict.get_deref(b"Parent", doc)
+                .and_then(Object::as_dict)
+                .and_then(|parent| parent.get(b"FT"))
+        })
+        .and_then(Object::as_name)
+        .ok()
+}
+
+/// Pages, annotations, fields, signatures and other typed objects, as opposed
+/// to the plain data streams and arrays of the `/DSS`.
+fn is_structure(dict: &Dictionary) -> bool {
+    dict.has(b"Type") || dict.has(b"Subtype") || is_field(dict)
+}
+
+fn is_field(dict: &Dictionary) -> bool {
+    dict.has(b"FT") || (dict.has(b"T") && dict.has(b"Kids"))
+}
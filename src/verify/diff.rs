@@ -0,0 +1,188 @@
+//! Diff
+//! List what an incremental update changed, object by object, with the
+//! changed keys and the operators added to or removed from content streams.
+use std::collections::{BTreeMap, BTreeSet};
+
+use lopdf::{Document, Object, ObjectId, content::Content, content::Operation};
+
+use super::changes::{
+    ModificationKind, ObjectChange, changed_keys, classify_changes, dict_of, object_changes,
+    page_contents,
+};
+
+/// Content streams longer than this many differing operators are reported as
+/// entirely removed and added rather than aligned.
+const MAX_ALIGNED_OPERATIONS: usize = 2000;
+
+/// The changes from revision `from` to revision `to`.
+#[derive(Debug, Clone)]
+pub struct RevisionDiff {
+    /// 1-based.
+    pub from: usize,
+    /// 1-based.
+    pub to: usize,
+    pub objects: Vec<ObjectDiff>,
+}
+
+/// One added, modified or removed object.
+#[derive(Debug, Clone)]
+pub struct ObjectDiff {
+    pub id: ObjectId,
+    pub change: ObjectChange,
+    /// What the change does to the document, `None` for objects that matter
+    /// only through the objects referring to them, such as fonts.
+    pub kind: Option<ModificationKind>,
+    /// The changed keys of a modified dictionary or stream dictionary.
+    pub keys: Vec<KeyChange>,
+    /// The operator-level changes of a page content stream or form XObject.
+    pub content: Vec<ContentChange>,
+}
+
+/// A dictionary entry that was added, removed or changed.
+#[derive(Debug, Clone)]
+pub struct KeyChange {
+    pub key: String,
+    pub old: Option<Object>,
+    pub new: Option<Object>,
+}
+
+/// An operator added to or removed from a content stream, `index` is its
+/// position in the new or the old stream.
+#[derive(Debug, Clone)]
+pub enum ContentChange {
+    Added { index: usize, operation: Operation },
+    Removed { index: usize, operation: Operation },
+}
+
+impl RevisionDiff {
+    /// Compare `old`, the revision `from`, with `new`, the revision `to`.
+    pub(crate) fn new(old: &Document, new: &Document, from: usize, to: usize) -> Self {
+        let kinds = classify_changes(old, new, to, None)
+            .into_iter()
+            .filter_map(|modification| Some((modification.object_id?, modification.kind)))
+            .collect::<BTreeMap<_, _>>();
+        let contents = page_contents(old)
+            .into_iter()
+            .chain(page_contents(new))
+            .collect::<BTreeSet<_>>();
+        let objects = object_changes(old, new)
+            .into_iter()
+            .map(|(id, change)| {
+                let old = old.objects.get(&id);
+                let new = new.objects.get(&id);
+                let keys = match (old.and_then(dict_of), new.and_then(dict_of)) {
+                    (Some(old), Some(new)) => changed_keys(old, new)
+                        .into_iter()
+                        .map(|key| KeyChange {
+                            key: String::from_utf8_lossy(key).into_owned(),
+                            old: old.get(key).ok().cloned(),
+                            new: new.get(key).ok().cloned(),
+                        })
+                        .collect(),
+                    _ => Vec::new(),
+                };
+                let content = match contents.contains(&id) || is_form(old) || is_form(new) {
+                    true => content_changes(&operations(old), &operations(new)),
+                    false => Vec::new(),
+                };
+                ObjectDiff {
+                    id,
+                    change,
+                    kind: kinds.get(&id).copied(),
+                    keys,
+                    content,
+                }
+            })
+            .collect();
+        RevisionDiff { from, to, objects }
+    }
+
+    pub fn count(&self, change: ObjectChange) -> usize {
+        self.objects
+            .iter()
+            .filter(|object| object.change == change)
+            .count()
+    }
+}
+
+fn is_form(obj: Option<&Object>) -> bool {
+    matches!(obj, Some(Object::Stream(stream))
+        if stream.dict.get(b"Subtype").and_then(Object::as_name).ok() == Some(b"Form"))
+}
+
+/// The operators of a content stream, none if it cannot be decoded.
+fn operations(obj: Option<&Object>) -> Vec<Operation> {
+    let Some(Object::Stream(stream)) = obj else {
+        return Vec::new();
+    };
+    let content = stream
+        .decompressed_content()
+        .unwrap_or_else(|_| stream.content.clone());
+    Content::decode(&content)
+        .map(|content| content.operations)
+        .unwrap_or_default()
+}
+
+/// Align the operators of two streams around their longest common
+/// subsequence, after skipping the common prefix and suffix.
+fn content_changes(old: &[Operation], new: &[Operation]) -> Vec<ContentChange> {
+    let prefix = old
+        .iter()
+        .zip(new)
+        .take_while(|(old, new)| same_operation(old, new))
+        .count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(old, new)| same_operation(old, new))
+        .count();
+    let old_rest = &old[prefix..old.len() - suffix];
+    let new_rest = &new[prefix..new.len() - suffix];
+
+    let removed = |index: usize| ContentChange::Removed {
+        index: prefix + index,
+        operation: old_rest[index].clone(),
+    };
+    let added = |index: usize| ContentChange::Added {
+        index: prefix + index,
+        operation: new_rest[index].clone(),
+    };
+    if old_rest.len().saturating_mul(new_rest.len()) > MAX_ALIGNED_OPERATIONS.pow(2) {
+        return (0..old_rest.len())
+            .map(removed)
+            .chain((0..new_rest.len()).map(added))
+            .collect();
+    }
+
+    // lengths[i][j] is the length of the common subsequence of old_rest[i..] and new_rest[j..]
+    let (n, m) = (old_rest.len(), new_rest.len());
+    let mut lengths = vec![vec![0usize; m + 1]; n + 1];
+    for i in (0..n).rev() {
+        for j in (0..m).rev() {
+            lengths[i][j] = match same_operation(&old_rest[i], &new_rest[j]) {
+                true => lengths[i + 1][j + 1] + 1,
+                false => lengths[i + 1][j].max(lengths[i][j + 1]),
+            };
+        }
+    }
+    let mut changes = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < n || j < m {
+        if i < n && j < m && same_operation(&old_rest[i], &new_rest[j]) {
+            i += 1;
+            j += 1;
+        } else if i < n && (j == m || lengths[i + 1][j] >= lengths[i][j + 1]) {
+            changes.push(removed(i));
+            i += 1;
+        } else {
+            changes.push(added(j));
+            j += 1;
+        }
+    }
+    changes
+}
+
+fn same_operation(old: &Operation, new: &Operation) -> bool {
+    old.operator == new.operator && old.operands == new.operands
+}
@@ -0,0 +1,707 @@
+//! Verify
+//! Check the signatures of a PDF document in-process, without the openssl binary.
+use std::{
+    collections::{HashMap, HashSet},
+    path::Path,
+    time::SystemTime,
+};
+
+pub use self::changes::{Modification, ModificationKind, ObjectChange};
+pub use self::diff::{ContentChange, KeyChange, ObjectDiff, RevisionDiff};
+pub use self::report::{
+    CertificateReport, IntegrityReport, IntegrityStatus, ModificationReport, RevocationReport,
+    RevocationState, SignatureReport, TimestampReport, TrustReport, Verdict, VerificationReport,
+};
+pub use self::revocation::{CertificateRevocation, RevocationSource, RevocationStatus};
+use self::revocation::{RevocationChecker, issuer_path};
+use self::trust::KeyPurpose;
+pub use self::trust::{TrustStatus, TrustStore};
+
+use anyhow::Result;
+use lopdf::{Dictionary, Document, Object, ObjectId};
+use openssl::x509::{X509, X509NameRef};
+
+use crate::{
+    cms::{
+        decode_signed_data,
+        verify::{SignerCheck, check_timestamp_token, verify_signed_data},
+    },
+    manager::{
+        DocMdpPermission, ValidationData,
+        mdp::{doc_mdp_permission, field_mdp_lock},
+    },
+    revocation::RevocationFetcher,
+    utils::{
+        covered_end, load_revision, revision, revision_ends, signed_revision, terminal_fields,
+    },
+};
+
+pub(crate) mod changes;
+pub(crate) mod diff;
+pub(crate) mod report;
+pub(crate) mod revocation;
+pub(crate) mod trust;
+
+/// The integrity of one signature.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SignatureStatus {
+    /// The ByteRange is unchanged since signing and the signature value is valid.
+    Valid,
+    /// The signed bytes no longer match the digest in the signature.
+    DigestMismatch,
+    /// The signature value does not verify with the signer certificate.
+    InvalidSignature,
+    /// The signature could not be checked, such as a malformed CMS.
+    Error(String),
+}
+
+/// The result of verifying one signature dictionary.
+#[derive(Debug, Clone)]
+pub struct SignatureVerification {
+    /// The fully qualified name of the signature field, `None` for a signature
+    /// dictionary no field refers to.
+    pub field_name: Option<String>,
+    /// The 1-based incremental revision the signature covers, the revision its
+    /// ByteRange ends in.
+    pub revision: usize,
+    /// The ByteRange runs to the end of the file, no revision was added after it.
+    pub covers_whole_file: bool,
+    /// The changes of the revisions added after the signed one.
+    pub modifications: Vec<Modification>,
+    /// The `/SubFilter` of the signature dictionary.
+    pub sub_filter: Option<String>,
+    /// The `/ByteRange` of the signature dictionary.
+    pub byte_range: Vec<usize>,
+    /// The signer certificate, or the TSA certificate of a document timestamp.
+    pub signer: Option<X509>,
+    /// The signing-time attribute, the time the signer claims to have signed
+    /// at, or the genTime of a document timestamp.
+    pub signing_time: Option<SystemTime>,
+    pub status: SignatureStatus,
+    /// The certificate path from the signer to a trust anchor, empty unless trusted.
+    pub chain: Vec<X509>,
+    /// `None` if the verifier has no trust store.
+    pub trust: Option<TrustStatus>,
+    /// The revocation status of the signer certificate and its CAs, empty
+    /// unless revocation checking is enabled.
+    pub revocation: Vec<CertificateRevocation>,
+    /// The signatureTimeStampToken of the signature.
+    pub timestamp: Option<TimestampVerification>,
+    /// The time the certificates were validated at: the time of the signature
+    /// timestamp, else of the earliest document timestamp covering the
+    /// signature, `None` for now. The genTime of a document timestamp itself.
+    pub validation_time: Option<SystemTime>,
+}
+
+/// The result of checking an RFC 3161 timestamp token over a signature value.
+#[derive(Debug, Clone)]
+pub struct TimestampVerification {
+    /// The genTime of the token.
+    pub time: Option<SystemTime>,
+    /// The TSA certificate.
+    pub tsa: Option<X509>,
+    /// `DigestMismatch` if the message imprint is not the digest of the signature value.
+    pub status: SignatureStatus,
+    /// The certificate path from the TSA to a trust anchor, empty unless trusted.
+    pub chain: Vec<X509>,
+    /// `None` if the verifier has no trust store.
+    pub trust: Option<TrustStatus>,
+}
+
+impl TimestampVerification {
+    /// The token is intact and, with a trust store, its TSA is trusted.
+    pub fn is_valid(&self) -> bool {
+        self.status == SignatureStatus::Valid
+            && !matches!(self.trust, Some(TrustStatus::Untrusted(_)))
+    }
+
+    pub fn tsa_subject(&self) -> Option<String> {
+        self.tsa
+            .as_ref()
+            .map(|cert| name_to_string(cert.subject_name()))
+    }
+}
+
+impl SignatureVerification {
+    pub fn is_valid(&self) -> bool {
+        self.status == SignatureStatus::Valid
+    }
+
+    pub fn is_trusted(&self) -> bool {
+        self.trust == Some(TrustStatus::Trusted)
+    }
+
+    /// A certificate of the signer's path was revoked before the validation
+    /// time, a revocation after a timestamp does not affect the signature.
+    pub fn is_revoked(&self) -> bool {
+        self.revocation.iter().any(|check| match check.status {
+            RevocationStatus::Revoked {
+                time: Some(time), ..
+            } => self
+                .validation_time
+                .is_none_or(|validation| time <= validation),
+            RevocationStatus::Revoked { time: None, .. } => true,
+            _ => false,
+        })
+    }
+
+    /// The signature is a document timestamp (`ETSI.RFC3161`).
+    pub fn is_document_timestamp(&self) -> bool {
+        self.sub_filter.as_deref() == Some("ETSI.RFC3161")
+    }
+
+    /// The changes made after signing that the document does not permit.
+    pub fn suspicious_modifications(&self) -> impl Iterator<Item = &Modification> {
+        self.modifications.iter().filter(|m| !m.allowed)
+    }
+
+    /// The subject of the signer certificate, such as `CN=Alice, O=Example`.
+    pub fn signer_subject(&self) -> Option<String> {
+        self.signer
+            .as_ref()
+            .map(|cert| name_to_string(cert.subject_name()))
+    }
+}
+
+/// Verifies the signatures of a loaded document.
+pub struct PdfVerifier {
+    data: Vec<u8>,
+    doc: Document,
+    trust_store: Option<TrustStore>,
+    revocation: Option<RevocationSources>,
+}
+
+/// The revocation information used besides the one embedded in the document.
+struct RevocationSources {
+    data: ValidationData,
+    fetcher: Option<Box<dyn RevocationFetcher>>,
+}
+
+impl PdfVerifier {
+    pub fn load(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_bytes(std::fs::read(path)?)
+    }
+
+    pub fn from_bytes(data: Vec<u8>) -> Result<Self> {
+        let doc = load_revision(&data)?;
+        Ok(PdfVerifier {
+            data,
+            doc,
+            trust_store: None,
+            revocation: None,
+        })
+    }
+
+    /// Validate the certificate path of every signer against `trust_store`.
+    pub fn with_trust_store(mut self, trust_store: TrustStore) -> Self {
+        self.trust_store = Some(trust_store);
+        self
+    }
+
+    /// Check the revocation status of every signer path. CRLs and OCSP
+    /// responses come from the `/DSS`, the adbe-revocationInfoArchival
+    /// attribute of each signature and `data`, which works offline. `fetcher`
+    /// is only asked for the certificates none of them cover.
+    pub fn with_revocation_check(
+        mut self,
+        data: ValidationData,
+        fetcher: Option<Box<dyn RevocationFetcher>>,
+    ) -> Self {
+        self.revocation = Some(RevocationSources { data, fetcher });
+        self
+    }
+
+    /// Verify every signature of the document in revision order: the values of
+    /// the signature fields, then signature dictionaries no field refers to.
+    pub fn verify(&self) -> Result<Vec<SignatureVerification>> {
+        let mut results = Vec::new();
+        // The FieldMDP locks of the signatures, in the order of `results`
+        let mut locks = Vec::new();
+        let mut seen = HashSet::new();
+        for field in terminal_fields(&self.doc) {
+            if field.field_type != Some(b"Sig") {
+                continue;
+            }
+            let Ok((id, value)) = field
+                .dict
+                .get(b"V")
+                .and_then(|value| self.doc.dereference(value))
+            else {
+                continue;
+            };
+            let Ok(sig) = value.as_dict() else {
+                continue;
+            };
+            seen.extend(id);
+            results.push(self.verify_signature(Some(field.name), sig));
+            locks.push(field_mdp_lock(sig, &self.doc));
+        }
+        for (id, obj) in &self.doc.objects {
+            match obj.as_dict() {
+                Ok(sig) if !seen.contains(id) && is_signature(sig) => {
+                    results.push(self.verify_signature(None, sig));
+                    locks.push(field_mdp_lock(sig, &self.doc));
+                }
+                _ => {}
+            }
+        }
+
+        // Document timestamps are validated at their genTime first, they then
+        // date the signatures of the revisions they cover
+        for (result, check) in &mut results {
+            if let (true, Some(check)) = (result.is_document_timestamp(), check) {
+                self.validate_certificates(result, check, check.signing_time);
+            }
+        }
+        let doc_timestamps = results
+            .iter()
+            .map(|(result, _)| result)
+            .filter(|result| {
+                result.is_document_timestamp()
+                    && result.is_valid()
+                    && !matches!(result.trust, Some(TrustStatus::Untrusted(_)))
+            })
+            .filter_map(|result| Some((result.revision, result.signing_time?)))
+            .collect::<Vec<_>>();
+        for (result, check) in &mut results {
+            let Some(check) = check.as_ref().filter(|_| !result.is_document_timestamp()) else {
+                continue;
+            };
+            let time = result
+                .timestamp
+                .as_ref()
+                .filter(|timestamp| timestamp.is_valid())
+                .and_then(|timestamp| timestamp.time)
+                .or_else(|| {
+                    doc_timestamps
+                        .iter()
+                        .filter(|(revision, _)| *revision > result.revision)
+                        .map(|(_, time)| *time)
+                        .min()
+                });
+            self.validate_certificates(result, check, time);
+        }
+
+        let modifications = self.modifications();
+        let fields = self.field_names_by_object();
+        let mut results = results
+            .into_iter()
+            .zip(locks)
+            .map(|((mut result, _), lock)| {
+                result.modifications = modifications
+                    .iter()
+                    .filter(|m| m.revision > result.revision)
+                    .cloned()
+                    .map(|mut m| {
+                        // A field locked by the signature may no longer change (FieldMDP)
+                        let name = m.object_id.and_then(|id| fields.get(&id));
+                        if let (Some(lock), Some(name)) = (&lock, name)
+                            && lock.locks(name)
+                        {
+                            m.allowed = false;
+                        }
+                        m
+                    })
+                    .collect();
+                result
+            })
+            .collect::<Vec<_>>();
+        results.sort_by_key(|result| result.revision);
+        Ok(results)
+    }
+
+    /// The number of incremental revisions, the original document included.
+    pub fn revision_count(&self) -> usize {
+        revision_ends(&self.data).len()
+    }
+
+    /// The bytes of the 1-based revision `n`, a standalone PDF.
+    pub fn revision(&self, n: usize) -> Result<&[u8]> {
+        revision(&self.data, n)
+    }
+
+    /// The bytes covered by the signature of the field `field_name`, the
+    /// revision the signer saw, a standalone PDF.
+    pub fn signed_revision(&self, field_name: &str) -> Result<&[u8]> {
+        signed_revision(&self.data, &self.doc, field_name)
+    }
+
+    /// Compare the 1-based revisions `from` and `to` object by object.
+    pub fn diff(&self, from: usize, to: usize) -> Result<RevisionDiff> {
+        if from == 0 || from >= to {
+            return Err(anyhow::anyhow!(
+                "Cannot compare revision {} with revision {}, the document has {} revisions",
+                from,
+                to,
+                self.revision_count()
+            ));
+        }
+        let old = load_revision(revision(&self.data, from)?)?;
+        let new = load_revision(revision(&self.data, to)?)?;
+        Ok(RevisionDiff::new(&old, &new, from, to))
+    }
+
+    /// Verify every signature and summarize the results, see [`VerificationReport`].
+    pub fn report(&self) -> Result<VerificationReport> {
+        Ok(VerificationReport::new(
+            &self.verify()?,
+            self.doc_mdp_permission(),
+        ))
+    }
+
+    /// Check the integrity of a signature and of its timestamp, the
+    /// certificates are validated once the timestamps are known.
+    fn verify_signature(
+        &self,
+        field_name: Option<String>,
+        sig: &Dictionary,
+    ) -> (SignatureVerification, Option<SignerCheck>) {
+        let sub_filter = sig
+            .get(b"SubFilter")
+            .and_then(Object::as_name)
+            .ok()
+            .map(|name| String::from_utf8_lossy(name).into_owned());
+        let byte_range = sig
+            .get(b"ByteRange")
+            .and_then(Object::as_array)
+            .map(|range| {
+                range
+                    .iter()
+                    .filter_map(|n| n.as_i64().ok())
+                    .filter_map(|n| usize::try_from(n).ok())
+                    .collect::<Vec<_>>()
+            })
+            .unwrap_or_default();
+        let revision = self.revision_of(&byte_range);
+        // The end of line after the last `%%EOF` may be left out of the ByteRange
+        let covers_whole_file = covered_end(&byte_range)
+            .and_then(|end| self.data.get(end..))
+            .is_some_and(|rest| rest.iter().all(|b| matches!(b, b'\r' | b'\n')));
+        let (check, status) = match self.check_integrity(sig, sub_filter.as_deref(), &byte_range) {
+            Ok((check, status)) => (Some(check), status),
+            Err(err) => (None, SignatureStatus::Error(err.to_string())),
+        };
+        let timestamp = check.as_ref().and_then(|check| {
+            let token = check.timestamp_token.as_deref()?;
+            Some(self.verify_timestamp(token, &check.signature))
+        });
+        let result = SignatureVerification {
+            field_name,
+            revision,
+            covers_whole_file,
+            modifications: Vec::new(),
+            sub_filter,
+            byte_range,
+            signing_time: check.as_ref().and_then(|check| check.signing_time),
+            signer: check.as_ref().map(|check| check.signer.clone()),
+            status,
+            chain: Vec::new(),
+            trust: None,
+            revocation: Vec::new(),
+            timestamp,
+            validation_time: None,
+        };
+        (result, check)
+    }
+
+    /// Check a signatureTimeStampToken over `signature` and its TSA at the genTime.
+    fn verify_timestamp(&self, token: &[u8], signature: &[u8]) -> TimestampVerification {
+        let check =
+            decode_signed_data(token).and_then(|token| check_timestamp_token(&token, signature));
+        let check = match check {
+            Ok(check) => check,
+            Err(err) => {
+                return TimestampVerification {
+                    time: None,
+                    tsa: None,
+                    status: SignatureStatus::Error(err.to_string()),
+                    chain: Vec::new(),
+                    trust: None,
+                };
+            }
+        };
+        let (chain, trust) =
+            self.validate_path(&check, check.signing_time, KeyPurpose::TimeStamping);
+        TimestampVerification {
+            time: check.signing_time,
+            status: check.status(),
+            tsa: Some(check.signer),
+            chain,
+            trust,
+        }
+    }
+
+    /// Validate the signer path at `time`, now if `None`, and check its revocation.
+    fn validate_certificates(
+        &self,
+        result: &mut SignatureVerification,
+        check: &SignerCheck,
+        time: Option<SystemTime>,
+    ) {
+        let purpose = match result.is_document_timestamp() {
+            true => KeyPurpose::TimeStamping,
+            false => KeyPurpose::DocumentSigning,
+        };
+        let (chain, trust) = self.validate_path(check, time, purpose);
+        if let Some(sources) = &self.revocation {
+            let time = time.unwrap_or_else(SystemTime::now);
+            result.revocation = self.check_revocation(sources, check, &chain, time);
+        }
+        result.chain = chain;
+        result.trust = trust;
+        result.validation_time = time;
+    }
+
+    fn validate_path(
+        &self,
+        check: &SignerCheck,
+        time: Option<SystemTime>,
+        purpose: KeyPurpose,
+    ) -> (Vec<X509>, Option<TrustStatus>) {
+        let Some(trust_store) = &self.trust_store else {
+            return (Vec::new(), None);
+        };
+        match trust_store.validate(&check.signer, &check.certificates, time, purpose) {
+            Ok(chain) => (chain, Some(TrustStatus::Trusted)),
+            Err(err) => (Vec::new(), Some(TrustStatus::Untrusted(err.to_string()))),
+        }
+    }
+
+    fn check_integrity(
+        &self,
+        sig: &Dictionary,
+        sub_filter: Option<&str>,
+        byte_range: &[usize],
+    ) -> Result<(SignerCheck, SignatureStatus)> {
+        let contents = sig.get(b"Contents")?.as_str()?;
+        let signed_bytes = self.signed_bytes(byte_range, contents)?;
+        let signed_data = decode_signed_data(contents)?;
+
+        let check = match sub_filter {
+            // The token signs its TSTInfo, whose message imprint covers the ByteRange
+            Some("ETSI.RFC3161") => check_timestamp_token(&signed_data, &signed_bytes)?,
+            Some("adbe.pkcs7.detached") | Some("ETSI.CAdES.detached") | None => {
+                verify_signed_data(&signed_data, Some(&signed_bytes))?
+            }
+            Some(sub_filter) => {
+                return Err(anyhow::anyhow!("Unsupported SubFilter {}", sub_filter));
+            }
+        };
+        let status = check.status();
+        Ok((check, status))
+    }
+
+    /// Check the trusted `chain` of the signer at `time`, or the path built from
+    /// the certificates known to the document when there is none.
+    fn check_revocation(
+        &self,
+        sources: &RevocationSources,
+        check: &SignerCheck,
+        chain: &[X509],
+        time: SystemTime,
+    ) -> Vec<CertificateRevocation> {
+        let mut data = sources.data.clone();
+        self.add_dss_data(&mut data);
+        // Material that fails to parse is skipped, it cannot answer for any certificate
+        for cert in &check.certificates {
+            data.add_cert(cert.clone()).ok();
+        }
+        for crl in &check.crls {
+            data.add_crl(crl.clone()).ok();
+        }
+        for ocsp in &check.ocsps {
+            data.add_ocsp(ocsp.clone()).ok();
+        }
+        let checker = RevocationChecker {
+            data: &data,
+            fetcher: sources.fetcher.as_deref(),
+            time,
+        };
+        if !chain.is_empty() {
+            return checker.check_path(chain);
+        }
+        issuer_path(&check.signer, &data.certs)
+            .into_iter()
+            .map(|(cert, issuer)| checker.check(&cert, issuer.as_deref()))
+            .collect()
+    }
+
+    /// Add the certificates, CRLs and OCSP responses of the `/DSS`.
+    fn add_dss_data(&self, data: &mut ValidationData) {
+        let Ok(dss) = self
+            .doc
+            .catalog()
+            .and_then(|catalog| catalog.get_deref(b"DSS", &self.doc))
+            .and_then(Object::as_dict)
+        else {
+            return;
+        };
+        let streams = |key: &[u8]| {
+            dss.get_deref(key, &self.doc)
+                .and_then(Object::as_array)
+                .map(|items| {
+                    items
+                        .iter()
+                        .filter_map(|item| self.doc.dereference(item).ok())
+                        .filter_map(|(_, item)| item.as_stream().ok())
+                        .map(|stream| {
+                            stream
+                                .decompressed_content()
+                                .unwrap_or_else(|_| stream.content.clone())
+                        })
+                        .collect::<Vec<_>>()
+                })
+                .unwrap_or_default()
+        };
+        for cert in streams(b"Certs") {
+            if let Ok(cert) = X509::from_der(&cert) {
+                data.add_cert(cert).ok();
+            }
+        }
+        for crl in streams(b"CRLs") {
+            data.add_crl(crl).ok();
+        }
+        for ocsp in streams(b"OCSPs") {
+            data.add_ocsp(ocsp).ok();
+        }
+    }
+
+    fn revision_of(&self, byte_range: &[usize]) -> usize {
+        let ends = revision_ends(&self.data);
+        let covered = covered_end(byte_range).unwrap_or(self.data.len());
+        ends.iter()
+            .position(|end| *end >= covered)
+            .unwrap_or(ends.len().saturating_sub(1))
+            + 1
+    }
+
+    /// The changes of every revision after the first. Bytes after the last
+    /// `%%EOF` count as one more revision, and a revision that cannot be read
+    /// as a suspicious change.
+    fn modifications(&self) -> Vec<Modification> {
+        let mut ends = revision_ends(&self.data);
+        if ends.last() != Some(&self.data.len()) {
+            ends.push(self.data.len());
+        }
+        let permission = self.doc_mdp_permission();
+        let earlier = ends[..ends.len() - 1]
+            .iter()
+            .map(|end| load_revision(&self.data[..*end]).ok())
+            .collect::<Vec<_>>();
+        let revisions = earlier
+            .iter()
+            .map(Option::as_ref)
+            .chain([Some(&self.doc)])
+            .collect::<Vec<_>>();
+        let mut modifications = Vec::new();
+        for (index, pair) in revisions.windows(2).enumerate() {
+            let revision = index + 2;
+            match pair {
+                [Some(old), Some(new)] => {
+                    modifications.extend(changes::classify_changes(old, new, revision, permission));
+                }
+                _ => modifications.push(Modification {
+                    revision,
+                    object_id: None,
+                    change: ObjectChange::Modified,
+                    kind: ModificationKind::Object,
+                    allowed: false,
+                }),
+            }
+        }
+        modifications
+    }
+
+    /// The fully qualified name of the field each field and widget object belongs to.
+    fn field_names_by_object(&self) -> HashMap<ObjectId, String> {
+        let mut names = HashMap::new();
+        for field in terminal_fields(&self.doc) {
+            for id in field.id.into_iter().chain(field.widget_ids(&self.doc)) {
+                names.insert(id, field.name.clone());
+            }
+        }
+        names
+    }
+
+    /// The `/P` of the certification signature, `None` if the document is not certified.
+    pub fn doc_mdp_permission(&self) -> Option<DocMdpPermission> {
+        let sig = self
+            .doc
+            .catalog()
+            .and_then(|catalog| catalog.get_deref(b"Perms", &self.doc))
+            .and_then(Object::as_dict)
+            .and_then(|perms| perms.get_deref(b"DocMDP", &self.doc))
+            .and_then(Object::as_dict)
+            .ok()?;
+        doc_mdp_permission(sig, &self.doc)
+    }
+
+    /// The bytes covered by a ByteRange, which must lie within the file, start
+    /// at its beginning and leave out exactly the `/Contents` hex string of the
+    /// signature.
+    fn signed_bytes(&self, byte_range: &[usize], contents: &[u8]) -> Result<Vec<u8>> {
+        let [start1, len1, start2, len2] = byte_range else {
+            return Err(anyhow::anyhow!("Invalid ByteRange {:?}", byte_range));
+        };
+        if *start1 != 0 {
+            return Err(anyhow::anyhow!(
+                "ByteRange {:?} does not start at the beginning of the file",
+                byte_range
+            ));
+        }
+        let first = self.data.get(..*len1);
+        let second = start2
+            .checked_add(*len2)
+            .and_then(|end| self.data.get(*start2..end));
+        let (Some(first), Some(second)) = (first, second) else {
+            return Err(anyhow::anyhow!(
+                "ByteRange {:?} is outside the file",
+                byte_range
+            ));
+        };
+        let gap = self.data.get(*len1..*start2).unwrap_or_default();
+        if !is_hex_string_of(gap, contents) {
+            return Err(anyhow::anyhow!(
+                "ByteRange {:?} does not leave out exactly the /Contents of the signature",
+                byte_range
+            ));
+        }
+        Ok([first, second].concat())
+    }
+}
+
+/// Whether `gap` is the hex string `<...>` that encodes `contents`.
+fn is_hex_string_of(gap: &[u8], contents: &[u8]) -> bool {
+    let Some(hex) = gap
+        .strip_prefix(b"<")
+        .and_then(|gap| gap.strip_suffix(b">"))
+    else {
+        return false;
+    };
+    hex.len() == 2 * contents.len() && hex::decode(hex).is_ok_and(|decoded| decoded == contents)
+}
+
+/// A signature or document timestamp dictionary, as opposed to a field or a
+/// signature reference.
+pub(crate) fn is_signature(dict: &Dictionary) -> bool {
+    matches!(
+        dict.get(b"Type").and_then(Object::as_name),
+        Ok(b"Sig" | b"DocTimeStamp")
+    ) && dict.has(b"Contents")
+        && dict.has(b"ByteRange")
+}
+
+/// Format a distinguished name as `CN=..., O=...`.
+pub(crate) fn name_to_string(name: &X509NameRef) -> String {
+    name.entries()
+        .map(|entry| {
+            let key = entry.object().nid().short_name().unwrap_or("?");
+            format!(
+                "{}={}",
+                key,
+                String::from_utf8_lossy(entry.data().as_slice())
+            )
+        })
+        .collect::<Vec<_>>()
+        .join(", ")
+}
@@ -0,0 +1,310 @@
+//! Report
+//! A plain, serializable summary of the verification results, for JSON output
+//! and for gating on the outcome.
+use std::time::SystemTime;
+
+use chrono::{DateTime, Utc};
+use openssl::{
+    hash::{MessageDigest, hash},
+    x509::X509Ref,
+};
+use serde::Serialize;
+
+use super::{
+    Modification, ModificationKind, ObjectChange, RevocationSource, RevocationStatus,
+    SignatureStatus, SignatureVerification, TimestampVerification, TrustStatus, name_to_string,
+    revocation::parse_time,
+};
+use crate::manager::DocMdpPermission;
+
+/// The overall outcome of a document, the worst finding of its signatures.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verdict {
+    /// Every signature is intact and trusted when a trust store was given, and
+    /// only permitted changes were made after signing.
+    Valid,
+    /// The signatures are intact but a later revision made a change the
+    /// document does not permit.
+    Modified,
+    /// A signer or TSA certificate does not chain to a trust anchor.
+    Untrusted,
+    /// A signature or timestamp does not verify, or a signer certificate was
+    /// revoked before the validation time.
+    Invalid,
+    /// The document has no signature.
+    Unsigned,
+}
+
+/// The verification of a document.
+#[derive(Debug, Clone, Serialize)]
+pub struct VerificationReport {
+    pub verdict: Verdict,
+    /// The `/P` of the certification signature, `None` if the document is not certified.
+    pub doc_mdp_permission: Option<i64>,
+    pub signatures: Vec<SignatureReport>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SignatureReport {
+    pub field_name: Option<String>,
+    pub revision: usize,
+    pub covers_whole_file: bool,
+    pub sub_filter: Option<String>,
+    pub byte_range: Vec<usize>,
+    pub integrity: IntegrityReport,
+    pub signer: Option<CertificateReport>,
+    /// RFC 3339.
+    pub signing_time: Option<String>,
+    /// RFC 3339, `None` if the certificates were validated at the current time.
+    pub validation_time: Option<String>,
+    /// `None` if no trust store was given.
+    pub trust: Option<TrustReport>,
+    /// The certificate path to the trust anchor, empty unless trusted.
+    pub chain: Vec<CertificateReport>,
+    /// Empty unless revocation checking was enabled.
+    pub revocation: Vec<RevocationReport>,
+    pub timestamp: Option<TimestampReport>,
+    pub modifications: Vec<ModificationReport>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct IntegrityReport {
+    pub status: IntegrityStatus,
+    /// Why the signature could not be checked.
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum IntegrityStatus {
+    Valid,
+    DigestMismatch,
+    InvalidSignature,
+    Error,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CertificateReport {
+    pub subject: String,
+    pub issuer: String,
+    /// Upper case hex.
+    pub serial_number: String,
+    /// RFC 3339.
+    pub not_before: Option<String>,
+    /// RFC 3339.
+    pub not_after: Option<String>,
+    /// The SHA-256 fingerprint of the DER certificate, upper case hex.
+    pub sha256: String,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TrustReport {
+    pub trusted: bool,
+    /// Why the certificate is not trusted.
+    pub reason: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct RevocationReport {
+    pub subject: String,
+    pub status: RevocationState,
+    pub source: Option<RevocationSource>,
+    /// The CRLReason name, such as `keyCompromise`.
+    pub reason: Option<String>,
+    /// RFC 3339.
+    pub revocation_time: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RevocationState {
+    Good,
+    Revoked,
+    Unknown,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct TimestampReport {
+    /// RFC 3339.
+    pub time: Option<String>,
+    pub tsa: Option<CertificateReport>,
+    pub integrity: IntegrityReport,
+    pub trust: Option<TrustReport>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ModificationReport {
+    pub revision: usize,
+    /// The object number and generation, `None` for a whole unreadable revision.
+    pub object: Option<(u32, u16)>,
+    pub change: ObjectChange,
+    pub kind: ModificationKind,
+    pub allowed: bool,
+}
+
+impl VerificationReport {
+    /// Summarize the results of [`PdfVerifier::verify`](super::PdfVerifier::verify).
+    pub fn new(results: &[SignatureVerification], doc_mdp: Option<DocMdpPermission>) -> Self {
+        VerificationReport {
+            verdict: verdict(results),
+            doc_mdp_permission: doc_mdp.map(|permission| permission as i64),
+            signatures: results.iter().map(SignatureReport::new).collect(),
+        }
+    }
+}
+
+fn verdict(results: &[SignatureVerification]) -> Verdict {
+    if results.is_empty() {
+        return Verdict::Unsigned;
+    }
+    let timestamps = || {
+        results
+            .iter()
+            .filter_map(|result| result.timestamp.as_ref())
+    };
+    let untrusted = |trust: &Option<TrustStatus>| matches!(trust, Some(TrustStatus::Untrusted(_)));
+    if results
+        .iter()
+        .any(|result| !result.is_valid() || result.is_revoked())
+        || timestamps().any(|timestamp| timestamp.status != SignatureStatus::Valid)
+    {
+        Verdict::Invalid
+    } else if results.iter().any(|result| untrusted(&result.trust))
+        || timestamps().any(|timestamp| untrusted(&timestamp.trust))
+    {
+        Verdict::Untrusted
+    } else if results
+        .iter()
+        .any(|result| result.suspicious_modifications().next().is_some())
+    {
+        Verdict::Modified
+    } else {
+        Verdict::Valid
+    }
+}
+
+impl SignatureReport {
+    fn new(result: &SignatureVerification) -> Self {
+        SignatureReport {
+            field_name: result.field_name.clone(),
+            revision: result.revision,
+            covers_whole_file: result.covers_whole_file,
+            sub_filter: result.sub_filter.clone(),
+            byte_range: result.byte_range.clone(),
+            integrity: IntegrityReport::new(&result.status),
+            signer: result.signer.as_deref().map(CertificateReport::new),
+            signing_time: result.signing_time.map(rfc3339),
+            validation_time: result.validation_time.map(rfc3339),
+            trust: result.trust.as_ref().map(TrustReport::new),
+            chain: result
+                .chain
+                .iter()
+                .map(|cert| CertificateReport::new(cert))
+                .collect(),
+            revocation: result
+                .revocation
+                .iter()
+                .map(|check| {
+                    let (status, reason, time) = match &check.status {
+                        RevocationStatus::Good => (RevocationState::Good, None, None),
+                        RevocationStatus::Revoked { reason, time } => {
+                            (RevocationState::Revoked, reason.clone(), *time)
+                        }
+                        RevocationStatus::Unknown => (RevocationState::Unknown, None, None),
+                    };
+                    RevocationReport {
+                        subject: check.subject(),
+                        status,
+                        source: check.source,
+                        reason,
+                        revocation_time: time.map(rfc3339),
+                    }
+                })
+                .collect(),
+            timestamp: result.timestamp.as_ref().map(TimestampReport::new),
+            modifications: result
+                .modifications
+                .iter()
+                .map(ModificationReport::new)
+                .collect(),
+        }
+    }
+}
+
+impl IntegrityReport {
+    fn new(status: &SignatureStatus) -> Self {
+        let (status, error) = match status {
+            SignatureStatus::Valid => (IntegrityStatus::Valid, None),
+            SignatureStatus::DigestMismatch => (IntegrityStatus::DigestMismatch, None),
+            SignatureStatus::InvalidSignature => (IntegrityStatus::InvalidSignature, None),
+            SignatureStatus::Error(err) => (IntegrityStatus::Error, Some(err.clone())),
+        };
+        IntegrityReport { status, error }
+    }
+}
+
+impl CertificateReport {
+    fn new(cert: &X509Ref) -> Self {
+        let serial_number = cert
+            .serial_number()
+            .to_bn()
+            .and_then(|serial| serial.to_hex_str().map(|hex| hex.to_string()))
+            .unwrap_or_default();
+        let sha256 = cert
+            .to_der()
+            .and_then(|der| hash(MessageDigest::sha256(), &der))
+            .map(hex::encode_upper)
+            .unwrap_or_default();
+        CertificateReport {
+            subject: name_to_string(cert.subject_name()),
+            issuer: name_to_string(cert.issuer_name()),
+            serial_number,
+            not_before: parse_time(&cert.not_before().to_string()).map(rfc3339),
+            not_after: parse_time(&cert.not_after().to_string()).map(rfc3339),
+            sha256,
+        }
+    }
+}
+
+impl TrustReport {
+    fn new(trust: &TrustStatus) -> Self {
+        match trust {
+            TrustStatus::Trusted => TrustReport {
+                trusted: true,
+                reason: None,
+            },
+            TrustStatus::Untrusted(reason) => TrustReport {
+                trusted: false,
+                reason: Some(reason.clone()),
+            },
+        }
+    }
+}
+
+impl TimestampReport {
+    fn new(timestamp: &TimestampVerification) -> Self {
+        TimestampReport {
+            time: timestamp.time.map(rfc3339),
+            tsa: timestamp.tsa.as_deref().map(CertificateReport::new),
+            integrity: IntegrityReport::new(&timestamp.status),
+            trust: timestamp.trust.as_ref().map(TrustReport::new),
+        }
+    }
+}
+
+impl ModificationReport {
+    fn new(modification: &Modification) -> Self {
+        ModificationReport {
+            revision: modification.revision,
+            object: modification.object_id,
+            change: modification.change,
+            kind: modification.kind,
+            allowed: modification.allowed,
+        }
+    }
+}
+
+fn rfc3339(time: SystemTime) -> String {
+    DateTime::<Utc>::from(time).to_rfc3339()
+}
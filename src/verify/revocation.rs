@@ -0,0 +1,317 @@
+//! Revocation
+//! Decide whether a certificate was revoked, from CRLs and OCSP responses
+//! gathered offline, or fetched when a fetcher is configured.
+use std::time::{Duration, SystemTime};
+
+use chrono::{NaiveDateTime, TimeZone, Utc};
+use der::Decode;
+use openssl::{
+    hash::MessageDigest,
+    ocsp::{OcspCertId, OcspCertStatus, OcspFlag, OcspResponse, OcspResponseStatus},
+    stack::Stack,
+    x509::{
+        CrlStatus, ReasonCode, X509, X509Crl, X509Ref, X509VerifyResult,
+        store::X509StoreBuilder,
+        verify::{X509VerifyFlags, X509VerifyParam},
+    },
+};
+use serde::Serialize;
+use x509_cert::Certificate;
+
+use super::name_to_string;
+use crate::{
+    cms::ocsp::revocation_reason, config::REVOCATION_FRESHNESS_SECS, manager::ValidationData,
+    revocation::RevocationFetcher,
+};
+
+/// The revocation status of one certificate.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RevocationStatus {
+    /// A CRL or OCSP response of the issuer covers the certificate and does not revoke it.
+    Good,
+    /// The certificate was revoked, `reason` is the CRLReason name such as `keyCompromise`.
+    Revoked {
+        reason: Option<String>,
+        time: Option<SystemTime>,
+    },
+    /// No CRL or OCSP response of the issuer current at the validation time
+    /// covers the certificate.
+    Unknown,
+}
+
+/// Where a revocation status came from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RevocationSource {
+    Ocsp,
+    Crl,
+}
+
+/// The revocation status of one certificate of a signer's path.
+#[derive(Debug, Clone)]
+pub struct CertificateRevocation {
+    pub cert: X509,
+    pub status: RevocationStatus,
+    /// `None` if the status is unknown.
+    pub source: Option<RevocationSource>,
+}
+
+impl CertificateRevocation {
+    /// The subject of the certificate, such as `CN=Alice, O=Example`.
+    pub fn subject(&self) -> String {
+        name_to_string(self.cert.subject_name())
+    }
+}
+
+/// Check certificates against the CRLs and OCSP responses of `data`, and of
+/// `fetcher` for the certificates `data` has no answer for. Only CRLs and
+/// responses current at the validation `time` answer.
+pub(crate) struct RevocationChecker<'a> {
+    pub(crate) data: &'a ValidationData,
+    pub(crate) fetcher: Option<&'a dyn RevocationFetcher>,
+    pub(crate) time: SystemTime,
+}
+
+impl RevocationChecker<'_> {
+    /// Check every certificate of `path` but the last, a trust anchor is not
+    /// checked. The issuer of each certificate is the next one in `path`.
+    pub(crate) fn check_path(&self, path: &[X509]) -> Vec<CertificateRevocation> {
+        path.windows(2)
+            .map(|pair| self.check(&pair[0], Some(&pair[1])))
+            .collect()
+    }
+
+    /// Check a certificate, its status is unknown without the issuer.
+    pub(crate) fn check(&self, cert: &X509Ref, issuer: Option<&X509Ref>) -> CertificateRevocation {
+        let found = issuer.and_then(|issuer| {
+            self.check_offline(cert, issuer)
+                .or_else(|| self.check_online(cert, issuer))
+        });
+        let (status, source) = match found {
+            Some((status, source)) => (status, Some(source)),
+            None => (RevocationStatus::Unknown, None),
+        };
+        CertificateRevocation {
+            cert: cert.to_owned(),
+            status,
+            source,
+        }
+    }
+
+    /// An OCSP response is preferred over CRLs, as when embedding them.
+    fn check_offline(
+        &self,
+        cert: &X509Ref,
+        issuer: &X509Ref,
+    ) -> Option<(RevocationStatus, RevocationSource)> {
+        let ocsp = self
+            .data
+            .ocsps
+            .iter()
+            .find_map(|response| self.check_ocsp(response, cert, issuer));
+        ocsp.map(|status| (status, RevocationSource::Ocsp))
+            .or_else(|| {
+                self.data
+                    .crls
+                    .iter()
+                    .find_map(|crl| check_crl(crl, cert, issuer, self.time))
+                    .map(|status| (status, RevocationSource::Crl))
+            })
+    }
+
+    fn check_online(
+        &self,
+        cert: &X509Ref,
+        issuer: &X509Ref,
+    ) -> Option<(RevocationStatus, RevocationSource)> {
+        let fetcher = self.fetcher?;
+        if let Ok(Some(response)) = fetcher.fetch_ocsp(cert, issuer)
+            && let Some(status) = self.check_ocsp(&response, cert, issuer)
+        {
+            return Some((status, RevocationSource::Ocsp));
+        }
+        fetcher
+            .fetch_crls(cert)
+            .ok()?
+            .iter()
+            .find_map(|crl| check_crl(crl, cert, issuer, self.time))
+            .map(|status| (status, RevocationSource::Crl))
+    }
+
+    /// The status of `cert` in a successful OCSP response signed by the issuer
+    /// or a responder it delegated to, if the response is current at the
+    /// validation time. The responder certificate is checked at the time of the
+    /// response, so archived responses stay usable.
+    fn check_ocsp(
+        &self,
+        response: &[u8],
+        cert: &X509Ref,
+        issuer: &X509Ref,
+    ) -> Option<RevocationStatus> {
+        let ocsp = OcspResponse::from_der(response).ok()?;
+        if ocsp.status() != OcspResponseStatus::SUCCESSFUL {
+            return None;
+        }
+        let basic = ocsp.basic().ok()?;
+        let (cert_status, this_update, next_update, revocation_time) =
+            [MessageDigest::sha1(), MessageDigest::sha256()]
+                .into_iter()
+                .filter_map(|md| OcspCertId::from_cert(md, cert, issuer).ok())
+                .find_map(|id| {
+                    let status = basic.find_status(&id)?;
+                    let revocation_time = status
+                        .revocation_time
+                        .and_then(|time| parse_time(&time.to_string()));
+                    Some((
+                        status.status,
+                        parse_time(&status.this_update.to_string()),
+                        status
+                            .next_update()
+                            .and_then(|time| parse_time(&time.to_string())),
+                        revocation_time,
+                    ))
+                })?;
+        if !is_current(this_update, next_update, self.time) {
+            return None;
+        }
+
+        let mut builder = X509StoreBuilder::new().ok()?;
+        builder.add_cert(issuer.to_owned()).ok()?;
+        let mut param = X509VerifyParam::new().ok()?;
+        param.set_flags(X509VerifyFlags::PARTIAL_CHAIN).ok()?;
+        if let Some(time) = this_update {
+            let secs = time.duration_since(SystemTime::UNIX_EPOCH).ok()?.as_secs();
+            param.set_time(secs.try_into().ok()?);
+        }
+        builder.set_param(&param).ok()?;
+        let store = builder.build();
+        let mut certs = Stack::new().ok()?;
+        for known in &self.data.certs {
+            certs.push(known.clone()).ok()?;
+        }
+        basic.verify(&certs, &store, OcspFlag::empty()).ok()?;
+
+        match cert_status {
+            OcspCertStatus::GOOD => Some(RevocationStatus::Good),
+            OcspCertStatus::REVOKED => {
+                // The reason openssl reports is not the CRLReason, read it from the response
+                let serial = Certificate::from_der(&cert.to_der().ok()?)
+                    .ok()?
+                    .tbs_certificate
+                    .serial_number;
+                let reason = revocation_reason(response, &serial).ok().flatten();
+                Some(RevocationStatus::Revoked {
+                    reason: reason.map(reason_name),
+                    time: revocation_time,
+                })
+            }
+            _ => None,
+        }
+    }
+}
+
+/// The status of `cert` in a CRL signed by its issuer, if the CRL is current at `time`.
+fn check_crl(
+    crl: &[u8],
+    cert: &X509Ref,
+    issuer: &X509Ref,
+    time: SystemTime,
+) -> Option<RevocationStatus> {
+    let crl = X509Crl::from_der(crl).ok()?;
+    let key = issuer.public_key().ok()?;
+    if crl.issuer_name().to_der().ok()? != issuer.subject_name().to_der().ok()?
+        || !crl.verify(&key).ok()?
+    {
+        return None;
+    }
+    let this_update = parse_time(&crl.last_update().to_string());
+    let next_update = crl
+        .next_update()
+        .and_then(|time| parse_time(&time.to_string()));
+    if !is_current(this_update, next_update, time) {
+        return None;
+    }
+    match crl.get_by_cert(&cert.to_owned()) {
+        CrlStatus::NotRevoked | CrlStatus::RemoveFromCrl(_) => Some(RevocationStatus::Good),
+        CrlStatus::Revoked(entry) => {
+            let reason = entry
+                .extension::<ReasonCode>()
+                .ok()
+                .flatten()
+                .and_then(|(_, reason)| reason.get_i64().ok());
+            Some(RevocationStatus::Revoked {
+                reason: reason.map(reason_name),
+                time: parse_time(&entry.revocation_date().to_string()),
+            })
+        }
+    }
+}
+
+/// Whether a CRL or an OCSP response issued at `this_update` answers at `time`.
+/// Issued at or after `time`, it tells the status at that time, as the
+/// validation data added after a timestamp (PAdES B-LT) does. Issued before,
+/// `time` must fall before its `next_update`. Without a nextUpdate newer
+/// information may be available at any time (RFC 6960 4.2.2.1), the answer
+/// stays current for [`REVOCATION_FRESHNESS_SECS`] only.
+fn is_current(
+    this_update: Option<SystemTime>,
+    next_update: Option<SystemTime>,
+    time: SystemTime,
+) -> bool {
+    let Some(this_update) = this_update else {
+        return false;
+    };
+    let freshness = Duration::from_secs(REVOCATION_FRESHNESS_SECS);
+    this_update >= time || time <= next_update.unwrap_or(this_update + freshness)
+}
+
+/// Follow issuers through `certs` from `cert`, up to a self-signed certificate
+/// or a certificate whose issuer is unknown, `None` then.
+pub(crate) fn issuer_path(cert: &X509Ref, certs: &[X509]) -> Vec<(X509, Option<X509>)> {
+    let mut path = Vec::new();
+    let mut current = cert.to_owned();
+    // Bound the walk in case of certificates that issue each other
+    for _ in 0..8 {
+        if current.issued(&current) == X509VerifyResult::OK {
+            break;
+        }
+        let issuer = certs
+            .iter()
+            .find(|candidate| {
+                candidate.issued(&current) == X509VerifyResult::OK
+                    && candidate.to_der().ok() != current.to_der().ok()
+            })
+            .cloned();
+        path.push((current, issuer.clone()));
+        match issuer {
+            Some(issuer) => current = issuer,
+            None => break,
+        }
+    }
+    path
+}
+
+/// Parse the openssl display form of a time, such as `Jan  2 03:04:05 2024 GMT`.
+pub(crate) fn parse_time(time: &str) -> Option<SystemTime> {
+    let time = time.split_whitespace().collect::<Vec<_>>().join(" ");
+    let time = NaiveDateTime::parse_from_str(&time, "%b %d %H:%M:%S %Y GMT").ok()?;
+    Some(Utc.from_utc_datetime(&time).into())
+}
+
+/// The name of a CRLReason (RFC 5280).
+fn reason_name(reason: i64) -> String {
+    let name = match reason {
+        0 => "unspecified",
+        1 => "keyCompromise",
+        2 => "cACompromise",
+        3 => "affiliationChanged",
+        4 => "superseded",
+        5 => "cessationOfOperation",
+        6 => "certificateHold",
+        8 => "removeFromCRL",
+        9 => "privilegeWithdrawn",
+        10 => "aACompromise",
+        _ => return format!("reason {}", reason),
+    };
+    name.to_owned()
+}
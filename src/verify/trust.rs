@@ -0,0 +1,204 @@
+//! Trust
+//! Build the certificate path of a signer to a local trust anchor.
+use std::{
+    path::Path,
+    time::{SystemTime, UNIX_EPOCH},
+};
+
+use anyhow::Result;
+use der::{Decode, oid::AssociatedOid};
+use openssl::{
+    stack::Stack,
+    x509::{
+        X509, X509Ref, X509StoreContext,
+        store::X509StoreBuilder,
+        verify::{X509VerifyFlags, X509VerifyParam},
+    },
+};
+use x509_cert::{
+    Certificate,
+    ext::pkix::{ExtendedKeyUsage, KeyUsage, KeyUsages},
+};
+
+use super::name_to_string;
+use crate::cms::oid;
+
+/// Whether a signer certificate chains to a trust anchor.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TrustStatus {
+    /// A path to a trust anchor was built, every certificate was valid at the
+    /// validation time and the signer certificate may sign documents.
+    Trusted,
+    /// The reason the certificate is not trusted.
+    Untrusted(String),
+}
+
+/// What the signer certificate is used for, it decides the required key usage.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum KeyPurpose {
+    /// A signature over the document.
+    DocumentSigning,
+    /// The TSA certificate of a timestamp token.
+    TimeStamping,
+}
+
+/// The certificates trusted as the end of a certificate path, self-signed
+/// roots or any CA the user chose to trust.
+#[derive(Default, Clone)]
+pub struct TrustStore {
+    anchors: Vec<X509>,
+}
+
+impl TrustStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add_anchor(&mut self, cert: X509) -> Result<()> {
+        for anchor in &self.anchors {
+            if anchor.to_der()? == cert.to_der()? {
+                return Ok(());
+            }
+        }
+        self.anchors.push(cert);
+        Ok(())
+    }
+
+    /// Add every certificate of a PEM bundle or a single DER certificate, or of
+    /// every such file in a directory.
+    pub fn add_path(&mut self, path: impl AsRef<Path>) -> Result<()> {
+        let path = path.as_ref();
+        if path.is_dir() {
+            let mut entries = std::fs::read_dir(path)?
+                .map(|entry| entry.map(|entry| entry.path()))
+                .collect::<Result<Vec<_>, _>>()?;
+            entries.sort();
+            for entry in entries.iter().filter(|entry| entry.is_file()) {
+                // Skip files that hold no certificate, such as a README or a hash link target
+                if let Ok(certs) = read_certs(entry) {
+                    for cert in certs {
+                        self.add_anchor(cert)?;
+                    }
+                }
+            }
+            return Ok(());
+        }
+        for cert in read_certs(path)? {
+            self.add_anchor(cert)?;
+        }
+        Ok(())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.anchors.is_empty()
+    }
+
+    /// Build the path from `signer` to an anchor with the help of the
+    /// `untrusted` certificates, and check it at `time`. Return the path,
+    /// signer first and anchor last.
+    pub(crate) fn validate(
+        &self,
+        signer: &X509Ref,
+        untrusted: &[X509],
+        time: Option<SystemTime>,
+        purpose: KeyPurpose,
+    ) -> Result<Vec<X509>> {
+        let mut builder = X509StoreBuilder::new()?;
+        for anchor in &self.anchors {
+            builder.add_cert(anchor.clone())?;
+        }
+        let mut param = X509VerifyParam::new()?;
+        // An intermediate CA may be the trust anchor, not only a self-signed root
+        param.set_flags(X509VerifyFlags::PARTIAL_CHAIN)?;
+        if let Some(time) = time {
+            let secs = time.duration_since(UNIX_EPOCH)?.as_secs();
+            param.set_time(secs.try_into()?);
+        }
+        builder.set_param(&param)?;
+        let store = builder.build();
+
+        let mut chain = Stack::new()?;
+        for cert in untrusted {
+            chain.push(cert.clone())?;
+        }
+        let mut context = X509StoreContext::new()?;
+        let path = context.init(&store, signer, &chain, |context| {
+            if context.verify_cert()? {
+                let path = context.chain().into_iter().flatten();
+                return Ok(Ok(path.map(ToOwned::to_owned).collect::<Vec<_>>()));
+            }
+            let subject = context
+                .current_cert()
+                .map(|cert| name_to_string(cert.subject_name()))
+                .unwrap_or_default();
+            Ok(Err(anyhow::anyhow!(
+                "{} ({})",
+                context.error().error_string(),
+                subject
+            )))
+        })??;
+        check_usage(signer, purpose)?;
+        Ok(path)
+    }
+}
+
+/// The signer certificate must allow digital signatures, and if it restricts
+/// its extended key usage, allow `purpose`. A TSA certificate must name
+/// timeStamping.
+fn check_usage(signer: &X509Ref, purpose: KeyPurpose) -> Result<()> {
+    let allowed: &[_] = match purpose {
+        KeyPurpose::DocumentSigning => &[
+            oid::ID_KP_DOCUMENT_SIGNING,
+            oid::ADOBE_AUTHENTIC_DOCUMENTS_TRUST,
+            oid::ID_KP_EMAIL_PROTECTION,
+            oid::ANY_EXTENDED_KEY_USAGE,
+        ],
+        KeyPurpose::TimeStamping => &[oid::ID_KP_TIME_STAMPING],
+    };
+    let mut has_extended_usage = false;
+    let cert = Certificate::from_der(&signer.to_der()?)?;
+    for ext in cert.tbs_certificate.extensions.iter().flatten() {
+        let value = ext.extn_value.as_bytes();
+        match ext.extn_id {
+            KeyUsage::OID => {
+                let usage = KeyUsage::from_der(value)?.0;
+                if !usage.contains(KeyUsages::DigitalSignature)
+                    && !usage.contains(KeyUsages::NonRepudiation)
+                {
+                    return Err(anyhow::anyhow!(
+                        "The key usage of the signer certificate does not allow signing"
+                    ));
+                }
+            }
+            ExtendedKeyUsage::OID => {
+                has_extended_usage = true;
+                let usage = ExtendedKeyUsage::from_der(value)?.0;
+                if !usage.iter().any(|usage| allowed.contains(usage)) {
+                    let purpose = match purpose {
+                        KeyPurpose::DocumentSigning => "document signing",
+                        KeyPurpose::TimeStamping => "time stamping",
+                    };
+                    return Err(anyhow::anyhow!(
+                        "The extended key usage of the signer certificate does not allow {}",
+                        purpose
+                    ));
+                }
+            }
+            _ => {}
+        }
+    }
+    if purpose == KeyPurpose::TimeStamping && !has_extended_usage {
+        return Err(anyhow::anyhow!(
+            "The TSA certificate has no extended key usage"
+        ));
+    }
+    Ok(())
+}
+
+fn read_certs(path: impl AsRef<Path>) -> Result<Vec<X509>> {
+    let data = std::fs::read(path)?;
+    match X509::stack_from_pem(&data) {
+        Ok(certs) if !certs.is_empty() => Ok(certs),
+        _ => Ok(vec![X509::from_der(&data)?]),
+    }
+}
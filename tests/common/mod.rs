@@ -0,0 +1,438 @@
+//! Fixtures shared by the integration tests: a scratch directory, a test PKI
+//! issuing signer certificates and helpers to sign, verify and append revisions.
+#![allow(dead_code)]
+
+use std::{
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::Result;
+use der::{
+    Decode, Encode,
+    asn1::{GeneralizedTime, UtcTime},
+};
+use lopdf::{Document, IncrementalDocument};
+use openssl::{
+    asn1::Asn1Time,
+    bn::{BigNum, MsbOption},
+    ec::{EcGroup, EcKey},
+    hash::MessageDigest,
+    nid::Nid,
+    pkey::{PKey, Private},
+    rsa::Rsa,
+    sign::Signer,
+    x509::{
+        X509, X509Builder, X509Name, X509NameBuilder,
+        extension::{BasicConstraints, ExtendedKeyUsage, KeyUsage},
+    },
+};
+use pdf_modify::{
+    ImageRect, P12Signer, PDFSignManager, PdfVerifier, PemSigner, Sign, SignatureVerification,
+    SignerInfo, TrustStore,
+};
+use x509_cert::spki::SubjectPublicKeyInfoOwned;
+
+pub const HELLO_WORLD: &str = "files/hello_world.pdf";
+
+/// A directory removed with its content when dropped.
+pub struct TestDir(PathBuf);
+
+impl TestDir {
+    pub fn new(name: &str) -> Self {
+        let dir = std::env::temp_dir().join(format!("pdf_modify-{}-{}", name, std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        TestDir(dir)
+    }
+
+    pub fn path(&self, file: &str) -> PathBuf {
+        self.0.join(file)
+    }
+
+    /// Write `data` to `file` and return its path.
+    pub fn write(&self, file: &str, data: &[u8]) -> PathBuf {
+        let path = self.path(file);
+        std::fs::write(&path, data).unwrap();
+        path
+    }
+}
+
+impl Drop for TestDir {
+    fn drop(&mut self) {
+        std::fs::remove_dir_all(&self.0).ok();
+    }
+}
+
+/// The signer of `certs/mycert.p12`, a self-signed RSA certificate.
+pub fn fixture_signer() -> P12Signer {
+    P12Signer::load("certs/mycert.p12").unwrap()
+}
+
+/// The key types the test PKI issues signer certificates for.
+#[derive(Debug, Clone, Copy)]
+pub enum KeyKind {
+    Rsa,
+    EcP256,
+    Ed25519,
+}
+
+impl KeyKind {
+    pub fn generate(self) -> PKey<Private> {
+        match self {
+            KeyKind::Rsa => PKey::from_rsa(Rsa::generate(2048).unwrap()).unwrap(),
+            KeyKind::EcP256 => {
+                let group = EcGroup::from_curve_name(Nid::X9_62_PRIME256V1).unwrap();
+                PKey::from_ec_key(EcKey::generate(&group).unwrap()).unwrap()
+            }
+            KeyKind::Ed25519 => PKey::generate_ed25519().unwrap(),
+        }
+    }
+}
+
+/// A root CA valid from yesterday for a year, issuing signer certificates.
+pub struct Pki {
+    pub root: X509,
+    root_key: PKey<Private>,
+}
+
+impl Pki {
+    pub fn generate() -> Self {
+        let root_key = KeyKind::Rsa.generate();
+        let mut builder = cert_builder("pdf_modify test root", &root_key);
+        builder
+            .set_issuer_name(name("pdf_modify test root").as_ref())
+            .unwrap();
+        builder
+            .append_extension(BasicConstraints::new().critical().ca().build().unwrap())
+            .unwrap();
+        builder
+            .append_extension(
+                KeyUsage::new()
+                    .critical()
+                    .key_cert_sign()
+                    .crl_sign()
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap();
+        builder.sign(&root_key, MessageDigest::sha256()).unwrap();
+        Pki {
+            root: builder.build(),
+            root_key,
+        }
+    }
+
+    /// Issue a document signing certificate for a new key of `kind`.
+    pub fn issue(&self, name: &str, kind: KeyKind) -> (X509, PKey<Private>) {
+        self.issue_valid(name, kind, now() - 86400, now() + 365 * 86400)
+    }
+
+    /// Issue a document signing certificate valid between the Unix times
+    /// `not_before` and `not_after`.
+    pub fn issue_valid(
+        &self,
+        name: &str,
+        kind: KeyKind,
+        not_before: i64,
+        not_after: i64,
+    ) -> (X509, PKey<Private>) {
+        let key = kind.generate();
+        let mut builder = cert_builder(name, &key);
+        builder
+            .set_not_before(Asn1Time::from_unix(not_before).unwrap().as_ref())
+            .unwrap();
+        builder
+            .set_not_after(Asn1Time::from_unix(not_after).unwrap().as_ref())
+            .unwrap();
+        builder.set_issuer_name(self.root.subject_name()).unwrap();
+        builder
+            .append_extension(BasicConstraints::new().critical().build().unwrap())
+            .unwrap();
+        builder
+            .append_extension(
+                KeyUsage::new()
+                    .critical()
+                    .digital_signature()
+                    .non_repudiation()
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap();
+        builder
+            .append_extension(ExtendedKeyUsage::new().email_protection().build().unwrap())
+            .unwrap();
+        builder
+            .sign(&self.root_key, MessageDigest::sha256())
+            .unwrap();
+        (builder.build(), key)
+    }
+
+    /// An intermediate CA issued by this one, as the `root` of a new `Pki`.
+    pub fn intermediate(&self, name: &str) -> Pki {
+        let key = KeyKind::Rsa.generate();
+        let mut builder = cert_builder(name, &key);
+        builder.set_issuer_name(self.root.subject_name()).unwrap();
+        builder
+            .append_extension(BasicConstraints::new().critical().ca().build().unwrap())
+            .unwrap();
+        builder
+            .append_extension(
+                KeyUsage::new()
+                    .critical()
+                    .key_cert_sign()
+                    .crl_sign()
+                    .build()
+                    .unwrap(),
+            )
+            .unwrap();
+        builder
+            .sign(&self.root_key, MessageDigest::sha256())
+            .unwrap();
+        Pki {
+            root: builder.build(),
+            root_key: key,
+        }
+    }
+
+    /// A PEM signer with a new key of `kind`, the root as its chain.
+    pub fn pem_signer(&self, dir: &TestDir, name: &str, kind: KeyKind) -> PemSigner {
+        let (cert, key) = self.issue(name, kind);
+        let cert = dir.write(&format!("{name}.crt"), &cert.to_pem().unwrap());
+        let key = dir.write(
+            &format!("{name}.key"),
+            &key.private_key_to_pem_pkcs8().unwrap(),
+        );
+        let chain = dir.write(&format!("{name}-chain.crt"), &self.root.to_pem().unwrap());
+        PemSigner::load(cert, key)
+            .unwrap()
+            .with_chain_file(chain)
+            .unwrap()
+    }
+
+    pub fn trust_store(&self) -> TrustStore {
+        let mut store = TrustStore::new();
+        store.add_anchor(self.root.clone()).unwrap();
+        store
+    }
+
+    /// A CRL of the root issued at `this_update`, revoking each certificate of
+    /// `revoked` for key compromise at the given time. Times are Unix seconds.
+    pub fn crl(
+        &self,
+        this_update: i64,
+        next_update: Option<i64>,
+        revoked: &[(&X509, i64)],
+    ) -> Vec<u8> {
+        let mut tbs = vec![
+            tlv(0x02, &[1]),
+            sha256_with_rsa(),
+            self.root.subject_name().to_der().unwrap(),
+            time::<UtcTime>(this_update),
+        ];
+        tbs.extend(next_update.map(time::<UtcTime>));
+        if !revoked.is_empty() {
+            let entries = revoked.iter().map(|(cert, time)| {
+                let reason = seq(&[oid(&[0x55, 0x1d, 0x15]), tlv(0x04, &KEY_COMPROMISE)]);
+                seq(&[serial(cert), self::time::<UtcTime>(*time), seq(&[reason])])
+            });
+            tbs.push(seq(&entries.collect::<Vec<_>>()));
+        }
+        self.signed(seq(&tbs), None)
+    }
+
+    /// A successful OCSP response of the root for `cert`, signed by the root.
+    pub fn ocsp(
+        &self,
+        cert: &X509,
+        status: OcspStatus,
+        this_update: i64,
+        next_update: Option<i64>,
+    ) -> Vec<u8> {
+        let sha1 = |data: &[u8]| tlv(0x04, &openssl::sha::sha1(data));
+        let spki = SubjectPublicKeyInfoOwned::from_der(
+            &self.root.public_key().unwrap().public_key_to_der().unwrap(),
+        )
+        .unwrap();
+        let cert_id = seq(&[
+            seq(&[oid(&[0x2b, 0x0e, 0x03, 0x02, 0x1a]), vec![0x05, 0x00]]),
+            sha1(&self.root.subject_name().to_der().unwrap()),
+            sha1(spki.subject_public_key.raw_bytes()),
+            serial(cert),
+        ]);
+        let status = match status {
+            OcspStatus::Good => vec![0x80, 0x00],
+            OcspStatus::Revoked(time) => tlv(
+                0xa1,
+                &[
+                    self::time::<GeneralizedTime>(time),
+                    tlv(0xa0, &KEY_COMPROMISE),
+                ]
+                .concat(),
+            ),
+            OcspStatus::Unknown => vec![0x82, 0x00],
+        };
+        let mut single = vec![cert_id, status, time::<GeneralizedTime>(this_update)];
+        single.extend(next_update.map(|time| tlv(0xa0, &self::time::<GeneralizedTime>(time))));
+        let response_data = seq(&[
+            tlv(0xa1, &self.root.subject_name().to_der().unwrap()),
+            time::<GeneralizedTime>(this_update),
+            seq(&[seq(&single)]),
+        ]);
+        let responder = tlv(0xa0, &seq(&[self.root.to_der().unwrap()]));
+        let basic = self.signed(response_data, Some(responder));
+        let basic_type = oid(&[0x2b, 0x06, 0x01, 0x05, 0x05, 0x07, 0x30, 0x01, 0x01]);
+        seq(&[
+            vec![0x0a, 0x01, 0x00],
+            tlv(0xa0, &seq(&[basic_type, tlv(0x04, &basic)])),
+        ])
+    }
+
+    /// `SEQUENCE { tbs, sha256WithRSAEncryption, signature, extra }` signed by the root.
+    fn signed(&self, tbs: Vec<u8>, extra: Option<Vec<u8>>) -> Vec<u8> {
+        let mut signer = Signer::new(MessageDigest::sha256(), &self.root_key).unwrap();
+        let signature = signer.sign_oneshot_to_vec(&tbs).unwrap();
+        let signature = tlv(0x03, &[&[0], signature.as_slice()].concat());
+        let mut items = vec![tbs, sha256_with_rsa(), signature];
+        items.extend(extra);
+        seq(&items)
+    }
+}
+
+/// The status an OCSP response gives, a revocation time in Unix seconds.
+pub enum OcspStatus {
+    Good,
+    Revoked(i64),
+    Unknown,
+}
+
+/// The DER of the CRLReason keyCompromise.
+const KEY_COMPROMISE: [u8; 3] = [0x0a, 0x01, 0x01];
+
+fn tlv(tag: u8, content: &[u8]) -> Vec<u8> {
+    let mut der = vec![tag];
+    match content.len() {
+        len @ 0..0x80 => der.push(len as u8),
+        len => {
+            let bytes = len.to_be_bytes();
+            let bytes = &bytes[bytes.iter().position(|b| *b != 0).unwrap()..];
+            der.push(0x80 | bytes.len() as u8);
+            der.extend_from_slice(bytes);
+        }
+    }
+    der.extend_from_slice(content);
+    der
+}
+
+fn seq(items: &[Vec<u8>]) -> Vec<u8> {
+    tlv(0x30, &items.concat())
+}
+
+fn oid(der: &[u8]) -> Vec<u8> {
+    tlv(0x06, der)
+}
+
+fn sha256_with_rsa() -> Vec<u8> {
+    seq(&[
+        oid(&[0x2a, 0x86, 0x48, 0x86, 0xf7, 0x0d, 0x01, 0x01, 0x0b]),
+        vec![0x05, 0x00],
+    ])
+}
+
+fn serial(cert: &X509) -> Vec<u8> {
+    let mut serial = cert.serial_number().to_bn().unwrap().to_vec();
+    if serial.first().is_none_or(|b| b & 0x80 != 0) {
+        serial.insert(0, 0);
+    }
+    tlv(0x02, &serial)
+}
+
+fn time<T: TimeFromUnix>(secs: i64) -> Vec<u8> {
+    T::der(Duration::from_secs(secs as u64))
+}
+
+trait TimeFromUnix {
+    fn der(since_epoch: Duration) -> Vec<u8>;
+}
+
+impl TimeFromUnix for UtcTime {
+    fn der(since_epoch: Duration) -> Vec<u8> {
+        UtcTime::from_unix_duration(since_epoch)
+            .unwrap()
+            .to_der()
+            .unwrap()
+    }
+}
+
+impl TimeFromUnix for GeneralizedTime {
+    fn der(since_epoch: Duration) -> Vec<u8> {
+        GeneralizedTime::from_unix_duration(since_epoch)
+            .unwrap()
+            .to_der()
+            .unwrap()
+    }
+}
+
+fn name(common_name: &str) -> X509Name {
+    let mut name = X509NameBuilder::new().unwrap();
+    name.append_entry_by_nid(Nid::COMMONNAME, common_name)
+        .unwrap();
+    name.build()
+}
+
+fn cert_builder(name: &str, key: &PKey<Private>) -> X509Builder {
+    let mut serial = BigNum::new().unwrap();
+    serial.rand(64, MsbOption::MAYBE_ZERO, false).unwrap();
+    let mut builder = X509::builder().unwrap();
+    builder.set_version(2).unwrap();
+    builder
+        .set_serial_number(serial.to_asn1_integer().unwrap().as_ref())
+        .unwrap();
+    builder.set_subject_name(self::name(name).as_ref()).unwrap();
+    builder.set_pubkey(key).unwrap();
+    builder
+        .set_not_before(Asn1Time::from_unix(now() - 86400).unwrap().as_ref())
+        .unwrap();
+    builder
+        .set_not_after(Asn1Time::days_from_now(365).unwrap().as_ref())
+        .unwrap();
+    builder
+}
+
+/// Seconds since the epoch.
+pub fn now() -> i64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .unwrap()
+        .as_secs() as i64
+}
+
+/// Sign the PDF at `input` with an invisible signature and return the new file.
+pub fn sign_file(input: &Path, signer: &dyn Sign) -> Result<Vec<u8>> {
+    let mut manager = PDFSignManager::load(input)?;
+    manager.sign(SignerInfo::empty(), None::<ImageRect<&Path>>, signer)?;
+    save(&mut manager)
+}
+
+pub fn save(manager: &mut PDFSignManager) -> Result<Vec<u8>> {
+    let mut data = Vec::new();
+    manager.save_to(&mut data)?;
+    Ok(data)
+}
+
+pub fn verify(data: &[u8]) -> Vec<SignatureVerification> {
+    PdfVerifier::from_bytes(data.to_vec())
+        .unwrap()
+        .verify()
+        .unwrap()
+}
+
+/// Append an incremental update made by `edit` to `data`, without signing.
+pub fn append_revision(data: &[u8], edit: impl FnOnce(&mut IncrementalDocument)) -> Vec<u8> {
+    let mut doc =
+        IncrementalDocument::create_from(data.to_vec(), Document::load_mem(data).unwrap());
+    doc.new_document.version = "1.7".into();
+    edit(&mut doc);
+    let mut updated = Vec::new();
+    doc.save_to(&mut updated).unwrap();
+    updated
+}
@@ -0,0 +1,141 @@
+//! Compare two revisions object by object.
+mod common;
+
+use common::append_revision;
+use lopdf::{Dictionary, Document, Object, ObjectId, Stream, dictionary};
+use pdf_modify::{
+    ContentChange, ModificationKind, ObjectChange, ObjectDiff, PdfVerifier, RevisionDiff,
+};
+
+/// Replace the page content and add an unreferenced object in revision 2.
+fn edited() -> (Vec<u8>, ObjectId, ObjectId) {
+    let input = std::fs::read(common::HELLO_WORLD).unwrap();
+    let mut ids = None;
+    let data = append_revision(&input, |doc| {
+        let prev = doc.get_prev_documents();
+        let page = prev.page_iter().next().unwrap();
+        let content_id = prev.get_page_contents(page)[0];
+        let content = b"BT /F1 24 Tf 100 700 Td (Goodbye) Tj ET".to_vec();
+        doc.new_document
+            .set_object(content_id, Stream::new(Dictionary::new(), content));
+        let added = doc.new_document.add_object(dictionary! {
+            "Title" => Object::string_literal("Added"),
+        });
+        ids = Some((content_id, added));
+    });
+    let (content_id, added) = ids.unwrap();
+    (data, content_id, added)
+}
+
+/// Append an update whose cross-reference table, or stream, frees the object `id`.
+fn free_object(data: &[u8], id: ObjectId, stream: bool) -> Vec<u8> {
+    let doc = Document::load_mem(data).unwrap();
+    let (root, _) = doc.trailer.get(b"Root").unwrap().as_reference().unwrap();
+    let size = doc.max_id + 1;
+    let prev = String::from_utf8_lossy(data)
+        .rsplit("startxref")
+        .next()
+        .unwrap()
+        .split_whitespace()
+        .next()
+        .unwrap()
+        .to_owned();
+    let mut updated = data.to_vec();
+    let xref = updated.len();
+    let generation = id.1 + 1;
+    if stream {
+        let mut rows = vec![0, 0, 0, 0, 0];
+        rows.extend(generation.to_be_bytes());
+        rows.push(1);
+        rows.extend((xref as u32).to_be_bytes());
+        rows.extend([0, 0]);
+        updated.extend(
+            format!(
+                "{size} 0 obj\n<</Type /XRef /Size {} /W [1 4 2] /Index [{} 1 {size} 1] /Root {root} 0 R /Prev {prev} /Length {}>>stream\n",
+                size + 1,
+                id.0,
+                rows.len()
+            )
+            .as_bytes(),
+        );
+        updated.extend(rows);
+        updated.extend(b"\nendstream\nendobj\n");
+    } else {
+        updated.extend(
+            format!(
+                "xref\n{} 1\n0000000000 {generation:05} f \ntrailer\n<</Size {size} /Root {root} 0 R /Prev {prev}>>\n",
+                id.0
+            )
+            .as_bytes(),
+        );
+    }
+    updated.extend(format!("startxref\n{xref}\n%%EOF\n").as_bytes());
+    updated
+}
+
+fn find(diff: &RevisionDiff, id: ObjectId) -> &ObjectDiff {
+    diff.objects
+        .iter()
+        .find(|object| object.id == id)
+        .unwrap_or_else(|| panic!("{id:?} not in {diff:?}"))
+}
+
+#[test]
+fn added_and_changed_objects() {
+    let (data, content_id, added) = edited();
+    let verifier = PdfVerifier::from_bytes(data).unwrap();
+    assert_eq!(verifier.revision_count(), 2);
+    let diff = verifier.diff(1, 2).unwrap();
+    assert_eq!((diff.from, diff.to), (1, 2));
+    assert_eq!(diff.count(ObjectChange::Removed), 0);
+
+    let object = find(&diff, added);
+    assert_eq!(object.change, ObjectChange::Added);
+    assert!(object.keys.is_empty());
+
+    let object = find(&diff, content_id);
+    assert_eq!(object.change, ObjectChange::Modified);
+    assert_eq!(object.kind, Some(ModificationKind::PageContent));
+    // Only the text operator differs, the operators around it are aligned
+    let [
+        ContentChange::Removed {
+            operation: removed, ..
+        },
+        ContentChange::Added {
+            operation: added, ..
+        },
+    ] = &object.content[..]
+    else {
+        panic!("{:?}", object.content);
+    };
+    assert_eq!(added.operator, "Tj");
+    assert_eq!(added.operands, [Object::string_literal("Goodbye")]);
+    assert_eq!(removed.operator, "Tj");
+    assert_ne!(removed.operands, added.operands);
+}
+
+#[test]
+fn removed_objects() {
+    let (data, _, added) = edited();
+    for stream in [false, true] {
+        let verifier = PdfVerifier::from_bytes(free_object(&data, added, stream)).unwrap();
+        assert_eq!(verifier.revision_count(), 3);
+
+        let diff = verifier.diff(2, 3).unwrap();
+        assert_eq!(diff.count(ObjectChange::Removed), 1, "{diff:?}");
+        assert_eq!(diff.count(ObjectChange::Modified), 0, "{diff:?}");
+        assert_eq!(find(&diff, added).change, ObjectChange::Removed);
+        // Present in neither the first nor the last revision
+        let diff = verifier.diff(1, 3).unwrap();
+        assert!(diff.objects.iter().all(|object| object.id != added));
+    }
+}
+
+#[test]
+fn revisions_out_of_range() {
+    let (data, _, _) = edited();
+    let verifier = PdfVerifier::from_bytes(data).unwrap();
+    for (from, to) in [(0, 1), (0, 2), (2, 2), (2, 1), (1, 3), (3, 4)] {
+        assert!(verifier.diff(from, to).is_err(), "{from} {to}");
+    }
+}
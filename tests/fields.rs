@@ -0,0 +1,189 @@
+//! The `/TU`, `/SV` and `/Lock` entries of empty signature fields, and the
+//! seed values checked when the field is signed.
+mod common;
+
+use std::path::Path;
+
+use common::{KeyKind, Pki, TestDir, fixture_signer, save};
+use lopdf::{Dictionary, Document, Object, decode_text_string};
+use pdf_modify::{
+    FieldLock, PDFSignManager, SeedValue, Sign, SignatureFieldOptions, SignerInfo, SubFilter,
+};
+
+/// The terminal field named `name` of the saved document.
+fn field<'a>(doc: &'a Document, name: &str) -> &'a Dictionary {
+    let acro_form = doc
+        .catalog()
+        .unwrap()
+        .get_deref(b"AcroForm", doc)
+        .and_then(Object::as_dict)
+        .unwrap();
+    acro_form
+        .get_deref(b"Fields", doc)
+        .and_then(Object::as_array)
+        .unwrap()
+        .iter()
+        .map(|field| doc.get_dictionary(field.as_reference().unwrap()).unwrap())
+        .find(|field| decode_text_string(field.get(b"T").unwrap()).unwrap() == name)
+        .unwrap_or_else(|| panic!("no field {name}"))
+}
+
+fn names(dict: &Dictionary, key: &[u8]) -> Vec<String> {
+    dict.get(key)
+        .and_then(Object::as_array)
+        .unwrap()
+        .iter()
+        .map(|name| String::from_utf8(name.as_name().unwrap().to_vec()).unwrap())
+        .collect()
+}
+
+fn texts(dict: &Dictionary, key: &[u8]) -> Vec<String> {
+    dict.get(key)
+        .and_then(Object::as_array)
+        .unwrap()
+        .iter()
+        .map(|text| decode_text_string(text).unwrap())
+        .collect()
+}
+
+/// A manager with the empty signature field `seeded` on the first page.
+fn with_field(options: SignatureFieldOptions) -> PDFSignManager {
+    let mut manager = PDFSignManager::load(common::HELLO_WORLD).unwrap();
+    manager
+        .add_signature_field(1, [50, 50, 150, 100], "seeded", options)
+        .unwrap();
+    manager
+}
+
+#[test]
+fn seed_value_and_lock_are_written() {
+    let pki = Pki::generate();
+    let (subject, _) = pki.issue("signer", KeyKind::EcP256);
+    let mut manager = with_field(SignatureFieldOptions {
+        tooltip: Some("Sign here".to_owned()),
+        seed_value: Some(SeedValue {
+            sub_filters: vec![SubFilter::CadesDetached],
+            digest_methods: vec!["SHA256".to_owned()],
+            reasons: vec!["Approved".to_owned(), "Reviewed".to_owned()],
+            subjects: vec![subject.clone()],
+            issuers: vec![pki.root.clone()],
+        }),
+        lock: Some(FieldLock::Exclude(vec!["seeded".to_owned()])),
+    });
+    let doc = Document::load_mem(&save(&mut manager).unwrap()).unwrap();
+    let field = field(&doc, "seeded");
+    assert_eq!(
+        decode_text_string(field.get(b"TU").unwrap()).unwrap(),
+        "Sign here"
+    );
+
+    let sv = field
+        .get_deref(b"SV", &doc)
+        .and_then(Object::as_dict)
+        .unwrap();
+    assert_eq!(sv.get(b"Type").unwrap().as_name().unwrap(), b"SV");
+    // SubFilter, Reasons and DigestMethod are required
+    assert_eq!(sv.get(b"Ff").unwrap().as_i64().unwrap(), 2 | 8 | 64);
+    assert_eq!(names(sv, b"SubFilter"), ["ETSI.CAdES.detached"]);
+    assert_eq!(names(sv, b"DigestMethod"), ["SHA256"]);
+    assert_eq!(texts(sv, b"Reasons"), ["Approved", "Reviewed"]);
+    let cert = sv
+        .get_deref(b"Cert", &doc)
+        .and_then(Object::as_dict)
+        .unwrap();
+    assert_eq!(cert.get(b"Type").unwrap().as_name().unwrap(), b"SVCert");
+    assert_eq!(cert.get(b"Ff").unwrap().as_i64().unwrap(), 1 | 2);
+    let der = |key: &[u8]| {
+        cert.get(key).and_then(Object::as_array).unwrap()[0]
+            .as_str()
+            .unwrap()
+            .to_vec()
+    };
+    assert_eq!(der(b"Subject"), subject.to_der().unwrap());
+    assert_eq!(der(b"Issuer"), pki.root.to_der().unwrap());
+
+    let lock = field
+        .get_deref(b"Lock", &doc)
+        .and_then(Object::as_dict)
+        .unwrap();
+    assert_eq!(
+        lock.get(b"Type").unwrap().as_name().unwrap(),
+        b"SigFieldLock"
+    );
+    assert_eq!(lock.get(b"Action").unwrap().as_name().unwrap(), b"Exclude");
+    assert_eq!(texts(lock, b"Fields"), ["seeded"]);
+}
+
+#[test]
+fn empty_options_write_no_entries() {
+    let mut manager = with_field(Default::default());
+    let doc = Document::load_mem(&save(&mut manager).unwrap()).unwrap();
+    let field = field(&doc, "seeded");
+    for key in [&b"TU"[..], b"SV", b"Lock"] {
+        assert!(!field.has(key), "{}", String::from_utf8_lossy(key));
+    }
+}
+
+/// Sign the field `seeded` of `manager` and return the error message, if any.
+fn sign_seeded(manager: &mut PDFSignManager, signer: &dyn Sign) -> Option<String> {
+    manager
+        .sign_field("seeded", SignerInfo::empty(), None::<&Path>, signer)
+        .err()
+        .map(|e| e.to_string())
+}
+
+#[test]
+fn seed_value_is_enforced() {
+    let seeded = |seed_value| {
+        with_field(SignatureFieldOptions {
+            seed_value: Some(seed_value),
+            ..Default::default()
+        })
+    };
+    let cades = || fixture_signer().with_sub_filter(SubFilter::CadesDetached);
+
+    let sub_filter = SeedValue {
+        sub_filters: vec![SubFilter::CadesDetached],
+        ..Default::default()
+    };
+    let error = sign_seeded(&mut seeded(sub_filter.clone()), &fixture_signer()).unwrap();
+    assert!(error.contains("requires one of the SubFilters"), "{error}");
+
+    // The first reason is taken when the signer gives none
+    let mut manager = seeded(SeedValue {
+        reasons: vec!["Approved".to_owned()],
+        ..sub_filter
+    });
+    assert_eq!(sign_seeded(&mut manager, &cades()), None);
+    let doc = Document::load_mem(&save(&mut manager).unwrap()).unwrap();
+    let sig = field(&doc, "seeded")
+        .get_deref(b"V", &doc)
+        .and_then(Object::as_dict)
+        .unwrap();
+    assert_eq!(
+        decode_text_string(sig.get(b"Reason").unwrap()).unwrap(),
+        "Approved"
+    );
+
+    let dir = TestDir::new("fields-seed");
+    let pki = Pki::generate();
+    let (subject, _) = pki.issue("other", KeyKind::EcP256);
+    let error = sign_seeded(
+        &mut seeded(SeedValue {
+            subjects: vec![subject],
+            ..Default::default()
+        }),
+        &pki.pem_signer(&dir, "signer", KeyKind::EcP256),
+    )
+    .unwrap();
+    assert!(error.contains("not accepted by the field"), "{error}");
+
+    let issuers = SeedValue {
+        issuers: vec![pki.root.clone()],
+        ..Default::default()
+    };
+    let error = sign_seeded(&mut seeded(issuers.clone()), &fixture_signer()).unwrap();
+    assert!(error.contains("not issued by an issuer"), "{error}");
+    let signer = pki.pem_signer(&dir, "signer", KeyKind::EcP256);
+    assert_eq!(sign_seeded(&mut seeded(issuers), &signer), None);
+}
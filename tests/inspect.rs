@@ -0,0 +1,156 @@
+//! Describe the pages, fields, images and signatures of the fixture PDF, as
+//! given and after sealing and signing it.
+mod common;
+
+use chrono::TimeZone;
+use common::{fixture_signer, save};
+use pdf_modify::{
+    DocMdpPermission, DocumentInfo, ImageRect, LocalTimestampAuthority, PDFSignManager, SignerInfo,
+};
+
+const A4: [f32; 4] = [0.0, 0.0, 595.0, 842.0];
+const SEAL: &str = "files/seal.png";
+
+#[test]
+fn unsigned_document() {
+    let info = DocumentInfo::load(common::HELLO_WORLD).unwrap();
+    assert_eq!(info.version, "1.7");
+    assert_eq!(info.revisions, 1);
+    assert_eq!(info.pages.len(), 3);
+    for (index, page) in info.pages.iter().enumerate() {
+        assert_eq!(page.number, index as u32 + 1);
+        assert_eq!(page.media_box.as_deref(), Some(&A4[..]));
+        // The MediaBox stands in for a missing CropBox
+        assert_eq!(page.crop_box, page.media_box);
+        assert_eq!(page.rotate, 0);
+        assert_eq!(page.annotations, 0);
+        assert!(page.images.is_empty());
+    }
+    assert_eq!(info.pages[0].id, (4, 0));
+    assert!(info.fields.is_empty());
+    assert!(info.signatures.is_empty());
+}
+
+#[test]
+fn sealed_and_signed_document() {
+    let mut manager = PDFSignManager::load(common::HELLO_WORLD).unwrap();
+    manager.add_cross_page_seal(SEAL, (90, 120)).unwrap();
+    manager
+        .add_signature_field(2, [50, 50, 150, 100], "approval", Default::default())
+        .unwrap();
+    let signer_info = SignerInfo::builder()
+        .name("Alice")
+        .reason("Approved")
+        .location("Shanghai")
+        .date(chrono::Utc.with_ymd_and_hms(2024, 1, 2, 3, 4, 5).unwrap())
+        .build();
+    manager
+        .certify(
+            DocMdpPermission::FormFilling,
+            signer_info,
+            Some(ImageRect::new(SEAL, (100, 100), (120, 120))),
+            &fixture_signer(),
+        )
+        .unwrap();
+    manager
+        .timestamp(&LocalTimestampAuthority::generate().unwrap())
+        .unwrap();
+    let data = save(&mut manager).unwrap();
+    let info = DocumentInfo::from_bytes(&data).unwrap();
+    // The seal and the field are written with the certification
+    assert_eq!(info.revisions, 3);
+
+    // A third of the seal on the right edge of every page, vertically centered
+    for page in &info.pages {
+        let [image] = &page.images[..] else {
+            panic!("{:?}", page.images);
+        };
+        assert!(image.name.starts_with('X'), "{}", image.name);
+        assert!(image.width > 0 && image.height > 0);
+        assert_eq!(image.bits_per_component, Some(8));
+        assert_eq!(image.color_space.as_deref(), Some("DeviceRGB"));
+        assert!(image.has_soft_mask);
+        assert_eq!(image.placements, [[30.0, 0.0, 0.0, 120.0, 565.0, 361.0]]);
+    }
+    let images = info
+        .pages
+        .iter()
+        .map(|page| page.images[0].id)
+        .collect::<std::collections::BTreeSet<_>>();
+    assert_eq!(images.len(), 3);
+    // The widget of the empty field, the signature goes on the last page
+    let annotations = info
+        .pages
+        .iter()
+        .map(|page| page.annotations)
+        .collect::<Vec<_>>();
+    assert_eq!(annotations[1], 1);
+    assert!(annotations[2] >= 1);
+
+    let approval = info
+        .fields
+        .iter()
+        .find(|field| field.name == "approval")
+        .unwrap();
+    assert_eq!(approval.field_type.as_deref(), Some("Sig"));
+    assert!(!approval.signed);
+    assert_eq!(approval.value, None);
+    assert_eq!(approval.page, Some(2));
+    assert_eq!(
+        approval.rect.as_deref(),
+        Some(&[50.0, 50.0, 150.0, 100.0][..])
+    );
+
+    // In the order they were added
+    let [certification, timestamp] = &info.signatures[..] else {
+        panic!("{:?}", info.signatures);
+    };
+    let field = info
+        .fields
+        .iter()
+        .find(|field| field.name == *certification.field_name.as_ref().unwrap())
+        .unwrap();
+    assert!(field.signed);
+    assert_eq!(field.page, Some(3));
+    assert_eq!(
+        field.rect.as_deref(),
+        Some(&[100.0, 100.0, 220.0, 220.0][..])
+    );
+    assert_eq!(certification.sig_type.as_deref(), Some("Sig"));
+    assert_eq!(
+        certification.sub_filter.as_deref(),
+        Some("adbe.pkcs7.detached")
+    );
+    assert_eq!(certification.name.as_deref(), Some("Alice"));
+    assert_eq!(certification.reason.as_deref(), Some("Approved"));
+    assert_eq!(certification.location.as_deref(), Some("Shanghai"));
+    assert_eq!(certification.contact_info, None);
+    assert_eq!(
+        certification.signing_time.as_deref(),
+        Some("D:20240102030405Z")
+    );
+    assert_eq!(certification.byte_range.len(), 4);
+    assert_eq!(certification.byte_range[0], 0);
+    // Two hex digits per byte and the angle brackets
+    assert_eq!(
+        (certification.byte_range[2] - certification.byte_range[1]) as usize,
+        certification.contents_len * 2 + 2
+    );
+    assert!(
+        certification
+            .signer_subject
+            .as_deref()
+            .unwrap()
+            .ends_with("CN=example.com")
+    );
+    assert_eq!(certification.doc_mdp, Some(DocMdpPermission::FormFilling));
+
+    assert_eq!(timestamp.sig_type.as_deref(), Some("DocTimeStamp"));
+    assert_eq!(timestamp.sub_filter.as_deref(), Some("ETSI.RFC3161"));
+    assert!(timestamp.signer_subject.is_some());
+    assert_eq!(timestamp.doc_mdp, None);
+    assert_eq!(
+        timestamp.byte_range[3] as usize + timestamp.byte_range[2] as usize,
+        data.len()
+    );
+}
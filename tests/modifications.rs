@@ -0,0 +1,306 @@
+//! Incremental updates after a signature: what the verifier allows, and the
+//! changes smuggled in as validation data or as a new field value.
+mod common;
+
+use std::path::Path;
+
+use common::{append_revision, fixture_signer, save, sign_file, verify};
+use lopdf::{Dictionary, Document, IncrementalDocument, Object, ObjectId, Stream, dictionary};
+use pdf_modify::{
+    DocMdpPermission, FieldLock, ImageRect, ModificationKind, PDFSignManager,
+    SignatureFieldOptions, SignatureVerification, SignerInfo, ValidationData,
+};
+
+fn signed() -> Vec<u8> {
+    sign_file(Path::new(common::HELLO_WORLD), &fixture_signer()).unwrap()
+}
+
+fn suspicious(result: &SignatureVerification) -> Vec<ModificationKind> {
+    result
+        .suspicious_modifications()
+        .map(|modification| modification.kind)
+        .collect()
+}
+
+fn first_page(doc: &Document) -> ObjectId {
+    doc.page_iter().next().unwrap()
+}
+
+/// The merged field and widget dictionary of the only signature.
+fn signature_field(doc: &Document) -> ObjectId {
+    let fields = doc
+        .catalog()
+        .unwrap()
+        .get_deref(b"AcroForm", doc)
+        .and_then(Object::as_dict)
+        .and_then(|form| form.get(b"Fields"))
+        .and_then(Object::as_array)
+        .unwrap();
+    fields[0].as_reference().unwrap()
+}
+
+/// Replace the first content stream of the first page.
+fn rewrite_page(doc: &mut IncrementalDocument) -> ObjectId {
+    let prev = doc.get_prev_documents();
+    let content_id = prev.get_page_contents(first_page(prev))[0];
+    let content = b"BT /F1 24 Tf 100 700 Td (Pay 1000000) Tj ET".to_vec();
+    doc.new_document
+        .set_object(content_id, Stream::new(Dictionary::new(), content));
+    content_id
+}
+
+/// Add a `/DSS` to the catalog whose `/Certs` names `certs`.
+fn add_dss(doc: &mut IncrementalDocument, certs: Vec<ObjectId>) {
+    let root_id = doc
+        .get_prev_documents()
+        .trailer
+        .get(b"Root")
+        .and_then(Object::as_reference)
+        .unwrap();
+    doc.opt_clone_object_to_new_document(root_id).unwrap();
+    let certs = certs.into_iter().map(Object::Reference).collect::<Vec<_>>();
+    let dss = doc
+        .new_document
+        .add_object(dictionary! { "Certs" => certs });
+    doc.new_document
+        .get_dictionary_mut(root_id)
+        .unwrap()
+        .set("DSS", dss);
+}
+
+#[test]
+fn appended_page_content_is_suspicious() {
+    let attacked = append_revision(&signed(), |doc| {
+        rewrite_page(doc);
+    });
+    let results = verify(&attacked);
+    // The signed bytes are intact, only the update tells the attack apart
+    assert!(results[0].is_valid());
+    assert!(!results[0].covers_whole_file);
+    assert_eq!(suspicious(&results[0]), [ModificationKind::PageContent]);
+}
+
+#[test]
+fn dss_validation_data_is_allowed() {
+    let mut manager = PDFSignManager::load(common::HELLO_WORLD).unwrap();
+    manager
+        .sign(
+            SignerInfo::empty(),
+            None::<ImageRect<&Path>>,
+            &fixture_signer(),
+        )
+        .unwrap();
+    manager.add_dss(ValidationData::new(), None).unwrap();
+    let results = verify(&save(&mut manager).unwrap());
+    assert!(!results[0].modifications.is_empty());
+    assert_eq!(suspicious(&results[0]), []);
+}
+
+#[test]
+fn dss_cannot_launder_page_content() {
+    let attacked = append_revision(&signed(), |doc| {
+        let content_id = rewrite_page(doc);
+        add_dss(doc, vec![content_id]);
+    });
+    assert_eq!(
+        suspicious(&verify(&attacked)[0]),
+        [ModificationKind::PageContent]
+    );
+}
+
+#[test]
+fn dss_cannot_launder_annotations() {
+    let attacked = append_revision(&signed(), |doc| {
+        let widget_id = signature_field(doc.get_prev_documents());
+        doc.opt_clone_object_to_new_document(widget_id).unwrap();
+        doc.new_document
+            .get_dictionary_mut(widget_id)
+            .unwrap()
+            .set("Rect", vec![0.into(), 0.into(), 300.into(), 300.into()]);
+        add_dss(doc, vec![widget_id]);
+    });
+    assert_eq!(
+        suspicious(&verify(&attacked)[0]),
+        [ModificationKind::Object]
+    );
+}
+
+#[test]
+fn dss_cannot_launder_other_dictionaries() {
+    let attacked = append_revision(&signed(), |doc| {
+        // A plain dictionary named by the DSS is no validation data either
+        let info = doc.new_document.add_object(dictionary! {
+            "Title" => Object::string_literal("Signed contract"),
+        });
+        let root_id = doc
+            .get_prev_documents()
+            .trailer
+            .get(b"Root")
+            .and_then(Object::as_reference)
+            .unwrap();
+        add_dss(doc, vec![info]);
+        doc.new_document
+            .get_dictionary_mut(root_id)
+            .unwrap()
+            .set("OpenAction", info);
+    });
+    assert_eq!(
+        suspicious(&verify(&attacked)[0]),
+        [ModificationKind::Object]
+    );
+}
+
+#[test]
+fn signature_value_cannot_be_replaced() {
+    let attacked = append_revision(&signed(), |doc| {
+        let prev = doc.get_prev_documents();
+        let field_id = signature_field(prev);
+        let old_sig = prev
+            .get_dictionary(field_id)
+            .and_then(|field| field.get(b"V"))
+            .and_then(Object::as_reference)
+            .unwrap();
+        let sig = prev.get_dictionary(old_sig).unwrap().clone();
+        let sig = doc.new_document.add_object(sig);
+        doc.opt_clone_object_to_new_document(field_id).unwrap();
+        doc.new_document
+            .get_dictionary_mut(field_id)
+            .unwrap()
+            .set("V", sig);
+    });
+    let results = verify(&attacked);
+    let first = results.iter().min_by_key(|r| r.revision).unwrap();
+    assert_eq!(suspicious(first), [ModificationKind::Object]);
+}
+
+#[test]
+fn appearance_cannot_change_without_a_value() {
+    let attacked = append_revision(&signed(), |doc| {
+        let widget_id = signature_field(doc.get_prev_documents());
+        let appearance = doc.new_document.add_object(Stream::new(
+            dictionary! {
+                "Type" => "XObject",
+                "Subtype" => "Form",
+                "BBox" => vec![0.into(), 0.into(), 100.into(), 50.into()],
+            },
+            b"BT /F1 12 Tf 0 0 Td (Approved) Tj ET".to_vec(),
+        ));
+        doc.opt_clone_object_to_new_document(widget_id).unwrap();
+        doc.new_document
+            .get_dictionary_mut(widget_id)
+            .unwrap()
+            .set("AP", dictionary! { "N" => appearance });
+    });
+    assert_eq!(
+        suspicious(&verify(&attacked)[0]),
+        [ModificationKind::Object]
+    );
+}
+
+fn certified(permission: DocMdpPermission) -> PDFSignManager {
+    let mut manager = PDFSignManager::load(common::HELLO_WORLD).unwrap();
+    manager
+        .certify(
+            permission,
+            SignerInfo::empty(),
+            None::<ImageRect<&Path>>,
+            &fixture_signer(),
+        )
+        .unwrap();
+    manager
+}
+
+#[test]
+fn no_changes_certification_forbids_signatures() {
+    let mut manager = certified(DocMdpPermission::NoChanges);
+    manager
+        .sign(
+            SignerInfo::empty(),
+            None::<ImageRect<&Path>>,
+            &fixture_signer(),
+        )
+        .unwrap();
+    let results = verify(&save(&mut manager).unwrap());
+    let certification = results.iter().min_by_key(|r| r.revision).unwrap();
+    assert!(suspicious(certification).contains(&ModificationKind::Signature));
+}
+
+fn add_text_annotation(data: &[u8]) -> Vec<u8> {
+    append_revision(data, |doc| {
+        let page_id = first_page(doc.get_prev_documents());
+        let note = doc.new_document.add_object(dictionary! {
+            "Type" => "Annot",
+            "Subtype" => "Text",
+            "Rect" => vec![10.into(), 10.into(), 30.into(), 30.into()],
+            "Contents" => Object::string_literal("Looks good"),
+        });
+        doc.opt_clone_object_to_new_document(page_id).unwrap();
+        doc.new_document
+            .get_dictionary_mut(page_id)
+            .unwrap()
+            .as_hashmap_mut()
+            .entry(b"Annots".to_vec())
+            .or_insert_with(|| Object::Array(vec![]))
+            .as_array_mut()
+            .unwrap()
+            .push(note.into());
+    })
+}
+
+#[test]
+fn annotations_follow_the_doc_mdp_permission() {
+    let form_filling = save(&mut certified(DocMdpPermission::FormFilling)).unwrap();
+    let results = verify(&add_text_annotation(&form_filling));
+    assert!(suspicious(&results[0]).contains(&ModificationKind::Annotation));
+
+    let annotations = save(&mut certified(DocMdpPermission::Annotations)).unwrap();
+    let results = verify(&add_text_annotation(&annotations));
+    assert_eq!(suspicious(&results[0]), []);
+}
+
+/// Add the signature fields `a`, locking the fields of `lock` once signed, and
+/// `b`, then sign `a` and `b` in turn. Return the verification of `a`.
+fn sign_locked_fields(lock: FieldLock) -> SignatureVerification {
+    let mut manager = PDFSignManager::load(common::HELLO_WORLD).unwrap();
+    let options = SignatureFieldOptions {
+        lock: Some(lock),
+        ..Default::default()
+    };
+    manager
+        .add_signature_field(1, [50, 50, 150, 100], "a", options)
+        .unwrap();
+    manager
+        .add_signature_field(1, [200, 50, 300, 100], "b", Default::default())
+        .unwrap();
+    let signer = fixture_signer();
+    for field in ["a", "b"] {
+        manager
+            .sign_field(field, SignerInfo::empty(), None::<&Path>, &signer)
+            .unwrap();
+    }
+    verify(&save(&mut manager).unwrap())
+        .into_iter()
+        .find(|r| r.field_name.as_deref() == Some("a"))
+        .unwrap()
+}
+
+#[test]
+fn field_mdp_lock_is_enforced() {
+    let names = |names: &[&str]| names.iter().map(|name| name.to_string()).collect();
+    for (lock, locks_b) in [
+        (FieldLock::All, true),
+        (FieldLock::Include(names(&["b"])), true),
+        (FieldLock::Include(names(&["c"])), false),
+        (FieldLock::Exclude(names(&["c"])), true),
+        (FieldLock::Exclude(names(&["b"])), false),
+    ] {
+        let a = sign_locked_fields(lock.clone());
+        assert!(a.is_valid(), "{lock:?}");
+        let expected: &[ModificationKind] = if locks_b {
+            &[ModificationKind::Signature]
+        } else {
+            &[]
+        };
+        assert_eq!(suspicious(&a), expected, "{lock:?}");
+    }
+}
@@ -0,0 +1,91 @@
+//! Load a PEM or DER certificate, a certificate bundle and a plain or
+//! encrypted PKCS#8 private key.
+mod common;
+
+use std::path::Path;
+
+use common::{KeyKind, Pki, TestDir, sign_file};
+use openssl::symm::Cipher;
+use pdf_modify::{PdfVerifier, PemSigner, Sign};
+
+const PASSWORD: &str = "correct horse";
+
+fn assert_signs(signer: &PemSigner, pki: &Pki) {
+    let signed = sign_file(Path::new(common::HELLO_WORLD), signer).unwrap();
+    let results = PdfVerifier::from_bytes(signed)
+        .unwrap()
+        .with_trust_store(pki.trust_store())
+        .verify()
+        .unwrap();
+    assert!(results[0].is_valid(), "{:?}", results[0].status);
+    assert!(results[0].is_trusted(), "{:?}", results[0].trust);
+}
+
+#[test]
+fn encrypted_pkcs8_keys() {
+    let dir = TestDir::new("pem-encrypted");
+    let pki = Pki::generate();
+    let (cert, key) = pki.issue("signer", KeyKind::EcP256);
+    let cert = dir.write("signer.crt", &cert.to_pem().unwrap());
+    let pem = dir.write(
+        "signer.key",
+        &key.private_key_to_pem_pkcs8_passphrase(Cipher::aes_256_cbc(), PASSWORD.as_bytes())
+            .unwrap(),
+    );
+    let der = dir.write(
+        "signer.der",
+        &key.private_key_to_pkcs8_passphrase(Cipher::aes_256_cbc(), PASSWORD.as_bytes())
+            .unwrap(),
+    );
+    for key in [&pem, &der] {
+        let signer = PemSigner::load_with_password(&cert, key, PASSWORD)
+            .unwrap()
+            .with_chain_file(dir.write("chain.crt", &pki.root.to_pem().unwrap()))
+            .unwrap();
+        assert_signs(&signer, &pki);
+
+        let error = PemSigner::load_with_password(&cert, key, "wrong")
+            .err()
+            .unwrap();
+        assert!(
+            error.to_string().contains("password may be wrong"),
+            "{error}"
+        );
+        // An encrypted key needs the password, it is not prompted for
+        assert!(PemSigner::load(&cert, key).is_err());
+    }
+    let error = PemSigner::load(&cert, &pem).err().unwrap();
+    assert!(
+        error.to_string().contains("password is required"),
+        "{error}"
+    );
+}
+
+#[test]
+fn bundle_keeps_the_chain() {
+    let dir = TestDir::new("pem-bundle");
+    let pki = Pki::generate();
+    let (cert, key) = pki.issue("signer", KeyKind::Rsa);
+    let key = dir.write("signer.key", &key.private_key_to_pem_pkcs8().unwrap());
+
+    // The certificate of the key need not come first
+    for bundle in [
+        [cert.to_pem().unwrap(), pki.root.to_pem().unwrap()].concat(),
+        [pki.root.to_pem().unwrap(), cert.to_pem().unwrap()].concat(),
+    ] {
+        let signer = PemSigner::load(dir.write("bundle.pem", &bundle), &key).unwrap();
+        let certs = signer.certificates();
+        assert_eq!(certs.len(), 2);
+        assert_eq!(certs[0].to_der().unwrap(), cert.to_der().unwrap());
+        assert_eq!(certs[1].to_der().unwrap(), pki.root.to_der().unwrap());
+        // The root travels in the signature, the trust store finds the path
+        assert_signs(&signer, &pki);
+    }
+
+    let other = dir.write(
+        "other.crt",
+        &pki.issue("other", KeyKind::Rsa).0.to_pem().unwrap(),
+    );
+    let error = PemSigner::load(other, &key).err().unwrap();
+    assert!(error.to_string().contains("does not match"), "{error}");
+}
@@ -0,0 +1,180 @@
+//! Sign through SoftHSMv2. The test is skipped when the module is not installed,
+//! set `SOFTHSM2_MODULE` to its path if it is not in a standard location.
+use std::path::{Path, PathBuf};
+
+use anyhow::Result;
+use cryptoki::{
+    context::{CInitializeArgs, CInitializeFlags, Pkcs11},
+    object::{Attribute, CertificateType, KeyType, ObjectClass},
+    session::{Session, UserType},
+    types::AuthPin,
+};
+use openssl::{
+    pkey::{PKey, Private},
+    rsa::Rsa,
+    x509::X509,
+};
+use pdf_modify::{
+    CmsSigner, ImageRect, PDFSignManager, PdfVerifier, Pkcs11Signer, SignatureScheme,
+    SignatureStatus, SignerInfo, SubFilter,
+};
+
+const TOKEN: &str = "pdf_modify";
+const USER_PIN: &str = "1234";
+
+fn softhsm_module() -> Option<PathBuf> {
+    if let Some(module) = std::env::var_os("SOFTHSM2_MODULE") {
+        return Some(module.into());
+    }
+    [
+        "/usr/lib/softhsm/libsofthsm2.so",
+        "/usr/lib/x86_64-linux-gnu/softhsm/libsofthsm2.so",
+        "/usr/lib64/pkcs11/libsofthsm2.so",
+        "/usr/local/lib/softhsm/libsofthsm2.so",
+        "/opt/homebrew/lib/softhsm/libsofthsm2.so",
+    ]
+    .into_iter()
+    .map(PathBuf::from)
+    .find(|path| path.exists())
+}
+
+/// Point SoftHSMv2 at an empty token directory and import the key and the
+/// certificate of `certs/` into a new token.
+fn init_token(module: &Path, dir: &Path) -> Result<()> {
+    let tokens = dir.join("tokens");
+    std::fs::create_dir_all(&tokens)?;
+    let conf = dir.join("softhsm2.conf");
+    std::fs::write(
+        &conf,
+        format!("directories.tokendir = {}\n", tokens.display()),
+    )?;
+    // SAFETY: the only test of this binary, no other thread reads the environment
+    unsafe { std::env::set_var("SOFTHSM2_CONF", &conf) };
+
+    let pkcs11 = Pkcs11::new(module)?;
+    pkcs11.initialize(CInitializeArgs::new(CInitializeFlags::OS_LOCKING_OK))?;
+    let slot = pkcs11.get_slots_with_token()?[0];
+    let so_pin = AuthPin::new("5678".into());
+    pkcs11.init_token(slot, &so_pin, TOKEN)?;
+    // SoftHSMv2 moves an initialized token to a new slot
+    let slot = *pkcs11
+        .get_slots_with_initialized_token()?
+        .iter()
+        .find(|slot| {
+            pkcs11
+                .get_token_info(**slot)
+                .is_ok_and(|info| info.label().trim_end() == TOKEN)
+        })
+        .expect("initialized token");
+    let session = pkcs11.open_rw_session(slot)?;
+    session.login(UserType::So, Some(&so_pin))?;
+    session.init_pin(&AuthPin::new(USER_PIN.into()))?;
+    session.logout()?;
+    session.login(UserType::User, Some(&AuthPin::new(USER_PIN.into())))?;
+
+    // Another key without a certificate, found before the signer key
+    import_rsa_key(&session, "other", 2, &PKey::from_rsa(Rsa::generate(2048)?)?)?;
+    let key = PKey::private_key_from_pem(&std::fs::read("certs/mykey.key")?)?;
+    import_rsa_key(&session, "signer", 1, &key)?;
+    let cert = X509::from_pem(&std::fs::read("certs/mycert.crt")?)?;
+    session.create_object(&[
+        Attribute::Class(ObjectClass::CERTIFICATE),
+        Attribute::CertificateType(CertificateType::X_509),
+        Attribute::Token(true),
+        Attribute::Id(vec![1]),
+        Attribute::Subject(cert.subject_name().to_der()?),
+        Attribute::Value(cert.to_der()?),
+    ])?;
+    Ok(())
+}
+
+fn import_rsa_key(session: &Session, label: &str, id: u8, key: &PKey<Private>) -> Result<()> {
+    let rsa = key.rsa()?;
+    let number = |n: Option<&openssl::bn::BigNumRef>| n.map(|n| n.to_vec()).unwrap_or_default();
+    session.create_object(&[
+        Attribute::Class(ObjectClass::PRIVATE_KEY),
+        Attribute::KeyType(KeyType::RSA),
+        Attribute::Token(true),
+        Attribute::Private(true),
+        Attribute::Sensitive(true),
+        Attribute::Sign(true),
+        Attribute::Label(label.as_bytes().to_vec()),
+        Attribute::Id(vec![id]),
+        Attribute::Modulus(rsa.n().to_vec()),
+        Attribute::PublicExponent(rsa.e().to_vec()),
+        Attribute::PrivateExponent(rsa.d().to_vec()),
+        Attribute::Prime1(number(rsa.p())),
+        Attribute::Prime2(number(rsa.q())),
+        Attribute::Exponent1(number(rsa.dmp1())),
+        Attribute::Exponent2(number(rsa.dmq1())),
+        Attribute::Coefficient(number(rsa.iqmp())),
+    ])?;
+    Ok(())
+}
+
+fn open_signer(module: &Path) -> pdf_modify::Pkcs11SignerBuilder {
+    Pkcs11Signer::builder(module)
+        .token_label(TOKEN)
+        .key_label("signer")
+        .pin(USER_PIN)
+}
+
+fn sign(signer: &CmsSigner<Pkcs11Signer>) -> Result<Vec<u8>> {
+    let mut manager = PDFSignManager::load("files/hello_world.pdf")?;
+    manager.sign(SignerInfo::empty(), None::<ImageRect<&Path>>, signer)?;
+    let mut signed = Vec::new();
+    manager.save_to(&mut signed)?;
+    Ok(signed)
+}
+
+fn assert_valid(signed: Vec<u8>) -> Result<()> {
+    let results = PdfVerifier::from_bytes(signed)?.verify()?;
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].status, SignatureStatus::Valid);
+    Ok(())
+}
+
+#[test]
+fn sign_with_softhsm() -> Result<()> {
+    let Some(module) = softhsm_module() else {
+        eprintln!("SoftHSMv2 not found, skipping");
+        return Ok(());
+    };
+    let dir = std::env::temp_dir().join(format!("pdf_modify-softhsm-{}", std::process::id()));
+    init_token(&module, &dir)?;
+
+    // The certificate is read from the token through the CKA_ID of the key
+    let pkcs1 = CmsSigner::new(open_signer(&module).open()?);
+    let pss = CmsSigner::new(
+        open_signer(&module)
+            .scheme(SignatureScheme::RsaPss)
+            .open()?,
+    )
+    .with_sub_filter(SubFilter::CadesDetached);
+    assert_valid(sign(&pkcs1)?)?;
+    // Dropping a signer closes its own session only, the module stays initialized
+    drop(pkcs1);
+    assert_valid(sign(&pss)?)?;
+
+    // Without a key label the key is matched to the certificate
+    let cert = X509::from_pem(&std::fs::read("certs/mycert.crt")?)?;
+    let any_key = || {
+        Pkcs11Signer::builder(&module)
+            .token_label(TOKEN)
+            .pin(USER_PIN)
+    };
+    assert_valid(sign(&CmsSigner::new(any_key().open()?))?)?;
+    assert_valid(sign(&CmsSigner::new(
+        any_key().certificate(cert.clone()).open()?,
+    ))?)?;
+    let error = any_key()
+        .key_label("other")
+        .certificate(cert)
+        .open()
+        .err()
+        .expect("the other key is not the key of the certificate");
+    assert!(error.to_string().contains("is the key of"), "{error}");
+
+    std::fs::remove_dir_all(dir)?;
+    Ok(())
+}
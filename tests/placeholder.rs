@@ -0,0 +1,189 @@
+//! The `/Contents` placeholder: sized from an estimate, resized when the
+//! estimate is short, and a fixed size that is too small fails cleanly.
+mod common;
+
+use std::path::Path;
+
+use anyhow::Result;
+use common::{KeyKind, Pki, TestDir, fixture_signer, save, verify};
+use openssl::x509::X509;
+use pdf_modify::{
+    DigestAlgorithm, ImageRect, LocalTimestampAuthority, P12Signer, PDFSignManager, Sign,
+    SignerInfo, SubFilter, TimestampAuthority,
+};
+
+/// The slack added to every estimate and the room for a token of unknown
+/// size, `SIG_CONTENTS_MARGIN` and `TIMESTAMP_TOKEN_RESERVE`.
+const MARGIN: usize = 64;
+const TOKEN_RESERVE: usize = 8192;
+
+/// Estimates far fewer bytes than it signs.
+struct ShortEstimate(P12Signer);
+
+impl Sign for ShortEstimate {
+    fn sign(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.0.sign(data)
+    }
+
+    fn sub_filter(&self) -> SubFilter {
+        self.0.sub_filter()
+    }
+
+    fn certificates(&self) -> Vec<X509> {
+        self.0.certificates()
+    }
+
+    fn digest_algorithm(&self) -> DigestAlgorithm {
+        self.0.digest_algorithm()
+    }
+
+    fn estimated_len(&self) -> Result<usize> {
+        Ok(100)
+    }
+}
+
+fn sign(manager: &mut PDFSignManager, signer: &dyn Sign) -> Result<()> {
+    manager.sign(SignerInfo::empty(), None::<ImageRect<&Path>>, signer)
+}
+
+#[test]
+fn short_estimate_is_resized() {
+    let mut manager = PDFSignManager::load(common::HELLO_WORLD).unwrap();
+    sign(&mut manager, &ShortEstimate(fixture_signer())).unwrap();
+    sign(&mut manager, &ShortEstimate(fixture_signer())).unwrap();
+    let signed = save(&mut manager).unwrap();
+    let results = verify(&signed);
+    assert_eq!(results.len(), 2);
+    assert!(results.iter().all(|result| result.is_valid()));
+    assert_eq!(
+        PDFSignManager::load(common::TestDir::new("resized").write("signed.pdf", &signed))
+            .unwrap()
+            .revision_count(),
+        3
+    );
+}
+
+#[test]
+fn too_small_placeholder_leaves_no_trace() {
+    let input = std::fs::read(common::HELLO_WORLD).unwrap();
+    let mut manager = PDFSignManager::load(common::HELLO_WORLD).unwrap();
+    manager.set_placeholder_size(100);
+    let error = sign(&mut manager, &fixture_signer()).unwrap_err();
+    assert!(error.to_string().contains("were reserved"), "{error}");
+    // Nothing of the failed signature is saved
+    let unsigned = save(&mut manager).unwrap();
+    assert!(unsigned.starts_with(&input));
+    assert!(verify(&unsigned).is_empty());
+
+    manager.set_placeholder_size(8000);
+    sign(&mut manager, &fixture_signer()).unwrap();
+    let signed = save(&mut manager).unwrap();
+    let results = verify(&signed);
+    assert_eq!(results.len(), 1);
+    assert!(results[0].is_valid());
+    assert!(results[0].covers_whole_file);
+}
+
+#[test]
+fn failed_signature_keeps_the_previous_one() {
+    let mut manager = PDFSignManager::load(common::HELLO_WORLD).unwrap();
+    sign(&mut manager, &fixture_signer()).unwrap();
+    let signed = save(&mut manager).unwrap();
+    manager.set_placeholder_size(100);
+    assert!(sign(&mut manager, &fixture_signer()).is_err());
+    assert_eq!(save(&mut manager).unwrap(), signed);
+}
+
+/// A TSA that cannot tell the size of its tokens.
+struct UnknownTokenLen(LocalTimestampAuthority);
+
+impl TimestampAuthority for UnknownTokenLen {
+    fn timestamp(&self, data: &[u8]) -> Result<Vec<u8>> {
+        self.0.timestamp(data)
+    }
+}
+
+/// The bytes reserved for the only signature and the DER length of the signature.
+fn reserved_and_used(data: &[u8]) -> (usize, usize) {
+    let results = verify(data);
+    assert!(results[0].is_valid(), "{:?}", results[0].status);
+    let [_, len1, start2, _] = results[0].byte_range[..] else {
+        panic!("{:?}", results[0].byte_range);
+    };
+    let contents = hex::decode(&data[len1 + 1..start2 - 1]).unwrap();
+    // A SEQUENCE with a two byte length, as every signature here is
+    assert_eq!(contents[..2], [0x30, 0x82]);
+    let used = 4 + u16::from_be_bytes([contents[2], contents[3]]) as usize;
+    (contents.len(), used)
+}
+
+fn sign_with_tsa(signer: &dyn Sign, tsa: impl TimestampAuthority + 'static) -> Vec<u8> {
+    let mut manager = PDFSignManager::load(common::HELLO_WORLD).unwrap();
+    manager.set_timestamp_authority(tsa);
+    sign(&mut manager, signer).unwrap();
+    save(&mut manager).unwrap()
+}
+
+#[test]
+fn estimate_reserves_the_signature_and_the_margin() {
+    let signer = fixture_signer();
+    let signed = common::sign_file(Path::new(common::HELLO_WORLD), &signer).unwrap();
+    let (reserved, used) = reserved_and_used(&signed);
+    // Signed once in the estimated placeholder, not resized
+    assert_eq!(reserved, signer.estimated_len().unwrap() + MARGIN);
+    assert!(used <= reserved);
+    assert!(reserved - used < 2048, "{reserved} reserved for {used}");
+}
+
+#[test]
+fn estimate_covers_a_timestamp_token() {
+    let signer = fixture_signer().with_sub_filter(SubFilter::CadesDetached);
+    let tsa = LocalTimestampAuthority::generate().unwrap();
+    let token_len = tsa.estimated_token_len().unwrap();
+    let (reserved, used) = reserved_and_used(&sign_with_tsa(&signer, tsa));
+    assert_eq!(
+        reserved,
+        signer.estimated_len().unwrap() + token_len + MARGIN
+    );
+    assert!(used <= reserved);
+
+    let tsa = UnknownTokenLen(LocalTimestampAuthority::generate().unwrap());
+    let (reserved, used) = reserved_and_used(&sign_with_tsa(&signer, tsa));
+    assert_eq!(
+        reserved,
+        signer.estimated_len().unwrap() + TOKEN_RESERVE + MARGIN
+    );
+    assert!(used <= reserved);
+}
+
+#[test]
+fn estimate_covers_a_large_chain() {
+    let dir = TestDir::new("large-chain");
+    let pki = Pki::generate();
+    let (cert, key) = pki.issue("signer", KeyKind::Rsa);
+    let mut chain = pki.root.to_pem().unwrap();
+    for n in 0..8 {
+        chain.extend(
+            pki.issue(&format!("ca{n}"), KeyKind::Rsa)
+                .0
+                .to_pem()
+                .unwrap(),
+        );
+    }
+    let signer = pdf_modify::PemSigner::load(
+        dir.write("signer.crt", &cert.to_pem().unwrap()),
+        dir.write("signer.key", &key.private_key_to_pem_pkcs8().unwrap()),
+    )
+    .unwrap()
+    .with_chain_file(dir.write("chain.crt", &chain))
+    .unwrap()
+    .with_sub_filter(SubFilter::CadesDetached);
+    assert_eq!(signer.certificates().len(), 10);
+
+    let signed = common::sign_file(Path::new(common::HELLO_WORLD), &signer).unwrap();
+    let (reserved, used) = reserved_and_used(&signed);
+    // Larger than the fixed default placeholder, still signed once
+    assert!(used > 4096, "{used}");
+    assert_eq!(reserved, signer.estimated_len().unwrap() + MARGIN);
+    assert!(used <= reserved);
+}
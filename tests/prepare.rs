@@ -0,0 +1,86 @@
+//! Two-phase signing: write the placeholder, sign elsewhere, then complete.
+mod common;
+
+use std::path::Path;
+
+use common::{fixture_signer, verify};
+use pdf_modify::{
+    DigestAlgorithm, ImageRect, LocalTimestampAuthority, PDFSignManager, PreparedSignature, Sign,
+    SignatureStatus, SignerInfo, SubFilter,
+};
+
+fn prepare(sub_filter: SubFilter, digest_algorithm: DigestAlgorithm) -> PreparedSignature {
+    let mut manager = PDFSignManager::load(common::HELLO_WORLD).unwrap();
+    manager
+        .prepare(
+            SignerInfo::empty(),
+            None::<ImageRect<&Path>>,
+            sub_filter,
+            digest_algorithm,
+        )
+        .unwrap()
+}
+
+#[test]
+fn prepared_signature_completes_after_a_restore() {
+    let signer = fixture_signer()
+        .with_sub_filter(SubFilter::CadesDetached)
+        .with_digest(DigestAlgorithm::Sha384);
+    let prepared = prepare(SubFilter::CadesDetached, DigestAlgorithm::Sha384);
+    // The state survives being stored between the two phases
+    let stored = prepared.as_bytes().to_vec();
+    let prepared = PreparedSignature::from_bytes(stored, prepared.digest_algorithm()).unwrap();
+    assert_eq!(prepared.digest_algorithm(), DigestAlgorithm::Sha384);
+    assert_eq!(
+        prepared.digest(),
+        openssl::sha::sha384(&prepared.data_to_sign())
+    );
+
+    let cms = signer.sign(&prepared.data_to_sign()).unwrap();
+    let tsa = LocalTimestampAuthority::generate().unwrap();
+    let signed = prepared.complete(cms, Some(&tsa)).unwrap();
+    let results = verify(&signed);
+    assert_eq!(results.len(), 1);
+    assert!(results[0].is_valid());
+    assert!(results[0].covers_whole_file);
+    assert_eq!(
+        results[0].timestamp.as_ref().unwrap().status,
+        SignatureStatus::Valid
+    );
+}
+
+#[test]
+fn signature_of_other_data_is_rejected() {
+    let prepared = prepare(SubFilter::Pkcs7Detached, DigestAlgorithm::Sha256);
+    let cms = fixture_signer().sign(b"another document").unwrap();
+    let error = prepared.complete(cms, None).unwrap_err();
+    assert!(error.to_string().contains("signs other data"), "{error}");
+}
+
+#[test]
+fn signature_with_another_digest_is_rejected() {
+    let prepared = prepare(SubFilter::CadesDetached, DigestAlgorithm::Sha256);
+    let cms = fixture_signer()
+        .with_sub_filter(SubFilter::CadesDetached)
+        .with_digest(DigestAlgorithm::Sha384)
+        .sign(&prepared.data_to_sign())
+        .unwrap();
+    let error = prepared.complete(cms, None).unwrap_err();
+    assert!(error.to_string().contains("SHA256"), "{error}");
+}
+
+#[test]
+fn oversized_signature_is_rejected() {
+    let mut manager = PDFSignManager::load(common::HELLO_WORLD).unwrap();
+    manager.set_placeholder_size(64);
+    let prepared = manager
+        .prepare(
+            SignerInfo::empty(),
+            None::<ImageRect<&Path>>,
+            SubFilter::Pkcs7Detached,
+            DigestAlgorithm::Sha256,
+        )
+        .unwrap();
+    let cms = fixture_signer().sign(&prepared.data_to_sign()).unwrap();
+    assert!(prepared.complete(cms, None).is_err());
+}
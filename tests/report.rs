@@ -0,0 +1,252 @@
+//! The JSON report and the exit codes of `verify`, both of which CI gates on.
+mod common;
+
+use std::{ffi::OsStr, path::Path, process::Command};
+
+use common::{KeyKind, Pki, TestDir, append_revision, fixture_signer, save, sign_file};
+use lopdf::{Dictionary, Stream};
+use pdf_modify::{
+    DocMdpPermission, ImageRect, LocalTimestampAuthority, PDFSignManager, PdfVerifier, SignerInfo,
+    SubFilter,
+};
+use serde_json::{Value, json};
+
+/// The field names of a JSON object, sorted.
+fn keys(value: &Value) -> Vec<&str> {
+    let mut keys = value
+        .as_object()
+        .unwrap_or_else(|| panic!("not an object: {value}"))
+        .keys()
+        .map(String::as_str)
+        .collect::<Vec<_>>();
+    keys.sort();
+    keys
+}
+
+const CERTIFICATE_KEYS: [&str; 6] = [
+    "issuer",
+    "not_after",
+    "not_before",
+    "serial_number",
+    "sha256",
+    "subject",
+];
+
+#[test]
+fn report_fields_and_values() {
+    let dir = TestDir::new("report-json");
+    let pki = Pki::generate();
+    let signer = pki
+        .pem_signer(&dir, "signer", KeyKind::Rsa)
+        .with_sub_filter(SubFilter::CadesDetached);
+    let tsa = LocalTimestampAuthority::generate().unwrap();
+    let mut trust_store = pki.trust_store();
+    trust_store.add_anchor(tsa.certificate().clone()).unwrap();
+
+    let mut manager = PDFSignManager::load(common::HELLO_WORLD).unwrap();
+    manager.set_timestamp_authority(tsa);
+    manager
+        .sign(SignerInfo::empty(), None::<ImageRect<&Path>>, &signer)
+        .unwrap();
+    let report = PdfVerifier::from_bytes(save(&mut manager).unwrap())
+        .unwrap()
+        .with_trust_store(trust_store)
+        .report()
+        .unwrap();
+    let report = serde_json::to_value(report).unwrap();
+
+    assert_eq!(
+        keys(&report),
+        ["doc_mdp_permission", "signatures", "verdict"]
+    );
+    assert_eq!(report["verdict"], "valid");
+    assert_eq!(report["doc_mdp_permission"], Value::Null);
+    let signature = &report["signatures"][0];
+    assert_eq!(
+        keys(signature),
+        [
+            "byte_range",
+            "chain",
+            "covers_whole_file",
+            "field_name",
+            "integrity",
+            "modifications",
+            "revision",
+            "revocation",
+            "signer",
+            "signing_time",
+            "sub_filter",
+            "timestamp",
+            "trust",
+            "validation_time",
+        ]
+    );
+    assert_eq!(signature["revision"], 2);
+    assert_eq!(signature["covers_whole_file"], true);
+    assert_eq!(signature["sub_filter"], "ETSI.CAdES.detached");
+    assert_eq!(signature["byte_range"].as_array().unwrap().len(), 4);
+    assert_eq!(
+        signature["integrity"],
+        json!({ "status": "valid", "error": null })
+    );
+    assert_eq!(
+        signature["trust"],
+        json!({ "trusted": true, "reason": null })
+    );
+    assert_eq!(signature["revocation"], json!([]));
+    assert_eq!(signature["modifications"], json!([]));
+
+    let signer = &signature["signer"];
+    assert_eq!(keys(signer), CERTIFICATE_KEYS);
+    assert!(signer["subject"].as_str().unwrap().contains("CN=signer"));
+    let sha256 = signer["sha256"].as_str().unwrap();
+    assert_eq!(sha256.len(), 64);
+    assert_eq!(sha256, sha256.to_uppercase());
+    // The signer and the root
+    let chain = signature["chain"].as_array().unwrap();
+    assert_eq!(chain.len(), 2);
+    assert_eq!(chain[0], *signer);
+
+    // The signature is validated at the time of its timestamp
+    let timestamp = &signature["timestamp"];
+    assert_eq!(keys(timestamp), ["integrity", "time", "trust", "tsa"]);
+    assert_eq!(timestamp["integrity"]["status"], "valid");
+    assert_eq!(timestamp["trust"]["trusted"], true);
+    assert_eq!(keys(&timestamp["tsa"]), CERTIFICATE_KEYS);
+    let time = timestamp["time"].as_str().unwrap();
+    chrono::DateTime::parse_from_rfc3339(time).unwrap();
+    assert_eq!(signature["validation_time"], time);
+}
+
+/// Replace the first content stream of the first page in a new revision.
+fn rewrite_page(data: &[u8]) -> Vec<u8> {
+    append_revision(data, |doc| {
+        let prev = doc.get_prev_documents();
+        let page = prev.page_iter().next().unwrap();
+        let content_id = prev.get_page_contents(page)[0];
+        doc.new_document.set_object(
+            content_id,
+            Stream::new(Dictionary::new(), b"BT ET".to_vec()),
+        );
+    })
+}
+
+#[test]
+fn report_modifications_and_permission() {
+    let mut manager = PDFSignManager::load(common::HELLO_WORLD).unwrap();
+    manager
+        .certify(
+            DocMdpPermission::FormFilling,
+            SignerInfo::empty(),
+            None::<ImageRect<&Path>>,
+            &fixture_signer(),
+        )
+        .unwrap();
+    let attacked = rewrite_page(&save(&mut manager).unwrap());
+    let report = PdfVerifier::from_bytes(attacked).unwrap().report().unwrap();
+    let report = serde_json::to_value(report).unwrap();
+
+    assert_eq!(report["verdict"], "modified");
+    assert_eq!(report["doc_mdp_permission"], 2);
+    let signature = &report["signatures"][0];
+    assert_eq!(signature["covers_whole_file"], false);
+    // No trust store was given
+    assert_eq!(signature["trust"], Value::Null);
+    assert_eq!(signature["chain"], json!([]));
+    let modification = &signature["modifications"][0];
+    assert_eq!(
+        keys(modification),
+        ["allowed", "change", "kind", "object", "revision"]
+    );
+    assert_eq!(modification["revision"], 3);
+    assert_eq!(modification["change"], "modified");
+    assert_eq!(modification["kind"], "page_content");
+    assert_eq!(modification["allowed"], false);
+    let object = modification["object"].as_array().unwrap();
+    assert_eq!(object.len(), 2);
+    assert_eq!(object[1], 0);
+}
+
+#[test]
+fn unsigned_report() {
+    let report = PdfVerifier::load(common::HELLO_WORLD)
+        .unwrap()
+        .report()
+        .unwrap();
+    assert_eq!(
+        serde_json::to_value(report).unwrap(),
+        json!({ "verdict": "unsigned", "doc_mdp_permission": null, "signatures": [] })
+    );
+}
+
+type Arg<'a> = &'a dyn AsRef<OsStr>;
+
+/// Run `pdf_modify verify` and return its exit code and its standard output.
+fn verify_cli(args: &[Arg]) -> (i32, String) {
+    let output = Command::new(env!("CARGO_BIN_EXE_pdf_modify"))
+        .arg("verify")
+        .args(args)
+        .output()
+        .unwrap();
+    (
+        output.status.code().unwrap(),
+        String::from_utf8(output.stdout).unwrap(),
+    )
+}
+
+#[test]
+fn exit_codes() {
+    let dir = TestDir::new("report-exit");
+    let pki = Pki::generate();
+    let trust = dir.write("root.crt", &pki.root.to_pem().unwrap());
+    let signer = pki.pem_signer(&dir, "signer", KeyKind::EcP256);
+    let signed = sign_file(Path::new(common::HELLO_WORLD), &signer).unwrap();
+    let mut tampered = signed.clone();
+    let at = tampered
+        .windows(5)
+        .position(|window| window == b"Hello")
+        .unwrap();
+    tampered[at] = b'J';
+
+    let other_root = dir.write("other.crt", &Pki::generate().root.to_pem().unwrap());
+
+    let valid = dir.write("valid.pdf", &signed);
+    let cases: [(&str, &[Arg], i32, &str); 7] = [
+        ("valid", &[&"-i", &valid, &"--trust", &trust], 0, "valid"),
+        (
+            "invalid",
+            &[&"-i", &dir.write("tampered.pdf", &tampered)],
+            3,
+            "invalid",
+        ),
+        (
+            "untrusted",
+            &[
+                &"-i",
+                &dir.write("untrusted.pdf", &signed),
+                &"--trust",
+                &other_root,
+            ],
+            4,
+            "untrusted",
+        ),
+        (
+            "modified",
+            &[&"-i", &dir.write("modified.pdf", &rewrite_page(&signed))],
+            5,
+            "modified",
+        ),
+        ("unsigned", &[&"-i", &common::HELLO_WORLD], 6, "unsigned"),
+        ("error", &[&"-i", &dir.path("missing.pdf")], 1, ""),
+        ("usage", &[&"--no-such-flag"], 2, ""),
+    ];
+    for (case, args, code, verdict) in cases {
+        let (actual, stdout) = verify_cli(args);
+        assert_eq!(actual, code, "{case}: {stdout}");
+        if !verdict.is_empty() {
+            let (_, json) = verify_cli(&[args, &[&"--format", &"json"]].concat());
+            let report: Value = serde_json::from_str(&json).unwrap();
+            assert_eq!(report["verdict"], verdict, "{case}");
+        }
+    }
+}
@@ -0,0 +1,63 @@
+//! Extract the revision a signer saw, or any revision, as a standalone PDF.
+mod common;
+
+use std::path::Path;
+
+use common::{fixture_signer, save, verify};
+use pdf_modify::{ImageRect, PDFSignManager, PdfVerifier, SignerInfo};
+
+fn signed_twice() -> Vec<u8> {
+    let mut manager = PDFSignManager::load(common::HELLO_WORLD).unwrap();
+    let signer = fixture_signer();
+    for _ in 0..2 {
+        manager
+            .sign(SignerInfo::empty(), None::<ImageRect<&Path>>, &signer)
+            .unwrap();
+    }
+    save(&mut manager).unwrap()
+}
+
+/// Whether `revision` is `prefix` followed by at most an end-of-line.
+fn is_eol_extension(revision: &[u8], prefix: &[u8]) -> bool {
+    revision
+        .strip_prefix(prefix)
+        .is_some_and(|rest| matches!(rest, b"" | b"\n" | b"\r\n" | b"\r"))
+}
+
+#[test]
+fn signed_revision_is_what_the_signer_saw() {
+    let data = signed_twice();
+    let results = verify(&data);
+    let first = results[0].field_name.clone().unwrap();
+    let verifier = PdfVerifier::from_bytes(data.clone()).unwrap();
+    assert_eq!(verifier.revision_count(), 3);
+
+    let revision = verifier.signed_revision(&first).unwrap();
+    // The ByteRange ends at the %%EOF, the revision with its end-of-line
+    assert!(is_eol_extension(verifier.revision(2).unwrap(), revision));
+    // Extracted alone, the revision holds only the first signature, unmodified
+    let results = verify(revision);
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].field_name.as_deref(), Some(first.as_str()));
+    assert!(results[0].is_valid());
+    assert!(results[0].covers_whole_file);
+    assert!(results[0].modifications.is_empty());
+}
+
+#[test]
+fn revisions_are_standalone_documents() {
+    let data = signed_twice();
+    let dir = common::TestDir::new("revisions");
+    let path = dir.write("signed.pdf", &data);
+    let manager = PDFSignManager::load(&path).unwrap();
+    assert_eq!(manager.revision_count(), 3);
+    assert_eq!(manager.revision(3).unwrap(), data.as_slice());
+
+    let original = manager.revision(1).unwrap();
+    let input = std::fs::read(common::HELLO_WORLD).unwrap();
+    assert!(is_eol_extension(original, &input));
+    assert!(verify(original).is_empty());
+    assert!(manager.revision(0).is_err());
+    assert!(manager.revision(4).is_err());
+    assert!(manager.signed_revision("missing").is_err());
+}
@@ -0,0 +1,287 @@
+//! Revocation from CRLs and OCSP responses: given to the verifier, embedded in
+//! the `/DSS`, and checked at the time of a signature timestamp.
+mod common;
+
+use std::path::Path;
+
+use common::{KeyKind, OcspStatus, Pki, TestDir, now, save};
+use lopdf::{Dictionary, Document, Object};
+use openssl::x509::X509;
+use pdf_modify::{
+    ImageRect, LocalTimestampAuthority, PDFSignManager, PdfVerifier, RevocationSource,
+    RevocationStatus, Sign, SignatureVerification, SignerInfo, SubFilter, ValidationData,
+};
+
+const HOUR: i64 = 60 * 60;
+const DAY: i64 = 24 * HOUR;
+
+struct Signed {
+    pki: Pki,
+    signer: X509,
+    data: Vec<u8>,
+    _dir: TestDir,
+}
+
+fn sign(tsa: Option<LocalTimestampAuthority>) -> (Signed, Option<X509>) {
+    let dir = TestDir::new("revocation");
+    let pki = Pki::generate();
+    let signer = pki
+        .pem_signer(&dir, "signer", KeyKind::Rsa)
+        .with_sub_filter(SubFilter::CadesDetached);
+    let mut manager = PDFSignManager::load(common::HELLO_WORLD).unwrap();
+    let tsa_cert = tsa.as_ref().map(|tsa| tsa.certificate().clone());
+    if let Some(tsa) = tsa {
+        manager.set_timestamp_authority(tsa);
+    }
+    manager
+        .sign(SignerInfo::empty(), None::<ImageRect<&Path>>, &signer)
+        .unwrap();
+    let signed = Signed {
+        signer: signer.certificates().remove(0),
+        data: save(&mut manager).unwrap(),
+        pki,
+        _dir: dir,
+    };
+    (signed, tsa_cert)
+}
+
+fn check(signed: &Signed, data: &[u8], validation: ValidationData) -> SignatureVerification {
+    PdfVerifier::from_bytes(data.to_vec())
+        .unwrap()
+        .with_trust_store(signed.pki.trust_store())
+        .with_revocation_check(validation, None)
+        .verify()
+        .unwrap()
+        .remove(0)
+}
+
+fn with_crl(crl: Vec<u8>) -> ValidationData {
+    let mut data = ValidationData::new();
+    data.add_crl(crl).unwrap();
+    data
+}
+
+fn with_ocsp(ocsp: Vec<u8>) -> ValidationData {
+    let mut data = ValidationData::new();
+    data.add_ocsp(ocsp).unwrap();
+    data
+}
+
+fn signer_status(result: &SignatureVerification) -> (RevocationStatus, Option<RevocationSource>) {
+    assert_eq!(result.revocation.len(), 1, "{:?}", result.revocation);
+    (
+        result.revocation[0].status.clone(),
+        result.revocation[0].source,
+    )
+}
+
+#[test]
+fn crl_answers() {
+    let (signed, _) = sign(None);
+    let good = signed.pki.crl(now() - HOUR, Some(now() + DAY), &[]);
+    let result = check(&signed, &signed.data, with_crl(good));
+    assert_eq!(
+        signer_status(&result),
+        (RevocationStatus::Good, Some(RevocationSource::Crl))
+    );
+    assert!(!result.is_revoked());
+
+    let revoked_at = now() - 60;
+    let revoked = signed.pki.crl(
+        now() - HOUR,
+        Some(now() + DAY),
+        &[(&signed.signer, revoked_at)],
+    );
+    let result = check(&signed, &signed.data, with_crl(revoked));
+    let (status, source) = signer_status(&result);
+    assert_eq!(source, Some(RevocationSource::Crl));
+    let RevocationStatus::Revoked { reason, time } = status else {
+        panic!("{status:?}");
+    };
+    assert_eq!(reason.as_deref(), Some("keyCompromise"));
+    assert_eq!(
+        time,
+        Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(revoked_at as u64))
+    );
+    assert!(result.is_revoked());
+
+    // A CRL of another CA says nothing about the signer
+    let other = Pki::generate().crl(now() - HOUR, Some(now() + DAY), &[]);
+    let result = check(&signed, &signed.data, with_crl(other));
+    assert_eq!(signer_status(&result), (RevocationStatus::Unknown, None));
+}
+
+#[test]
+fn ocsp_answers() {
+    let (signed, _) = sign(None);
+    let ocsp = |status| {
+        let response = signed
+            .pki
+            .ocsp(&signed.signer, status, now() - HOUR, Some(now() + DAY));
+        check(&signed, &signed.data, with_ocsp(response))
+    };
+
+    let result = ocsp(OcspStatus::Good);
+    assert_eq!(
+        signer_status(&result),
+        (RevocationStatus::Good, Some(RevocationSource::Ocsp))
+    );
+
+    let result = ocsp(OcspStatus::Revoked(now() - 60));
+    let (status, source) = signer_status(&result);
+    assert_eq!(source, Some(RevocationSource::Ocsp));
+    assert!(
+        matches!(&status, RevocationStatus::Revoked { reason: Some(reason), .. } if reason == "keyCompromise"),
+        "{status:?}"
+    );
+    assert!(result.is_revoked());
+
+    let result = ocsp(OcspStatus::Unknown);
+    assert_eq!(signer_status(&result), (RevocationStatus::Unknown, None));
+}
+
+#[test]
+fn stale_answers_are_unknown() {
+    let (signed, _) = sign(None);
+    let expired = signed.pki.crl(now() - 3 * DAY, Some(now() - DAY), &[]);
+    let result = check(&signed, &signed.data, with_crl(expired));
+    assert_eq!(signer_status(&result), (RevocationStatus::Unknown, None));
+
+    // Without a nextUpdate an answer only stays current for a day
+    let old = signed.pki.crl(now() - 2 * DAY, None, &[]);
+    let result = check(&signed, &signed.data, with_crl(old));
+    assert_eq!(signer_status(&result), (RevocationStatus::Unknown, None));
+    let recent = signed
+        .pki
+        .ocsp(&signed.signer, OcspStatus::Good, now() - HOUR, None);
+    let result = check(&signed, &signed.data, with_ocsp(recent));
+    assert_eq!(signer_status(&result).0, RevocationStatus::Good);
+}
+
+#[test]
+fn dss_answers_offline() {
+    let (signed, _) = sign(None);
+    let dir = TestDir::new("revocation-dss");
+    let path = dir.write("signed.pdf", &signed.data);
+    let mut manager = PDFSignManager::load(path).unwrap();
+    let crl = signed.pki.crl(now() - HOUR, Some(now() + DAY), &[]);
+    manager.add_dss(with_crl(crl), None).unwrap();
+    let data = save(&mut manager).unwrap();
+
+    // Nothing but the document answers
+    let result = check(&signed, &data, ValidationData::new());
+    assert_eq!(
+        signer_status(&result),
+        (RevocationStatus::Good, Some(RevocationSource::Crl))
+    );
+    assert_eq!(result.suspicious_modifications().count(), 0);
+}
+
+/// The `/DSS` of the catalog and the `/VRI` entry of the only signature.
+fn dss_and_vri(data: &[u8]) -> (Dictionary, Dictionary) {
+    let doc = Document::load_mem(data).unwrap();
+    let deref = |obj: &Object| match obj {
+        Object::Reference(id) => doc.get_dictionary(*id).unwrap().clone(),
+        obj => obj.as_dict().unwrap().clone(),
+    };
+    let dss = deref(doc.catalog().unwrap().get(b"DSS").unwrap());
+    let contents = doc
+        .objects
+        .values()
+        .filter_map(|obj| obj.as_dict().ok())
+        .find(|dict| matches!(dict.get(b"Type").and_then(Object::as_name), Ok(b"Sig")))
+        .and_then(|sig| sig.get(b"Contents").and_then(Object::as_str).ok())
+        .unwrap();
+    let key = hex::encode_upper(openssl::sha::sha1(contents));
+    let vri = deref(dss.get(b"VRI").unwrap());
+    assert_eq!(vri.len(), 1);
+    let entry = deref(vri.get(key.as_bytes()).unwrap());
+    (dss, entry)
+}
+
+fn array_len(dict: &Dictionary, key: &[u8]) -> usize {
+    dict.get(key).and_then(Object::as_array).map_or(0, Vec::len)
+}
+
+#[test]
+fn dss_and_vri_round_trip() {
+    let (signed, _) = sign(None);
+    let dir = TestDir::new("revocation-vri");
+    let path = dir.write("signed.pdf", &signed.data);
+    let ocsp = signed.pki.ocsp(
+        &signed.signer,
+        OcspStatus::Good,
+        now() - HOUR,
+        Some(now() + DAY),
+    );
+    let add_dss = || {
+        let mut manager = PDFSignManager::load(&path).unwrap();
+        let missing = manager.add_dss(with_ocsp(ocsp.clone()), None).unwrap();
+        assert!(missing.is_empty(), "{missing:?}");
+        save(&mut manager).unwrap()
+    };
+    let data = add_dss();
+    // Nothing in the revision depends on when it was written
+    assert_eq!(add_dss(), data);
+
+    let (dss, vri) = dss_and_vri(&data);
+    // The signer and the root, the OCSP response of the signer
+    assert_eq!(array_len(&dss, b"Certs"), 2);
+    assert_eq!(array_len(&dss, b"OCSPs"), 1);
+    assert_eq!(array_len(&dss, b"CRLs"), 0);
+    assert_eq!(array_len(&vri, b"Cert"), 2);
+    assert_eq!(array_len(&vri, b"OCSP"), 1);
+    assert!(!vri.has(b"CRL"));
+    assert!(!vri.has(b"TU"));
+
+    // Verified offline, the document alone answers
+    let result = check(&signed, &data, ValidationData::new());
+    assert!(result.is_trusted(), "{:?}", result.trust);
+    assert_eq!(
+        signer_status(&result),
+        (RevocationStatus::Good, Some(RevocationSource::Ocsp))
+    );
+    assert_eq!(result.suspicious_modifications().count(), 0);
+}
+
+#[test]
+fn timestamped_signature_uses_later_answers() {
+    let tsa = LocalTimestampAuthority::generate().unwrap();
+    let (signed, tsa_cert) = sign(Some(tsa));
+    let verify = |validation| {
+        let mut trust = signed.pki.trust_store();
+        trust.add_anchor(tsa_cert.clone().unwrap()).unwrap();
+        PdfVerifier::from_bytes(signed.data.clone())
+            .unwrap()
+            .with_trust_store(trust)
+            .with_revocation_check(validation, None)
+            .verify()
+            .unwrap()
+            .remove(0)
+    };
+
+    // Issued after the timestamp, the CRL tells the status at its time
+    let later = signed.pki.crl(now() + 2, Some(now() + DAY), &[]);
+    let result = verify(with_crl(later));
+    assert!(result.validation_time.is_some());
+    assert_eq!(
+        signer_status(&result),
+        (RevocationStatus::Good, Some(RevocationSource::Crl))
+    );
+
+    // A revocation after the timestamp does not affect the signature
+    let revoked = signed
+        .pki
+        .crl(now() + 2, Some(now() + DAY), &[(&signed.signer, now() + 1)]);
+    let result = verify(with_crl(revoked));
+    assert!(matches!(
+        signer_status(&result).0,
+        RevocationStatus::Revoked { .. }
+    ));
+    assert!(!result.is_revoked());
+
+    // Expired before the timestamp, the CRL says nothing about it
+    let expired = signed.pki.crl(now() - 3 * DAY, Some(now() - DAY), &[]);
+    let result = verify(with_crl(expired));
+    assert_eq!(signer_status(&result), (RevocationStatus::Unknown, None));
+}
@@ -0,0 +1,95 @@
+//! PAdES B-T: a signature timestamp from a TSA over the signature value.
+mod common;
+
+use std::path::Path;
+
+use common::{KeyKind, Pki, TestDir, save};
+use pdf_modify::{
+    ImageRect, LocalTimestampAuthority, PDFSignManager, PdfVerifier, SignatureStatus, SignerInfo,
+    SubFilter, TimestampAuthority, TrustStore,
+};
+
+#[test]
+fn signature_timestamp_is_verified() {
+    let dir = TestDir::new("b-t");
+    let pki = Pki::generate();
+    let signer = pki
+        .pem_signer(&dir, "signer", KeyKind::Rsa)
+        .with_sub_filter(SubFilter::CadesDetached);
+    let tsa = LocalTimestampAuthority::generate().unwrap();
+    let tsa_cert = tsa.certificate().clone();
+
+    let mut manager = PDFSignManager::load(common::HELLO_WORLD).unwrap();
+    manager.set_timestamp_authority(tsa);
+    manager
+        .sign(SignerInfo::empty(), None::<ImageRect<&Path>>, &signer)
+        .unwrap();
+    let signed = save(&mut manager).unwrap();
+
+    let mut trust = pki.trust_store();
+    trust.add_anchor(tsa_cert).unwrap();
+    let results = PdfVerifier::from_bytes(signed)
+        .unwrap()
+        .with_trust_store(trust)
+        .verify()
+        .unwrap();
+    let result = &results[0];
+    assert!(result.is_valid());
+    assert!(result.is_trusted());
+    let timestamp = result.timestamp.as_ref().expect("signature timestamp");
+    assert!(timestamp.is_valid());
+    assert_eq!(
+        timestamp.tsa_subject().as_deref(),
+        Some("CN=pdf_modify local TSA")
+    );
+    // The certificates are validated at the time of the token, not now
+    assert_eq!(result.validation_time, timestamp.time);
+}
+
+#[test]
+fn timestamp_of_another_signature_is_rejected() {
+    /// Timestamps a fixed value instead of the signature it is asked for.
+    struct WrongImprint(LocalTimestampAuthority);
+
+    impl TimestampAuthority for WrongImprint {
+        fn timestamp(&self, _data: &[u8]) -> anyhow::Result<Vec<u8>> {
+            self.0.timestamp(b"another signature")
+        }
+    }
+
+    let signer = common::fixture_signer().with_sub_filter(SubFilter::CadesDetached);
+    let mut manager = PDFSignManager::load(common::HELLO_WORLD).unwrap();
+    manager.set_timestamp_authority(WrongImprint(LocalTimestampAuthority::generate().unwrap()));
+    manager
+        .sign(SignerInfo::empty(), None::<ImageRect<&Path>>, &signer)
+        .unwrap();
+    let signed = save(&mut manager).unwrap();
+
+    let results = common::verify(&signed);
+    // The signature itself is intact, its timestamp does not cover it
+    assert!(results[0].is_valid());
+    let timestamp = results[0].timestamp.as_ref().unwrap();
+    assert_eq!(timestamp.status, SignatureStatus::DigestMismatch);
+    assert!(!timestamp.is_valid());
+    assert_eq!(results[0].validation_time, None);
+}
+
+#[test]
+fn untrusted_tsa_is_reported() {
+    let signer = common::fixture_signer().with_sub_filter(SubFilter::CadesDetached);
+    let mut manager = PDFSignManager::load(common::HELLO_WORLD).unwrap();
+    manager.set_timestamp_authority(LocalTimestampAuthority::generate().unwrap());
+    manager
+        .sign(SignerInfo::empty(), None::<ImageRect<&Path>>, &signer)
+        .unwrap();
+    let signed = save(&mut manager).unwrap();
+
+    let results = PdfVerifier::from_bytes(signed)
+        .unwrap()
+        .with_trust_store(TrustStore::new())
+        .verify()
+        .unwrap();
+    let timestamp = results[0].timestamp.as_ref().unwrap();
+    assert_eq!(timestamp.status, SignatureStatus::Valid);
+    assert!(!timestamp.is_valid());
+}
@@ -0,0 +1,172 @@
+//! Build the signer certificate path to a trust anchor, through an
+//! intermediate CA, at the validation time.
+mod common;
+
+use std::path::Path;
+
+use common::{KeyKind, Pki, TestDir, now, sign_file};
+use openssl::x509::X509;
+use pdf_modify::{PdfVerifier, PemSigner, SignatureVerification, TrustStatus, TrustStore};
+
+const DAY: i64 = 24 * 60 * 60;
+
+/// A root, an intermediate CA and a signer it issued.
+struct Chain {
+    root: Pki,
+    intermediate: Pki,
+    dir: TestDir,
+}
+
+impl Chain {
+    fn generate(name: &str) -> Self {
+        let root = Pki::generate();
+        let intermediate = root.intermediate("pdf_modify test intermediate");
+        Chain {
+            root,
+            intermediate,
+            dir: TestDir::new(name),
+        }
+    }
+
+    /// Sign with a certificate of the intermediate CA valid between the Unix
+    /// times `not_before` and `not_after`, embedding `chain`.
+    fn sign(&self, not_before: i64, not_after: i64, chain: &[&X509]) -> Vec<u8> {
+        let (cert, key) =
+            self.intermediate
+                .issue_valid("signer", KeyKind::EcP256, not_before, not_after);
+        let mut signer = PemSigner::load(
+            self.dir.write("signer.crt", &cert.to_pem().unwrap()),
+            self.dir
+                .write("signer.key", &key.private_key_to_pem_pkcs8().unwrap()),
+        )
+        .unwrap();
+        if !chain.is_empty() {
+            let pem = chain
+                .iter()
+                .flat_map(|cert| cert.to_pem().unwrap())
+                .collect::<Vec<_>>();
+            signer = signer
+                .with_chain_file(self.dir.write("chain.crt", &pem))
+                .unwrap();
+        }
+        sign_file(Path::new(common::HELLO_WORLD), &signer).unwrap()
+    }
+
+    fn full(&self) -> Vec<u8> {
+        self.sign(
+            now() - DAY,
+            now() + DAY,
+            &[&self.intermediate.root, &self.root.root],
+        )
+    }
+}
+
+fn verify(data: Vec<u8>, anchors: &[&X509]) -> SignatureVerification {
+    let mut trust_store = TrustStore::new();
+    for anchor in anchors {
+        trust_store.add_anchor((*anchor).clone()).unwrap();
+    }
+    let result = PdfVerifier::from_bytes(data)
+        .unwrap()
+        .with_trust_store(trust_store)
+        .verify()
+        .unwrap()
+        .remove(0);
+    assert!(result.is_valid(), "{:?}", result.status);
+    result
+}
+
+fn subjects(result: &SignatureVerification) -> Vec<String> {
+    result
+        .chain
+        .iter()
+        .map(|cert| {
+            let cn = cert
+                .subject_name()
+                .entries_by_nid(openssl::nid::Nid::COMMONNAME)
+                .next()
+                .unwrap();
+            cn.data().to_string().unwrap()
+        })
+        .collect()
+}
+
+fn untrusted_reason(result: &SignatureVerification) -> &str {
+    match &result.trust {
+        Some(TrustStatus::Untrusted(reason)) => reason,
+        trust => panic!("{trust:?}"),
+    }
+}
+
+#[test]
+fn path_through_the_intermediate_is_trusted() {
+    let chain = Chain::generate("trust-full");
+    let result = verify(chain.full(), &[&chain.root.root]);
+    assert_eq!(result.trust, Some(TrustStatus::Trusted));
+    assert!(result.is_trusted());
+    assert_eq!(
+        subjects(&result),
+        [
+            "signer",
+            "pdf_modify test intermediate",
+            "pdf_modify test root"
+        ]
+    );
+}
+
+#[test]
+fn other_anchor_is_untrusted() {
+    let chain = Chain::generate("trust-other");
+    // A root of the same name, but another key
+    let result = verify(chain.full(), &[&Pki::generate().root]);
+    assert!(!result.is_trusted());
+    assert!(
+        untrusted_reason(&result).contains("signature failure"),
+        "{:?}",
+        result.trust
+    );
+    assert!(result.chain.is_empty());
+
+    // The root cannot be reached without the intermediate in the signature
+    let signed = chain.sign(now() - DAY, now() + DAY, &[]);
+    let result = verify(signed, &[&chain.root.root]);
+    assert!(
+        untrusted_reason(&result).contains("local issuer"),
+        "{:?}",
+        result.trust
+    );
+}
+
+#[test]
+fn intermediate_anchor_ends_the_path() {
+    let chain = Chain::generate("trust-partial");
+    let result = verify(chain.full(), &[&chain.intermediate.root]);
+    assert_eq!(result.trust, Some(TrustStatus::Trusted));
+    assert_eq!(
+        subjects(&result),
+        ["signer", "pdf_modify test intermediate"]
+    );
+}
+
+#[test]
+fn certificate_expired_at_the_validation_time_is_untrusted() {
+    let chain = Chain::generate("trust-expired");
+    let anchors = [&chain.root.root];
+    let ca = [&chain.intermediate.root, &chain.root.root];
+    let expired = chain.sign(now() - 10 * DAY, now() - DAY, &ca);
+    let result = verify(expired, &anchors);
+    assert!(result.validation_time.is_none());
+    assert!(
+        untrusted_reason(&result).contains("expired"),
+        "{:?}",
+        result.trust
+    );
+
+    let not_yet_valid = chain.sign(now() + DAY, now() + 10 * DAY, &ca);
+    let result = verify(not_yet_valid, &anchors);
+    assert!(
+        untrusted_reason(&result).contains("not yet valid"),
+        "{:?}",
+        result.trust
+    );
+}
@@ -0,0 +1,183 @@
+//! Sign with every SubFilter, key type and scheme, then verify natively, and
+//! reject ByteRanges that do not cover the whole file but the `/Contents`.
+mod common;
+
+use common::{KeyKind, Pki, TestDir, save, sign_file, verify};
+use pdf_modify::{
+    DigestAlgorithm, LocalTimestampAuthority, PDFSignManager, PdfVerifier, SignatureScheme,
+    SignatureStatus, SubFilter,
+};
+
+#[test]
+fn every_sub_filter_and_scheme_verifies() {
+    let dir = TestDir::new("schemes");
+    let pki = Pki::generate();
+    let input = std::path::Path::new(common::HELLO_WORLD);
+    let cases = [
+        (KeyKind::Rsa, None, None),
+        (
+            KeyKind::Rsa,
+            Some(SignatureScheme::RsaPkcs1v15),
+            Some(DigestAlgorithm::Sha384),
+        ),
+        (
+            KeyKind::Rsa,
+            Some(SignatureScheme::RsaPss),
+            Some(DigestAlgorithm::Sha256),
+        ),
+        (
+            KeyKind::Rsa,
+            Some(SignatureScheme::RsaPss),
+            Some(DigestAlgorithm::Sha512),
+        ),
+        (KeyKind::EcP256, None, None),
+        (KeyKind::EcP256, None, Some(DigestAlgorithm::Sha384)),
+        (KeyKind::Ed25519, None, None),
+    ];
+    for (index, (kind, scheme, digest)) in cases.into_iter().enumerate() {
+        for sub_filter in [SubFilter::Pkcs7Detached, SubFilter::CadesDetached] {
+            let mut signer = pki
+                .pem_signer(&dir, &format!("signer{index}"), kind)
+                .with_sub_filter(sub_filter);
+            if let Some(scheme) = scheme {
+                signer = signer.with_scheme(scheme);
+            }
+            if let Some(digest) = digest {
+                signer = signer.with_digest(digest);
+            }
+            let signed = sign_file(input, &signer).unwrap();
+            let results = PdfVerifier::from_bytes(signed)
+                .unwrap()
+                .with_trust_store(pki.trust_store())
+                .verify()
+                .unwrap();
+            let case = format!("{kind:?} {scheme:?} {digest:?} {sub_filter:?}");
+            assert_eq!(results.len(), 1, "{case}");
+            assert_eq!(results[0].status, SignatureStatus::Valid, "{case}");
+            assert!(results[0].is_trusted(), "{case}: {:?}", results[0].trust);
+            assert!(results[0].covers_whole_file, "{case}");
+            assert_eq!(
+                results[0].sub_filter.as_deref(),
+                Some(match sub_filter {
+                    SubFilter::Pkcs7Detached => "adbe.pkcs7.detached",
+                    _ => "ETSI.CAdES.detached",
+                })
+            );
+        }
+    }
+}
+
+#[test]
+fn fixture_certificate_signs_and_verifies() {
+    let signed = sign_file(
+        std::path::Path::new(common::HELLO_WORLD),
+        &common::fixture_signer(),
+    )
+    .unwrap();
+    let results = verify(&signed);
+    assert!(results[0].is_valid());
+    assert!(
+        results[0]
+            .signer_subject()
+            .unwrap()
+            .ends_with("CN=example.com")
+    );
+}
+
+#[test]
+fn document_timestamp_verifies() {
+    let mut manager = PDFSignManager::load(common::HELLO_WORLD).unwrap();
+    let tsa = LocalTimestampAuthority::generate().unwrap();
+    manager.timestamp(&tsa).unwrap();
+    let results = verify(&save(&mut manager).unwrap());
+    assert_eq!(results.len(), 1);
+    assert!(results[0].is_document_timestamp());
+    assert!(results[0].is_valid());
+    assert!(results[0].signing_time.is_some());
+}
+
+fn signed_fixture() -> Vec<u8> {
+    sign_file(
+        std::path::Path::new(common::HELLO_WORLD),
+        &common::fixture_signer(),
+    )
+    .unwrap()
+}
+
+/// Replace the ByteRange of the only signature, padded to keep every offset.
+fn rewrite_byte_range(data: &[u8], byte_range: impl Fn([usize; 4]) -> [usize; 4]) -> Vec<u8> {
+    let start = find(data, b"/ByteRange") + b"/ByteRange".len();
+    let open = start + find(&data[start..], b"[");
+    let close = open + find(&data[open..], b"]");
+    let current = std::str::from_utf8(&data[open + 1..close])
+        .unwrap()
+        .split_whitespace()
+        .map(|n| n.parse().unwrap())
+        .collect::<Vec<usize>>();
+    let [a, b, c, d] = byte_range(current.try_into().unwrap());
+    let width = close - open - 1;
+    let replaced = format!("{:width$}", format!("{a} {b} {c} {d}"));
+    assert_eq!(replaced.len(), width);
+    let mut data = data.to_vec();
+    data[open + 1..close].copy_from_slice(replaced.as_bytes());
+    data
+}
+
+fn find(data: &[u8], needle: &[u8]) -> usize {
+    data.windows(needle.len())
+        .position(|window| window == needle)
+        .unwrap()
+}
+
+fn assert_error(data: Vec<u8>) {
+    let results = verify(&data);
+    assert!(
+        matches!(results[0].status, SignatureStatus::Error(_)),
+        "{:?}",
+        results[0].status
+    );
+}
+
+#[test]
+fn tampered_content_is_a_digest_mismatch() {
+    let mut data = signed_fixture();
+    let at = find(&data, b"Hello");
+    data[at] = b'J';
+    assert_eq!(verify(&data)[0].status, SignatureStatus::DigestMismatch);
+}
+
+#[test]
+fn byte_range_must_start_at_zero() {
+    let data = rewrite_byte_range(&signed_fixture(), |[_, len1, start2, len2]| {
+        [1, len1 - 1, start2, len2]
+    });
+    assert_error(data);
+}
+
+#[test]
+fn byte_range_gap_must_be_the_contents() {
+    let signed = signed_fixture();
+    // A gap wider than the hex string leaves bytes out of the signature
+    assert_error(rewrite_byte_range(
+        &signed,
+        |[start1, len1, start2, len2]| [start1, len1 - 8, start2, len2],
+    ));
+    // A gap inside the hex string signs part of the signature
+    assert_error(rewrite_byte_range(
+        &signed,
+        |[start1, len1, start2, len2]| [start1, len1 + 2, start2, len2],
+    ));
+    assert_error(rewrite_byte_range(
+        &signed,
+        |[start1, len1, start2, len2]| [start1, len1, start2 - 2, len2 + 2],
+    ));
+}
+
+#[test]
+fn byte_range_must_lie_in_the_file() {
+    let signed = signed_fixture();
+    assert_error(rewrite_byte_range(
+        &signed,
+        |[start1, len1, start2, len2]| [start1, len1, start2, len2 + 1],
+    ));
+}